@@ -1,6 +1,7 @@
 
 import('./pkg')
     .then(wasm => {
-        
+        // scene controls for the console, such as sandbox.node_indices()
+        window.sandbox = wasm;
     })
     .catch(console.error);
@@ -0,0 +1,22 @@
+// screen-space ambient occlusion darkening the ambient light in crevices
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmbientOcclusionSettings {
+    pub enabled: bool,
+    // radius of the sampled hemisphere as a fraction of the model's bounding box diagonal
+    pub radius: f32,
+    // exponent applied to the unoccluded fraction, 1.0 keeps it as it is
+    pub intensity: f32,
+    // hemisphere samples per pixel, from 1 to 64
+    pub sample_count: u32,
+}
+
+impl Default for AmbientOcclusionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            radius: 0.04,
+            intensity: 1.5,
+            sample_count: 16,
+        }
+    }
+}
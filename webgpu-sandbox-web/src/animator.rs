@@ -0,0 +1,177 @@
+
+use std::sync::Arc;
+
+use nalgebra_glm as glm;
+
+use crate::asset::{Animation, AnimationSampler, ChannelPath, Interpolation, Model};
+use crate::graph::SceneGraph;
+
+// plays one animation clip of the model at a time and writes the sampled values into
+// the scene graph.
+pub struct Animator {
+    clips: Vec<Arc<Animation>>,
+    clip_index: Option<usize>,
+    time: f32,
+    playing: bool,
+    looping: bool,
+}
+
+impl Animator {
+    pub fn new(model: &Model) -> Self {
+        let clips = model.animations().clone();
+        let clip_index = if clips.is_empty() { None } else { Some(0) };
+        Self {
+            clips,
+            clip_index,
+            time: 0.0,
+            playing: true,
+            looping: true,
+        }
+    }
+
+    pub fn clip_names(&self) -> Vec<String> {
+        self.clips.iter()
+            .map(|v| v.name()
+                .map(Into::into)
+                .unwrap_or_else(|| format!("animation {}", v.animation_index())))
+            .collect()
+    }
+
+    pub fn clip_index(&self) -> Option<usize> {
+        self.clip_index
+    }
+
+    // restarts from the beginning of the clip
+    pub fn select_clip(&mut self, clip_index: Option<usize>) {
+        self.clip_index = clip_index.filter(|&v| v < self.clips.len());
+        self.time = 0.0;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    // a clip held at its end starts over
+    pub fn set_playing(&mut self, playing: bool) {
+        let duration = self.clip_index
+            .and_then(|v| self.clips.get(v))
+            .map(|v| v.duration());
+        if playing && !self.playing && duration.is_some_and(|v| self.time >= v) {
+            self.time = 0.0;
+        }
+        self.playing = playing;
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn forward_frame(&mut self, delta_time: f32, scene_graph: &mut SceneGraph) {
+        let Some(clip) = self.clip_index.and_then(|v| self.clips.get(v)) else { return };
+        let clip = Arc::clone(clip);
+        if !self.playing {
+            return
+        }
+        let duration = clip.duration();
+        self.time += delta_time;
+        if duration <= 0.0 {
+            self.time = 0.0;
+        } else if self.looping {
+            self.time %= duration;
+        } else if self.time >= duration {
+            // holds the last pose
+            self.time = duration;
+            self.playing = false;
+        }
+        Self::apply(&clip, self.time, scene_graph);
+    }
+
+    fn apply(clip: &Animation, time: f32, scene_graph: &mut SceneGraph) {
+        for channel in clip.channels() {
+            let value = Self::sample(channel.sampler(), channel.path(), time);
+            let node_index = channel.node_index();
+            match channel.path() {
+                ChannelPath::Translation => {
+                    scene_graph.set_translation(node_index, glm::make_vec3(&value));
+                },
+                ChannelPath::Rotation => {
+                    let rotation = glm::quat(value[0], value[1], value[2], value[3]);
+                    scene_graph.set_rotation(node_index, glm::quat_normalize(&rotation));
+                },
+                ChannelPath::Scale => {
+                    scene_graph.set_scale(node_index, glm::make_vec3(&value));
+                },
+                ChannelPath::Weights => {
+                    scene_graph.set_weights(node_index, value);
+                },
+            }
+        }
+    }
+
+    fn sample(sampler: &AnimationSampler, path: ChannelPath, time: f32) -> Vec<f32> {
+        let inputs = sampler.inputs();
+        // keyframe at or before the time
+        let next = inputs.partition_point(|&v| v <= time);
+        if next == 0 {
+            return sampler.value(0).to_vec()
+        }
+        if next == inputs.len() {
+            return sampler.value(inputs.len() - 1).to_vec()
+        }
+        let previous = next - 1;
+        let delta = inputs[next] - inputs[previous];
+        let t = if delta > 0.0 { (time - inputs[previous]) / delta } else { 0.0 };
+        match sampler.interpolation() {
+            Interpolation::Step => {
+                sampler.value(previous).to_vec()
+            },
+            Interpolation::Linear => {
+                let from = sampler.value(previous);
+                let to = sampler.value(next);
+                if path == ChannelPath::Rotation {
+                    Self::slerp(from, to, t)
+                } else {
+                    from.iter().zip(to)
+                        .map(|(a, b)| a + (b - a) * t)
+                        .collect()
+                }
+            },
+            Interpolation::CubicSpline => {
+                // hermite spline with the tangents scaled by the keyframe delta
+                let t2 = t * t;
+                let t3 = t2 * t;
+                let p0 = sampler.value(previous);
+                let m0 = sampler.out_tangent(previous);
+                let p1 = sampler.value(next);
+                let m1 = sampler.in_tangent(next);
+                (0..sampler.components())
+                    .map(|i| {
+                        (2.0 * t3 - 3.0 * t2 + 1.0) * p0[i]
+                            + (t3 - 2.0 * t2 + t) * delta * m0[i]
+                            + (-2.0 * t3 + 3.0 * t2) * p1[i]
+                            + (t3 - t2) * delta * m1[i]
+                    })
+                    .collect()
+            },
+        }
+    }
+
+    // rotations along the shortest arc
+    fn slerp(from: &[f32], to: &[f32], t: f32) -> Vec<f32> {
+        let from = glm::quat(from[0], from[1], from[2], from[3]);
+        let mut to = glm::quat(to[0], to[1], to[2], to[3]);
+        if glm::quat_dot(&from, &to) < 0.0 {
+            to = -to;
+        }
+        let rotation = if glm::quat_dot(&from, &to) > 0.9995 {
+            glm::quat_normalize(&glm::quat_lerp(&from, &to, t))
+        } else {
+            glm::quat_slerp(&from, &to, t)
+        };
+        rotation.coords.as_slice().to_vec()
+    }
+}
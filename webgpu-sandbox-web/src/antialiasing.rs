@@ -0,0 +1,22 @@
+// multisampling or temporal antialiasing of the meshes and the skybox
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AntialiasingSettings {
+    // 1 renders straight to the HDR target, 4 resolves a multisampled target into it. other
+    // counts round down to one of them, formats without multisampling take 1
+    pub sample_count: u32,
+    // jitters the projection every frame and accumulates the frames instead of multisampling,
+    // which also smooths aliasing inside the shading
+    pub temporal: bool,
+    // share of the current frame blended into the history
+    pub temporal_blend: f32,
+}
+
+impl Default for AntialiasingSettings {
+    fn default() -> Self {
+        Self {
+            sample_count: 4,
+            temporal: false,
+            temporal_blend: 0.1,
+        }
+    }
+}
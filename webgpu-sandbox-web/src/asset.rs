@@ -12,40 +12,75 @@ pub struct Model {
     meshes: Vec<Arc<Mesh>>,
     mesh_map: HashMap<usize, Arc<Mesh>>,
     nodes: Vec<Arc<Node>>,
-    camera: Option<Camera>,
+    animations: Vec<Arc<Animation>>,
+    skins: Vec<Arc<Skin>>,
+    cameras: Vec<Arc<Camera>>,
+    lights: Vec<Arc<Light>>,
     bounding_box: Option<BoundingBox>,
 }
 
 impl Model {
-    pub async fn fetch(url: &str) -> Result<Option<Arc<Self>>, JsValue> {
+    pub async fn fetch(url: &str, normal_generation: NormalGeneration) -> Result<Option<Arc<Self>>, JsValue> {
         let data = fetch(url).await?;
-        Ok(Self::new(data))
+        Ok(Self::new(data, &normal_generation))
     }
 
-    fn new(data: Vec<u8>) -> Option<Arc<Self>> {
+    fn new(data: Vec<u8>, normal_generation: &NormalGeneration) -> Option<Arc<Self>> {
         // loading
         let result = gltf::import_slice(&data).ok()?;
-        let (document, buffers, _images) = result;
+        let (document, buffers, images) = result;
         // construct
-        let meshes = Self::make_meshes(&document, &buffers);
+        let images = Self::make_images(&images);
+        let textures = Self::make_textures(&document, &images);
+        let materials = Self::make_materials(&document, &textures);
+        let meshes = Self::make_meshes(&document, &buffers, &materials, normal_generation);
         let mesh_map = Self::make_mesh_map(&meshes);
         let nodes = Self::make_nodes(&document);
-        let camera = Camera::new(&document);
+        let animations = Self::make_animations(&document, &buffers);
+        let skins = Self::make_skins(&document, &buffers);
+        let cameras = Self::make_cameras(&document, &nodes);
+        let lights = Self::make_lights(&document, &nodes);
         // post-processing
         let bounding_box = Self::calculate_bounding_box(&mesh_map, &nodes);
         let this = Self {
             meshes,
             mesh_map,
             nodes,
-            camera,
+            animations,
+            skins,
+            cameras,
+            lights,
             bounding_box,
         };
         Some(Arc::new(this))
     }
 
-    fn make_meshes(document: &gltf::Document, buffers: &Vec<gltf::buffer::Data>) -> Vec<Arc<Mesh>> {
+    fn make_images(images: &[gltf::image::Data]) -> Vec<Arc<Image>> {
+        images.iter()
+            .map(Image::new)
+            .collect()
+    }
+
+    fn make_textures(document: &gltf::Document, images: &[Arc<Image>]) -> Vec<Arc<Texture>> {
+        document.textures()
+            .filter_map(|v| Texture::new(&v, images))
+            .collect()
+    }
+
+    fn make_materials(document: &gltf::Document, textures: &[Arc<Texture>]) -> Vec<Arc<Material>> {
+        document.materials()
+            .map(|v| Material::new(&v, textures))
+            .collect()
+    }
+
+    fn make_meshes(
+        document: &gltf::Document,
+        buffers: &Vec<gltf::buffer::Data>,
+        materials: &[Arc<Material>],
+        normal_generation: &NormalGeneration
+    ) -> Vec<Arc<Mesh>> {
         document.meshes()
-            .map(|v| Mesh::new(v, buffers))
+            .map(|v| Mesh::new(v, buffers, materials, normal_generation))
             .collect()
     }
 
@@ -54,6 +89,38 @@ impl Model {
         Node::flatten_nodes(scene.nodes().collect())
     }
 
+    fn make_animations(document: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Vec<Arc<Animation>> {
+        document.animations()
+            .map(|v| Animation::new(&v, buffers))
+            .collect()
+    }
+
+    fn make_skins(document: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Vec<Arc<Skin>> {
+        document.skins()
+            .map(|v| Skin::new(&v, buffers))
+            .collect()
+    }
+
+    // cameras instantiated by the nodes of the default scene
+    fn make_cameras(document: &gltf::Document, nodes: &[Arc<Node>]) -> Vec<Arc<Camera>> {
+        nodes.iter()
+            .filter_map(|v| {
+                let node = document.nodes().nth(v.node_index())?;
+                Some(Camera::new(&node.camera()?, v.node_index()))
+            })
+            .collect()
+    }
+
+    // KHR_lights_punctual lights instantiated by the nodes of the default scene
+    fn make_lights(document: &gltf::Document, nodes: &[Arc<Node>]) -> Vec<Arc<Light>> {
+        nodes.iter()
+            .filter_map(|v| {
+                let node = document.nodes().nth(v.node_index())?;
+                Some(Light::new(&node.light()?, v.node_index()))
+            })
+            .collect()
+    }
+
     fn make_mesh_map(meshes: &Vec<Arc<Mesh>>) -> HashMap<usize, Arc<Mesh>> {
         meshes.iter()
             .map(Arc::clone)
@@ -69,8 +136,20 @@ impl Model {
         &self.nodes
     }
 
-    pub fn camera(&self) -> Option<&Camera> {
-        self.camera.as_ref()
+    pub fn animations(&self) -> &Vec<Arc<Animation>> {
+        &self.animations
+    }
+
+    pub fn skins(&self) -> &Vec<Arc<Skin>> {
+        &self.skins
+    }
+
+    pub fn cameras(&self) -> &Vec<Arc<Camera>> {
+        &self.cameras
+    }
+
+    pub fn lights(&self) -> &Vec<Arc<Light>> {
+        &self.lights
     }
 
     fn calculate_bounding_box(
@@ -102,20 +181,53 @@ pub struct Mesh {
     positions: Vec<f32>,
     indices: Vec<u32>,
     normals: Vec<f32>,
+    tex_coords_0: Vec<f32>,
+    tex_coords_1: Vec<f32>,
+    tangents: Vec<f32>,
+    colors: Vec<f32>,
+    joints: Vec<u32>,
+    weights: Vec<f32>,
+    skinned: bool,
+    morph_target_count: usize,
+    morph_positions: Vec<f32>,
+    morph_normals: Vec<f32>,
+    default_weights: Vec<f32>,
+    primitives: Vec<Primitive>,
     bounding_box: BoundingBox,
 }
 
 impl Mesh {
-    fn new(mesh: gltf::Mesh, buffers: &Vec<gltf::buffer::Data>) -> Arc<Self> {
+    fn new(
+        mesh: gltf::Mesh,
+        buffers: &Vec<gltf::buffer::Data>,
+        materials: &[Arc<Material>],
+        normal_generation: &NormalGeneration
+    ) -> Arc<Self> {
         let mesh_index = mesh.index();
-        // flattens primitives
-        let mesh_accessors = MeshAccessor::flatten(&mesh, buffers);
-        let state = FlattenMeshState::new(mesh_accessors);
+        // packs primitives into shared buffers
+        let mesh_accessors = MeshAccessor::flatten(&mesh, buffers, materials, normal_generation);
+        let state = PackedMeshState::new(mesh_accessors);
+        let mut default_weights = mesh.weights()
+            .map(|v| v.to_vec())
+            .unwrap_or_default();
+        default_weights.resize(state.morph_target_count, 0.0);
         let this = Self {
             mesh_index,
             positions: state.positions,
             indices: state.indices,
             normals: state.normals,
+            tex_coords_0: state.tex_coords_0,
+            tex_coords_1: state.tex_coords_1,
+            tangents: state.tangents,
+            colors: state.colors,
+            joints: state.joints,
+            weights: state.weights,
+            skinned: state.skinned,
+            morph_target_count: state.morph_target_count,
+            morph_positions: state.morph_positions,
+            morph_normals: state.morph_normals,
+            default_weights,
+            primitives: state.primitives,
             bounding_box: state.bounding_box.unwrap(),
         };
         Arc::new(this)
@@ -133,6 +245,66 @@ impl Mesh {
         &self.normals
     }
 
+    pub fn tex_coords_0(&self) -> &Vec<f32> {
+        &self.tex_coords_0
+    }
+
+    pub fn tex_coords_1(&self) -> &Vec<f32> {
+        &self.tex_coords_1
+    }
+
+    // xyzw tangents, zero when the primitive has none
+    pub fn tangents(&self) -> &Vec<f32> {
+        &self.tangents
+    }
+
+    // RGBA vertex colors, white when the primitive has none
+    pub fn colors(&self) -> &Vec<f32> {
+        &self.colors
+    }
+
+    // four joint indices per vertex, zero when the primitive is not skinned
+    pub fn joints(&self) -> &Vec<u32> {
+        &self.joints
+    }
+
+    pub fn weights(&self) -> &Vec<f32> {
+        &self.weights
+    }
+
+    // positions deformed by the joint matrices of a node like the vertex shader does
+    pub fn skinned_positions(&self, joint_matrices: &[glm::Mat4]) -> Vec<f32> {
+        skin_positions(&self.positions, &self.joints, &self.weights, joint_matrices)
+    }
+
+    // whether any primitive carries JOINTS_0 and WEIGHTS_0
+    pub fn skinned(&self) -> bool {
+        self.skinned
+    }
+
+    pub fn morph_target_count(&self) -> usize {
+        self.morph_target_count
+    }
+
+    // position deltas laid out per vertex then per target
+    pub fn morph_positions(&self) -> &Vec<f32> {
+        &self.morph_positions
+    }
+
+    // normal deltas laid out per vertex then per target
+    pub fn morph_normals(&self) -> &Vec<f32> {
+        &self.morph_normals
+    }
+
+    // morph target weights unless the node overrides them
+    pub fn default_weights(&self) -> &Vec<f32> {
+        &self.default_weights
+    }
+
+    pub fn primitives(&self) -> &Vec<Primitive> {
+        &self.primitives
+    }
+
     pub fn mesh_index(&self) -> usize {
         self.mesh_index
     }
@@ -142,83 +314,275 @@ impl Mesh {
     }
 }
 
+// node of the default scene. the local TRS and parent links describe the hierarchy while
+// the transform holds the world transform at load time.
 pub struct Node {
     node_index: usize,
+    name: Option<String>,
+    parent_index: Option<usize>,
+    children_indices: Vec<usize>,
+    translation: glm::Vec3,
+    rotation: glm::Quat,
+    scale: glm::Vec3,
     transform: glm::Mat4,
     mesh_index: Option<usize>,
+    skin_index: Option<usize>,
+    weights: Option<Vec<f32>>,
 }
 
 impl Node {
+    // parents always precede their children
     pub fn flatten_nodes(nodes: Vec<gltf::Node>) -> Vec<Arc<Self>> {
         nodes.iter()
-            .map(|v| Self::flatten(v, &glm::identity()))
-            .flatten()
+            .flat_map(|v| Self::flatten(v, None, &glm::identity()))
             .collect()
     }
 
-    fn new(node: &gltf::Node, transform: &glm::Mat4) -> Option<Arc<Self>> {
+    fn new(node: &gltf::Node, parent_index: Option<usize>, transform: &glm::Mat4) -> Option<Arc<Self>> {
         let node_index = node.index();
         let mesh_index = node.mesh().map(|v| v.index());
+        let skin_index = node.skin().map(|v| v.index());
+        // falls back to the mesh default weights
+        let weights = node.weights()
+            .or_else(|| node.mesh().and_then(|v| v.weights()))
+            .map(|v| v.to_vec());
         // transform
-        let matrix = node.transform().matrix();
-        let local_transform: Vec<f32> = matrix.into_iter()
-            .flatten()
-            .collect();
-        let local_transform = glm::make_mat4(&local_transform);
+        let (translation, rotation, scale) = node.transform().decomposed();
+        let translation = glm::make_vec3(&translation);
+        let rotation = glm::quat(rotation[0], rotation[1], rotation[2], rotation[3]);
+        let scale = glm::make_vec3(&scale);
+        let local_transform = Self::compose(&translation, &rotation, &scale);
         let transform = transform * local_transform;
         let this = Self {
             node_index,
+            name: node.name().map(Into::into),
+            parent_index,
+            children_indices: node.children().map(|v| v.index()).collect(),
+            translation,
+            rotation,
+            scale,
             mesh_index,
+            skin_index,
+            weights,
             transform,
         };
         Some(Arc::new(this))
     }
 
-    fn flatten(node: &gltf::Node, transform: &glm::Mat4) -> Vec<Arc<Self>> {
-        let Some(parent) = Node::new(&node, transform) else { return vec![] };
+    fn flatten(node: &gltf::Node, parent_index: Option<usize>, transform: &glm::Mat4) -> Vec<Arc<Self>> {
+        let Some(parent) = Node::new(node, parent_index, transform) else { return vec![] };
         let parent_transform = &parent.transform;
         let children: Vec<Arc<Self>> = node.children()
-            .map(|v| Node::flatten(&v, parent_transform))
-            .flatten()
+            .flat_map(|v| Node::flatten(&v, Some(parent.node_index), parent_transform))
             .collect();
         std::iter::once(parent)
             .chain(children)
             .collect()
     }
 
+    pub fn compose(translation: &glm::Vec3, rotation: &glm::Quat, scale: &glm::Vec3) -> glm::Mat4 {
+        glm::translation(translation) * glm::quat_to_mat4(rotation) * glm::scaling(scale)
+    }
+
     pub fn mesh_index(&self) -> Option<usize> {
         self.mesh_index
     }
 
+    pub fn skin_index(&self) -> Option<usize> {
+        self.skin_index
+    }
+
+    // morph target weights of the node or its mesh
+    pub fn weights(&self) -> Option<&Vec<f32>> {
+        self.weights.as_ref()
+    }
+
     pub fn node_index(&self) -> usize {
         self.node_index
     }
 
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn parent_index(&self) -> Option<usize> {
+        self.parent_index
+    }
+
+    pub fn children_indices(&self) -> &Vec<usize> {
+        &self.children_indices
+    }
+
+    pub fn translation(&self) -> &glm::Vec3 {
+        &self.translation
+    }
+
+    pub fn rotation(&self) -> &glm::Quat {
+        &self.rotation
+    }
+
+    pub fn scale(&self) -> &glm::Vec3 {
+        &self.scale
+    }
+
     pub fn transform(&self) -> &glm::Mat4 {
         &self.transform
     }
 }
 
+// named after the WebGPU primitive topologies
+#[allow(clippy::enum_variant_names)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Topology {
+    PointList,
+    LineList,
+    TriangleList,
+}
+
+impl Topology {
+    // converts the indices of a glTF primitive mode into one of the list topologies
+    fn convert(mode: gltf::mesh::Mode, indices: &[u32]) -> (Self, Vec<u32>) {
+        use gltf::mesh::Mode;
+        let count = indices.len();
+        match mode {
+            Mode::Points => (Self::PointList, indices.to_vec()),
+            Mode::Lines => (Self::LineList, indices.to_vec()),
+            Mode::LineStrip => {
+                let indices = (1..count)
+                    .flat_map(|i| [indices[i - 1], indices[i]])
+                    .collect();
+                (Self::LineList, indices)
+            },
+            Mode::LineLoop => {
+                let indices = (0..count)
+                    .flat_map(|i| [indices[i], indices[(i + 1) % count]])
+                    .collect();
+                (Self::LineList, indices)
+            },
+            Mode::Triangles => (Self::TriangleList, indices.to_vec()),
+            // flips every other triangle to keep the winding order
+            Mode::TriangleStrip => {
+                let indices = (2..count)
+                    .flat_map(|i| if i % 2 == 0 {
+                        [indices[i - 2], indices[i - 1], indices[i]]
+                    } else {
+                        [indices[i - 1], indices[i - 2], indices[i]]
+                    })
+                    .collect();
+                (Self::TriangleList, indices)
+            },
+            Mode::TriangleFan => {
+                let indices = (2..count)
+                    .flat_map(|i| [indices[i - 1], indices[i], indices[0]])
+                    .collect();
+                (Self::TriangleList, indices)
+            },
+        }
+    }
+}
+
+// primitive drawn from a range of the index buffer shared within its mesh
+pub struct Primitive {
+    topology: Topology,
+    material: Arc<Material>,
+    first_index: u32,
+    index_count: u32,
+    bounding_box: BoundingBox,
+}
+
+impl Primitive {
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn material(&self) -> &Arc<Material> {
+        &self.material
+    }
+
+    // none for the default material
+    pub fn material_index(&self) -> Option<usize> {
+        self.material.material_index()
+    }
+
+    pub fn first_index(&self) -> u32 {
+        self.first_index
+    }
+
+    pub fn index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    pub fn bounding_box(&self) -> &BoundingBox {
+        &self.bounding_box
+    }
+}
+
 #[derive(Default)]
-struct FlattenMeshState {
+struct PackedMeshState {
     pub index_offset: u32,
     pub positions: Vec<f32>,
     pub indices: Vec<u32>,
     pub normals: Vec<f32>,
+    pub tex_coords_0: Vec<f32>,
+    pub tex_coords_1: Vec<f32>,
+    pub tangents: Vec<f32>,
+    pub colors: Vec<f32>,
+    pub joints: Vec<u32>,
+    pub weights: Vec<f32>,
+    pub skinned: bool,
+    pub morph_target_count: usize,
+    pub morph_positions: Vec<f32>,
+    pub morph_normals: Vec<f32>,
+    pub primitives: Vec<Primitive>,
     pub bounding_box: Option<BoundingBox>,
 }
 
-impl FlattenMeshState {
+impl PackedMeshState {
     fn new(meshes: Vec<MeshAccessor>) -> Self {
+        // primitives of a mesh share the number of morph targets, padded with zero deltas otherwise
+        let morph_target_count = meshes.iter()
+            .map(|v| v.morph_target_count())
+            .max()
+            .unwrap_or(0);
+        let initial_state = Self {
+            morph_target_count,
+            ..Default::default()
+        };
         let state: Self = meshes.into_iter()
-            .fold(Default::default(), |mut state, v| {
+            .fold(initial_state, |mut state, v| {
                 let index_offset = state.index_offset;
                 let indices: Vec<u32> = v.indices().iter()
                     .map(|&v| v + index_offset)
                     .collect();
+                state.primitives.push(Primitive {
+                    topology: v.topology(),
+                    material: Arc::clone(v.material()),
+                    first_index: state.indices.len() as u32,
+                    index_count: indices.len() as u32,
+                    bounding_box: v.bounding_box().clone(),
+                });
                 state.positions.extend_from_slice(v.positions());
                 state.indices.extend_from_slice(&indices);
                 state.normals.extend_from_slice(v.normals());
+                state.tex_coords_0.extend_from_slice(v.tex_coords_0());
+                state.tex_coords_1.extend_from_slice(v.tex_coords_1());
+                state.tangents.extend_from_slice(v.tangents());
+                state.colors.extend_from_slice(v.colors());
+                state.joints.extend_from_slice(v.joints());
+                state.weights.extend_from_slice(v.weights());
+                state.skinned |= v.skinned();
+                let stride = v.morph_target_count() * 3;
+                let pad = |deltas: &Vec<f32>| (0..v.positions().len() / 3)
+                    .flat_map(|vertex| {
+                        let mut chunk = deltas[vertex * stride..(vertex + 1) * stride].to_vec();
+                        chunk.resize(morph_target_count * 3, 0.0);
+                        chunk
+                    })
+                    .collect::<Vec<f32>>();
+                if morph_target_count > 0 {
+                    state.morph_positions.extend(pad(v.morph_positions()));
+                    state.morph_normals.extend(pad(v.morph_normals()));
+                }
                 state.index_offset += (v.positions().len() / 3) as u32;
                 state.bounding_box = if let Some(ref bounding_box) = state.bounding_box {
                     Some(BoundingBox::merge(v.bounding_box(), bounding_box))
@@ -233,48 +597,162 @@ impl FlattenMeshState {
 
 struct MeshAccessor {
     primitive_index: usize,
+    topology: Topology,
     positions: Vec<f32>,
     indices: Vec<u32>,
     normals: Vec<f32>,
+    tex_coords_0: Vec<f32>,
+    tex_coords_1: Vec<f32>,
+    tangents: Vec<f32>,
+    colors: Vec<f32>,
+    joints: Vec<u32>,
+    weights: Vec<f32>,
+    skinned: bool,
+    morph_target_count: usize,
+    morph_positions: Vec<f32>,
+    morph_normals: Vec<f32>,
+    material: Arc<Material>,
     bounding_box: BoundingBox,
 }
 
 impl MeshAccessor {
-    fn flatten(mesh: &gltf::Mesh, buffers: &Vec<gltf::buffer::Data>) -> Vec<Self> {
+    fn flatten(
+        mesh: &gltf::Mesh,
+        buffers: &Vec<gltf::buffer::Data>,
+        materials: &[Arc<Material>],
+        normal_generation: &NormalGeneration
+    ) -> Vec<Self> {
         let views: Vec<_> = mesh.primitives()
             .into_iter()
-            .filter_map(|v| MeshAccessor::new(v, buffers))
+            .filter_map(|v| MeshAccessor::new(v, buffers, materials, normal_generation))
             .collect();
         views
     }
 
     // TODO(ogukei): make this zero-copy by reading buffers directly from shader.
-    fn new(primitive: gltf::Primitive, buffers: &Vec<gltf::buffer::Data>) -> Option<Self> {
+    fn new(
+        primitive: gltf::Primitive,
+        buffers: &Vec<gltf::buffer::Data>,
+        materials: &[Arc<Material>],
+        normal_generation: &NormalGeneration
+    ) -> Option<Self> {
         let primitive_index = primitive.index();
+        // falls back to the default material when the primitive has none
+        let material = primitive.material().index()
+            .and_then(|v| materials.get(v))
+            .map(Arc::clone)
+            .unwrap_or_default();
         let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
-        let positions: Vec<f32> = reader.read_positions()?
+        let mut positions: Vec<f32> = reader.read_positions()?
             .flatten()    
             .collect();
-        let indices: Vec<u32> = reader.read_indices()?
-            .into_u32()   
-            .collect();
-        let normals: Vec<f32> = reader.read_normals()?
-            .flatten()
+        let vertex_count = positions.len() / 3;
+        // non-indexed primitives draw their vertices in order
+        let indices: Vec<u32> = reader.read_indices()
+            .map(|v| v.into_u32().collect())
+            .unwrap_or_else(|| (0..vertex_count as u32).collect());
+        // strips, fans and loops are converted into lists
+        let (topology, indices) = Topology::convert(primitive.mode(), &indices);
+        let normals: Option<Vec<f32>> = reader.read_normals()
+            .map(|v| v.flatten().collect());
+        let mut tex_coords_0: Vec<f32> = reader.read_tex_coords(0)
+            .map(|v| v.into_f32().flatten().collect())
+            .unwrap_or_else(|| vec![0.0; vertex_count * 2]);
+        let mut tex_coords_1: Vec<f32> = reader.read_tex_coords(1)
+            .map(|v| v.into_f32().flatten().collect())
+            .unwrap_or_else(|| vec![0.0; vertex_count * 2]);
+        // zero tangents let the shader derive a tangent frame from screen-space derivatives
+        let mut tangents: Vec<f32> = reader.read_tangents()
+            .map(|v| v.flatten().collect())
+            .unwrap_or_else(|| vec![0.0; vertex_count * 4]);
+        let mut colors: Vec<f32> = reader.read_colors(0)
+            .map(|v| v.into_rgba_f32().flatten().collect())
+            .unwrap_or_else(|| vec![1.0; vertex_count * 4]);
+        // skinning attributes. vertices whose weights sum to zero, including those of primitives
+        // packed with skinned ones, keep their bind pose when deformed.
+        let skinned = reader.read_joints(0).is_some() && reader.read_weights(0).is_some();
+        let mut joints: Vec<u32> = reader.read_joints(0)
+            .filter(|_| skinned)
+            .map(|v| v.into_u16().flatten().map(u32::from).collect())
+            .unwrap_or_else(|| vec![0; vertex_count * 4]);
+        let mut weights: Vec<f32> = reader.read_weights(0)
+            .filter(|_| skinned)
+            .map(|v| v.into_f32().flatten().collect())
+            .unwrap_or_else(|| vec![0.0; vertex_count * 4]);
+        // morph target deltas, one array per target
+        let mut morph_targets: Vec<(Vec<f32>, Vec<f32>)> = reader.read_morph_targets()
+            .map(|(positions, normals, _)| {
+                let positions: Vec<f32> = positions
+                    .map(|v| v.flatten().collect())
+                    .unwrap_or_else(|| vec![0.0; vertex_count * 3]);
+                let normals: Vec<f32> = normals
+                    .map(|v| v.flatten().collect())
+                    .unwrap_or_else(|| vec![0.0; vertex_count * 3]);
+                (positions, normals)
+            })
             .collect();
+        // generates normals for geometry that ships without them, splitting vertices along creases
+        let (indices, normals) = match normals {
+            Some(normals) => (indices, normals),
+            // points and lines have no surface to derive normals from
+            None if topology != Topology::TriangleList => (indices, vec![0.0; vertex_count * 3]),
+            None => {
+                let generated = normal_generation.generate(&positions, &indices);
+                positions = generated.remap(&positions, 3);
+                tex_coords_0 = generated.remap(&tex_coords_0, 2);
+                tex_coords_1 = generated.remap(&tex_coords_1, 2);
+                tangents = generated.remap(&tangents, 4);
+                colors = generated.remap(&colors, 4);
+                joints = generated.remap(&joints, 4);
+                weights = generated.remap(&weights, 4);
+                morph_targets = morph_targets.iter()
+                    .map(|(positions, normals)| (generated.remap(positions, 3), generated.remap(normals, 3)))
+                    .collect();
+                (generated.indices, generated.normals)
+            },
+        };
+        let morph_target_count = morph_targets.len();
+        let morph_positions = Self::interleave_morph_targets(morph_targets.iter().map(|v| &v.0).collect());
+        let morph_normals = Self::interleave_morph_targets(morph_targets.iter().map(|v| &v.1).collect());
         let bounding_box = primitive.bounding_box();
         let bounding_box_max = glm::make_vec3(&bounding_box.max);
         let bounding_box_min = glm::make_vec3(&bounding_box.min);
         let bounding_box = BoundingBox::new(bounding_box_min, bounding_box_max);
         let this = Self {
             primitive_index,
+            topology,
             positions,
             indices,
             normals,
+            tex_coords_0,
+            tex_coords_1,
+            tangents,
+            colors,
+            joints,
+            weights,
+            skinned,
+            morph_target_count,
+            morph_positions,
+            morph_normals,
+            material,
             bounding_box,
         };
         Some(this)
     }
 
+    // lays out the deltas per vertex then per target
+    fn interleave_morph_targets(targets: Vec<&Vec<f32>>) -> Vec<f32> {
+        let Some(first) = targets.first() else { return vec![] };
+        (0..first.len() / 3)
+            .flat_map(|vertex| targets.iter()
+                .flat_map(move |v| v[vertex * 3..vertex * 3 + 3].iter().copied()))
+            .collect()
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
     pub fn positions(&self) -> &Vec<f32> {
         &self.positions
     }
@@ -287,30 +765,909 @@ impl MeshAccessor {
         &self.normals
     }
 
+    pub fn tex_coords_0(&self) -> &Vec<f32> {
+        &self.tex_coords_0
+    }
+
+    pub fn tex_coords_1(&self) -> &Vec<f32> {
+        &self.tex_coords_1
+    }
+
+    pub fn tangents(&self) -> &Vec<f32> {
+        &self.tangents
+    }
+
+    pub fn colors(&self) -> &Vec<f32> {
+        &self.colors
+    }
+
+    pub fn joints(&self) -> &Vec<u32> {
+        &self.joints
+    }
+
+    pub fn weights(&self) -> &Vec<f32> {
+        &self.weights
+    }
+
+    pub fn skinned(&self) -> bool {
+        self.skinned
+    }
+
+    pub fn morph_target_count(&self) -> usize {
+        self.morph_target_count
+    }
+
+    pub fn morph_positions(&self) -> &Vec<f32> {
+        &self.morph_positions
+    }
+
+    pub fn morph_normals(&self) -> &Vec<f32> {
+        &self.morph_normals
+    }
+
+    pub fn material(&self) -> &Arc<Material> {
+        &self.material
+    }
+
     pub fn bounding_box(&self) -> &BoundingBox {
         &self.bounding_box
     }
 }
 
+// generates normals of triangles shipped without them. faces meeting at a shared position
+// are smoothed together unless the angle between them exceeds the crease angle.
+#[derive(Copy, Clone)]
+pub struct NormalGeneration {
+    crease_angle: f32,
+}
+
+impl NormalGeneration {
+    pub fn smooth() -> Self {
+        Self::with_crease_angle(std::f32::consts::PI)
+    }
+
+    pub fn flat() -> Self {
+        Self::with_crease_angle(0.0)
+    }
+
+    // crease angle in radians
+    pub fn with_crease_angle(crease_angle: f32) -> Self {
+        Self {
+            crease_angle,
+        }
+    }
+
+    fn generate(&self, positions: &[f32], indices: &[u32]) -> GeneratedNormals {
+        let position = |index: u32| {
+            let offset = index as usize * 3;
+            glm::make_vec3(&positions[offset..offset + 3])
+        };
+        // face normals and corner weights
+        let faces: Vec<[u32; 3]> = indices.chunks_exact(3)
+            .map(|v| [v[0], v[1], v[2]])
+            .collect();
+        let face_normals: Vec<glm::Vec3> = faces.iter()
+            .map(|v| {
+                let normal = glm::cross(&(position(v[1]) - position(v[0])), &(position(v[2]) - position(v[0])));
+                let length = glm::length(&normal);
+                if length > 0.0 { normal / length } else { glm::zero() }
+            })
+            .collect();
+        // weighted by both the corner angle and the face area
+        let corner_weights: Vec<[f32; 3]> = faces.iter()
+            .map(|v| {
+                let p = [position(v[0]), position(v[1]), position(v[2])];
+                let area = glm::length(&glm::cross(&(p[1] - p[0]), &(p[2] - p[0]))) * 0.5;
+                let angle = |i: usize| {
+                    let a = p[(i + 1) % 3] - p[i];
+                    let b = p[(i + 2) % 3] - p[i];
+                    let denominator = glm::length(&a) * glm::length(&b);
+                    if denominator > 0.0 { (glm::dot(&a, &b) / denominator).clamp(-1.0, 1.0).acos() } else { 0.0 }
+                };
+                [angle(0) * area, angle(1) * area, angle(2) * area]
+            })
+            .collect();
+        // welds corners sharing a position, as vertices are often split along UV seams
+        let mut position_groups: HashMap<[u32; 3], Vec<(usize, usize)>> = HashMap::new();
+        for (face_index, face) in faces.iter().enumerate() {
+            for (corner_index, &vertex_index) in face.iter().enumerate() {
+                let offset = vertex_index as usize * 3;
+                let key = [positions[offset].to_bits(), positions[offset + 1].to_bits(), positions[offset + 2].to_bits()];
+                position_groups.entry(key)
+                    .or_default()
+                    .push((face_index, corner_index));
+            }
+        }
+        let crease_cosine = self.crease_angle.cos();
+        let mut vertex_lookup: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+        let mut generated = GeneratedNormals::default();
+        for (face_index, face) in faces.iter().enumerate() {
+            let face_normal = &face_normals[face_index];
+            for &vertex_index in face.iter() {
+                let offset = vertex_index as usize * 3;
+                let key = [positions[offset].to_bits(), positions[offset + 1].to_bits(), positions[offset + 2].to_bits()];
+                let normal: glm::Vec3 = position_groups[&key].iter()
+                    .filter(|&&(other_index, _)| {
+                        other_index == face_index || glm::dot(face_normal, &face_normals[other_index]) >= crease_cosine
+                    })
+                    .map(|&(other_index, corner_index)| face_normals[other_index] * corner_weights[other_index][corner_index])
+                    .fold(glm::zero(), |sum, v| sum + v);
+                let length = glm::length(&normal);
+                let normal = if length > 0.0 { normal / length } else { *face_normal };
+                // corners of a vertex ending up with the same normal share a single vertex
+                let normal_key = [normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits()];
+                let next_index = generated.vertex_map.len() as u32;
+                let index = *vertex_lookup.entry((vertex_index, normal_key))
+                    .or_insert_with(|| {
+                        generated.vertex_map.push(vertex_index);
+                        generated.normals.extend_from_slice(normal.as_slice());
+                        next_index
+                    });
+                generated.indices.push(index);
+            }
+        }
+        generated
+    }
+}
+
+impl Default for NormalGeneration {
+    fn default() -> Self {
+        Self::with_crease_angle(60f32.to_radians())
+    }
+}
+
+// linear blend skinning of xyz positions with four joints per vertex. vertices whose weights
+// sum to zero and joints out of range keep the bind pose.
+fn skin_positions(positions: &[f32], joints: &[u32], weights: &[f32], joint_matrices: &[glm::Mat4]) -> Vec<f32> {
+    positions.chunks_exact(3)
+        .zip(joints.chunks_exact(4).zip(weights.chunks_exact(4)))
+        .flat_map(|(position, (joints, weights))| {
+            let p = glm::vec4(position[0], position[1], position[2], 1.0);
+            let skin = joints.iter().zip(weights)
+                .filter_map(|(&joint, &weight)| Some(joint_matrices.get(joint as usize)? * weight))
+                .reduce(|sum, v| sum + v);
+            let weight_sum: f32 = weights.iter().sum();
+            let p = match skin {
+                Some(skin) if weight_sum > 0.0 => skin * p,
+                _ => p,
+            };
+            [p.x, p.y, p.z]
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct GeneratedNormals {
+    indices: Vec<u32>,
+    normals: Vec<f32>,
+    // original vertex index of each generated vertex
+    vertex_map: Vec<u32>,
+}
+
+impl GeneratedNormals {
+    fn remap<T: Copy>(&self, values: &[T], component_count: usize) -> Vec<T> {
+        self.vertex_map.iter()
+            .flat_map(|&v| {
+                let offset = v as usize * component_count;
+                values[offset..offset + component_count].iter().copied()
+            })
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum AlphaMode {
+    Opaque,
+    Mask,
+    Blend,
+}
+
+// glTF PBR metallic-roughness material
+pub struct Material {
+    material_index: Option<usize>,
+    base_color_factor: glm::Vec4,
+    metallic_factor: f32,
+    roughness_factor: f32,
+    emissive_factor: glm::Vec3,
+    alpha_mode: AlphaMode,
+    alpha_cutoff: f32,
+    double_sided: bool,
+    base_color_texture: Option<TextureReference>,
+    metallic_roughness_texture: Option<TextureReference>,
+    normal_texture: Option<TextureReference>,
+    normal_scale: f32,
+    occlusion_texture: Option<TextureReference>,
+    occlusion_strength: f32,
+    emissive_texture: Option<TextureReference>,
+}
+
+impl Material {
+    fn new(material: &gltf::Material, textures: &[Arc<Texture>]) -> Arc<Self> {
+        let pbr = material.pbr_metallic_roughness();
+        let normal_texture = material.normal_texture();
+        let occlusion_texture = material.occlusion_texture();
+        let alpha_mode = match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        };
+        let this = Self {
+            material_index: material.index(),
+            base_color_factor: glm::make_vec4(&pbr.base_color_factor()),
+            metallic_factor: pbr.metallic_factor(),
+            roughness_factor: pbr.roughness_factor(),
+            emissive_factor: glm::make_vec3(&material.emissive_factor()),
+            alpha_mode,
+            alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+            double_sided: material.double_sided(),
+            base_color_texture: pbr.base_color_texture()
+                .and_then(|v| TextureReference::new(&v.texture(), v.tex_coord(), textures)),
+            metallic_roughness_texture: pbr.metallic_roughness_texture()
+                .and_then(|v| TextureReference::new(&v.texture(), v.tex_coord(), textures)),
+            normal_texture: normal_texture.as_ref()
+                .and_then(|v| TextureReference::new(&v.texture(), v.tex_coord(), textures)),
+            normal_scale: normal_texture.as_ref()
+                .map(|v| v.scale())
+                .unwrap_or(1.0),
+            occlusion_texture: occlusion_texture.as_ref()
+                .and_then(|v| TextureReference::new(&v.texture(), v.tex_coord(), textures)),
+            occlusion_strength: occlusion_texture.as_ref()
+                .map(|v| v.strength())
+                .unwrap_or(1.0),
+            emissive_texture: material.emissive_texture()
+                .and_then(|v| TextureReference::new(&v.texture(), v.tex_coord(), textures)),
+        };
+        Arc::new(this)
+    }
+
+    pub fn material_index(&self) -> Option<usize> {
+        self.material_index
+    }
+
+    pub fn base_color_factor(&self) -> &glm::Vec4 {
+        &self.base_color_factor
+    }
+
+    pub fn metallic_factor(&self) -> f32 {
+        self.metallic_factor
+    }
+
+    pub fn roughness_factor(&self) -> f32 {
+        self.roughness_factor
+    }
+
+    pub fn emissive_factor(&self) -> &glm::Vec3 {
+        &self.emissive_factor
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    pub fn alpha_cutoff(&self) -> f32 {
+        self.alpha_cutoff
+    }
+
+    pub fn double_sided(&self) -> bool {
+        self.double_sided
+    }
+
+    pub fn base_color_texture(&self) -> Option<&TextureReference> {
+        self.base_color_texture.as_ref()
+    }
+
+    pub fn metallic_roughness_texture(&self) -> Option<&TextureReference> {
+        self.metallic_roughness_texture.as_ref()
+    }
+
+    pub fn normal_texture(&self) -> Option<&TextureReference> {
+        self.normal_texture.as_ref()
+    }
+
+    pub fn normal_scale(&self) -> f32 {
+        self.normal_scale
+    }
+
+    pub fn occlusion_texture(&self) -> Option<&TextureReference> {
+        self.occlusion_texture.as_ref()
+    }
+
+    pub fn occlusion_strength(&self) -> f32 {
+        self.occlusion_strength
+    }
+
+    pub fn emissive_texture(&self) -> Option<&TextureReference> {
+        self.emissive_texture.as_ref()
+    }
+}
+
+// default material defined by the glTF specification
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            material_index: None,
+            base_color_factor: glm::vec4(1.0, 1.0, 1.0, 1.0),
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            emissive_factor: glm::vec3(0.0, 0.0, 0.0),
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+            base_color_texture: None,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+            occlusion_texture: None,
+            occlusion_strength: 1.0,
+            emissive_texture: None,
+        }
+    }
+}
+
+// texture slot of a material with the texture coordinate set to sample with
+#[derive(Clone)]
+pub struct TextureReference {
+    texture: Arc<Texture>,
+    tex_coord: u32,
+}
+
+impl TextureReference {
+    fn new(texture: &gltf::Texture, tex_coord: u32, textures: &[Arc<Texture>]) -> Option<Self> {
+        let texture = textures.iter()
+            .find(|v| v.texture_index() == texture.index())?;
+        let this = Self {
+            texture: Arc::clone(texture),
+            tex_coord,
+        };
+        Some(this)
+    }
+
+    pub fn texture(&self) -> &Arc<Texture> {
+        &self.texture
+    }
+
+    pub fn tex_coord(&self) -> u32 {
+        self.tex_coord
+    }
+}
+
+pub struct Texture {
+    texture_index: usize,
+    image: Arc<Image>,
+    sampler: Sampler,
+}
+
+impl Texture {
+    fn new(texture: &gltf::Texture, images: &[Arc<Image>]) -> Option<Arc<Self>> {
+        let image = images.get(texture.source().index())?;
+        let this = Self {
+            texture_index: texture.index(),
+            image: Arc::clone(image),
+            sampler: Sampler::new(&texture.sampler()),
+        };
+        Some(Arc::new(this))
+    }
+
+    pub fn texture_index(&self) -> usize {
+        self.texture_index
+    }
+
+    pub fn image(&self) -> &Arc<Image> {
+        &self.image
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum WrapMode {
+    ClampToEdge,
+    MirroredRepeat,
+    Repeat,
+}
+
+#[derive(Copy, Clone)]
+pub struct Sampler {
+    mag_filter: Filter,
+    min_filter: Filter,
+    mipmap_filter: Filter,
+    wrap_s: WrapMode,
+    wrap_t: WrapMode,
+}
+
+impl Sampler {
+    fn new(sampler: &gltf::texture::Sampler) -> Self {
+        use gltf::texture::{MagFilter, MinFilter, WrappingMode};
+        let mag_filter = match sampler.mag_filter() {
+            Some(MagFilter::Nearest) => Filter::Nearest,
+            Some(MagFilter::Linear) | None => Filter::Linear,
+        };
+        let (min_filter, mipmap_filter) = match sampler.min_filter() {
+            Some(MinFilter::Nearest) => (Filter::Nearest, Filter::Nearest),
+            Some(MinFilter::Linear) => (Filter::Linear, Filter::Nearest),
+            Some(MinFilter::NearestMipmapNearest) => (Filter::Nearest, Filter::Nearest),
+            Some(MinFilter::LinearMipmapNearest) => (Filter::Linear, Filter::Nearest),
+            Some(MinFilter::NearestMipmapLinear) => (Filter::Nearest, Filter::Linear),
+            Some(MinFilter::LinearMipmapLinear) | None => (Filter::Linear, Filter::Linear),
+        };
+        let wrap_mode = |v: WrappingMode| match v {
+            WrappingMode::ClampToEdge => WrapMode::ClampToEdge,
+            WrappingMode::MirroredRepeat => WrapMode::MirroredRepeat,
+            WrappingMode::Repeat => WrapMode::Repeat,
+        };
+        Self {
+            mag_filter,
+            min_filter,
+            mipmap_filter,
+            wrap_s: wrap_mode(sampler.wrap_s()),
+            wrap_t: wrap_mode(sampler.wrap_t()),
+        }
+    }
+
+    pub fn mag_filter(&self) -> Filter {
+        self.mag_filter
+    }
+
+    pub fn min_filter(&self) -> Filter {
+        self.min_filter
+    }
+
+    pub fn mipmap_filter(&self) -> Filter {
+        self.mipmap_filter
+    }
+
+    pub fn wrap_s(&self) -> WrapMode {
+        self.wrap_s
+    }
+
+    pub fn wrap_t(&self) -> WrapMode {
+        self.wrap_t
+    }
+}
+
+// sampler defined by the glTF specification when a texture has none
+impl Default for Sampler {
+    fn default() -> Self {
+        Self {
+            mag_filter: Filter::Linear,
+            min_filter: Filter::Linear,
+            mipmap_filter: Filter::Linear,
+            wrap_s: WrapMode::Repeat,
+            wrap_t: WrapMode::Repeat,
+        }
+    }
+}
+
+// decoded image expanded to RGBA8
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new(data: &gltf::image::Data) -> Arc<Self> {
+        use gltf::image::Format;
+        let pixels = &data.pixels;
+        let pixels: Vec<u8> = match data.format {
+            Format::R8 => pixels.iter()
+                .flat_map(|&v| [v, 0, 0, 255])
+                .collect(),
+            Format::R8G8 => pixels.chunks_exact(2)
+                .flat_map(|v| [v[0], v[1], 0, 255])
+                .collect(),
+            Format::R8G8B8 => pixels.chunks_exact(3)
+                .flat_map(|v| [v[0], v[1], v[2], 255])
+                .collect(),
+            Format::R8G8B8A8 => pixels.clone(),
+            // 16-bit channels keep their most significant byte (little endian)
+            Format::R16 => pixels.chunks_exact(2)
+                .flat_map(|v| [v[1], 0, 0, 255])
+                .collect(),
+            Format::R16G16 => pixels.chunks_exact(4)
+                .flat_map(|v| [v[1], v[3], 0, 255])
+                .collect(),
+            Format::R16G16B16 => pixels.chunks_exact(6)
+                .flat_map(|v| [v[1], v[3], v[5], 255])
+                .collect(),
+            Format::R16G16B16A16 => pixels.chunks_exact(8)
+                .flat_map(|v| [v[1], v[3], v[5], v[7]])
+                .collect(),
+            Format::R32G32B32FLOAT => pixels.chunks_exact(12)
+                .flat_map(|v| {
+                    let v: Vec<f32> = v.chunks_exact(4)
+                        .map(|v| f32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+                        .collect();
+                    [Self::quantize(v[0]), Self::quantize(v[1]), Self::quantize(v[2]), 255]
+                })
+                .collect(),
+            Format::R32G32B32A32FLOAT => pixels.chunks_exact(16)
+                .flat_map(|v| {
+                    let v: Vec<f32> = v.chunks_exact(4)
+                        .map(|v| f32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+                        .collect();
+                    [Self::quantize(v[0]), Self::quantize(v[1]), Self::quantize(v[2]), Self::quantize(v[3])]
+                })
+                .collect(),
+        };
+        let this = Self {
+            width: data.width,
+            height: data.height,
+            pixels,
+        };
+        Arc::new(this)
+    }
+
+    fn quantize(value: f32) -> u8 {
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // RGBA8 pixels in row-major order
+    pub fn pixels(&self) -> &Vec<u8> {
+        &self.pixels
+    }
+}
+
+// joints deforming the skinned meshes. the inverse bind matrices bring vertices into the
+// space of each joint.
+pub struct Skin {
+    joint_indices: Vec<usize>,
+    inverse_bind_matrices: Vec<glm::Mat4>,
+}
+
+impl Skin {
+    fn new(skin: &gltf::Skin, buffers: &[gltf::buffer::Data]) -> Arc<Self> {
+        let joint_indices: Vec<usize> = skin.joints()
+            .map(|v| v.index())
+            .collect();
+        let reader = skin.reader(|v| Some(&buffers[v.index()]));
+        // identity matrices when the skin omits them
+        let inverse_bind_matrices: Vec<glm::Mat4> = reader.read_inverse_bind_matrices()
+            .map(|v| v.map(|m| glm::make_mat4(m.as_flattened())).collect())
+            .unwrap_or_else(|| vec![glm::identity(); joint_indices.len()]);
+        let this = Self {
+            joint_indices,
+            inverse_bind_matrices,
+        };
+        Arc::new(this)
+    }
+
+    pub fn joint_indices(&self) -> &Vec<usize> {
+        &self.joint_indices
+    }
+
+    pub fn inverse_bind_matrices(&self) -> &Vec<glm::Mat4> {
+        &self.inverse_bind_matrices
+    }
+}
+
+// animation clip. each channel targets one property of a node through its own sampler.
+pub struct Animation {
+    animation_index: usize,
+    name: Option<String>,
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl Animation {
+    fn new(animation: &gltf::Animation, buffers: &[gltf::buffer::Data]) -> Arc<Self> {
+        let channels: Vec<Channel> = animation.channels()
+            .filter_map(|v| Channel::new(&v, buffers))
+            .collect();
+        let duration = channels.iter()
+            .filter_map(|v| v.sampler().inputs().last())
+            .fold(0.0, |state: f32, &v| state.max(v));
+        let this = Self {
+            animation_index: animation.index(),
+            name: animation.name().map(Into::into),
+            channels,
+            duration,
+        };
+        Arc::new(this)
+    }
+
+    pub fn animation_index(&self) -> usize {
+        self.animation_index
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn channels(&self) -> &Vec<Channel> {
+        &self.channels
+    }
+
+    // the last keyframe time among the channels
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ChannelPath {
+    Translation,
+    Rotation,
+    Scale,
+    Weights,
+}
+
+pub struct Channel {
+    node_index: usize,
+    path: ChannelPath,
+    sampler: AnimationSampler,
+}
+
+impl Channel {
+    fn new(channel: &gltf::animation::Channel, buffers: &[gltf::buffer::Data]) -> Option<Self> {
+        use gltf::animation::util::ReadOutputs;
+        let reader = channel.reader(|v| Some(&buffers[v.index()]));
+        let inputs: Vec<f32> = reader.read_inputs()?.collect();
+        let (path, outputs): (ChannelPath, Vec<f32>) = match reader.read_outputs()? {
+            ReadOutputs::Translations(v) => (ChannelPath::Translation, v.flatten().collect()),
+            ReadOutputs::Rotations(v) => (ChannelPath::Rotation, v.into_f32().flatten().collect()),
+            ReadOutputs::Scales(v) => (ChannelPath::Scale, v.flatten().collect()),
+            ReadOutputs::MorphTargetWeights(v) => (ChannelPath::Weights, v.into_f32().collect()),
+        };
+        let interpolation = match channel.sampler().interpolation() {
+            gltf::animation::Interpolation::Step => Interpolation::Step,
+            gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        };
+        let sampler = AnimationSampler::new(interpolation, inputs, outputs)?;
+        let this = Self {
+            node_index: channel.target().node().index(),
+            path,
+            sampler,
+        };
+        Some(this)
+    }
+
+    pub fn node_index(&self) -> usize {
+        self.node_index
+    }
+
+    pub fn path(&self) -> ChannelPath {
+        self.path
+    }
+
+    pub fn sampler(&self) -> &AnimationSampler {
+        &self.sampler
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    CubicSpline,
+}
+
+// keyframes of a channel. cubic spline outputs hold in-tangent, value and out-tangent
+// for every keyframe.
+pub struct AnimationSampler {
+    interpolation: Interpolation,
+    inputs: Vec<f32>,
+    outputs: Vec<f32>,
+    components: usize,
+}
+
+impl AnimationSampler {
+    fn new(interpolation: Interpolation, inputs: Vec<f32>, outputs: Vec<f32>) -> Option<Self> {
+        let elements = match interpolation {
+            Interpolation::CubicSpline => inputs.len() * 3,
+            _ => inputs.len(),
+        };
+        if elements == 0 || !outputs.len().is_multiple_of(elements) {
+            console_log!("invalid animation sampler");
+            return None
+        }
+        let this = Self {
+            interpolation,
+            components: outputs.len() / elements,
+            inputs,
+            outputs,
+        };
+        Some(this)
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    pub fn inputs(&self) -> &Vec<f32> {
+        &self.inputs
+    }
+
+    // number of floats per value
+    pub fn components(&self) -> usize {
+        self.components
+    }
+
+    // value of the keyframe, skipping the tangents of cubic splines
+    pub fn value(&self, keyframe: usize) -> &[f32] {
+        self.element(keyframe, 1)
+    }
+
+    pub fn in_tangent(&self, keyframe: usize) -> &[f32] {
+        self.element(keyframe, 0)
+    }
+
+    pub fn out_tangent(&self, keyframe: usize) -> &[f32] {
+        self.element(keyframe, 2)
+    }
+
+    fn element(&self, keyframe: usize, offset: usize) -> &[f32] {
+        let index = match self.interpolation {
+            Interpolation::CubicSpline => keyframe * 3 + offset,
+            _ => keyframe,
+        };
+        &self.outputs[index * self.components..(index + 1) * self.components]
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum Projection {
+    Perspective {
+        yfov: f32,
+        // the viewport aspect ratio applies when absent
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        // infinite projection when absent
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+// camera instantiated by a node. it looks down the -Z axis of the node.
 pub struct Camera {
-    position: glm::Vec3,
+    camera_index: usize,
+    node_index: usize,
+    name: Option<String>,
+    projection: Projection,
 }
 
 impl Camera {
-    pub fn new(document: &gltf::Document) -> Option<Self> {
-        let camera_node = document.nodes()
-            .find_map(|v| v.camera().and(Some(v)));
-        let camera_node = camera_node?;
-        let (position, _, _) = camera_node.transform().decomposed();
-        let position = glm::make_vec3(&position);
+    fn new(camera: &gltf::Camera, node_index: usize) -> Arc<Self> {
+        let projection = match camera.projection() {
+            camera::Projection::Perspective(v) => Projection::Perspective {
+                yfov: v.yfov(),
+                aspect_ratio: v.aspect_ratio(),
+                znear: v.znear(),
+                zfar: v.zfar(),
+            },
+            camera::Projection::Orthographic(v) => Projection::Orthographic {
+                xmag: v.xmag(),
+                ymag: v.ymag(),
+                znear: v.znear(),
+                zfar: v.zfar(),
+            },
+        };
+        let this = Self {
+            camera_index: camera.index(),
+            node_index,
+            name: camera.name().map(Into::into),
+            projection,
+        };
+        Arc::new(this)
+    }
+
+    pub fn camera_index(&self) -> usize {
+        self.camera_index
+    }
+
+    pub fn node_index(&self) -> usize {
+        self.node_index
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // depth range of zero to one as WebGPU expects
+    pub fn projection_matrix(&self, viewport_aspect_ratio: f32) -> glm::Mat4 {
+        match self.projection {
+            Projection::Perspective { yfov, aspect_ratio, znear, zfar } => {
+                let aspect_ratio = aspect_ratio.unwrap_or(viewport_aspect_ratio);
+                match zfar {
+                    Some(zfar) => glm::perspective_rh_zo(aspect_ratio, yfov, znear, zfar),
+                    None => glm::infinite_perspective_rh_zo(aspect_ratio, yfov, znear),
+                }
+            },
+            Projection::Orthographic { xmag, ymag, znear, zfar } => {
+                glm::ortho_rh_zo(-xmag, xmag, -ymag, ymag, znear, zfar)
+            },
+        }
+    }
+
+    // inverse of the camera node world transform
+    pub fn view_matrix(&self, world_transform: &glm::Mat4) -> glm::Mat4 {
+        world_transform.try_inverse().unwrap_or_else(glm::identity)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+// punctual light instantiated by a node. directional and spot lights point down the -Z axis
+// of the node.
+pub struct Light {
+    node_index: usize,
+    kind: LightKind,
+    color: glm::Vec3,
+    intensity: f32,
+    range: Option<f32>,
+}
+
+impl Light {
+    fn new(light: &gltf::khr_lights_punctual::Light, node_index: usize) -> Arc<Self> {
+        use gltf::khr_lights_punctual::Kind;
+        let kind = match light.kind() {
+            Kind::Directional => LightKind::Directional,
+            Kind::Point => LightKind::Point,
+            Kind::Spot { inner_cone_angle, outer_cone_angle } => LightKind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            },
+        };
         let this = Self {
-            position,
+            node_index,
+            kind,
+            color: glm::make_vec3(&light.color()),
+            intensity: light.intensity(),
+            range: light.range(),
         };
-        Some(this)
+        Arc::new(this)
+    }
+
+    pub fn node_index(&self) -> usize {
+        self.node_index
+    }
+
+    pub fn kind(&self) -> LightKind {
+        self.kind
     }
 
-    pub fn position(&self) -> &glm::Vec3 {
-        &self.position
+    pub fn color(&self) -> &glm::Vec3 {
+        &self.color
+    }
+
+    // lux for directional lights, candela otherwise
+    pub fn intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    // infinite range when absent
+    pub fn range(&self) -> Option<f32> {
+        self.range
     }
 }
 
@@ -352,3 +1709,89 @@ impl BoundingBox {
         Self::new(min.xyz(), max.xyz())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // two triangles meeting along the edge from (0, 0, 0) to (1, 0, 0) at the angle in degrees,
+    // 180 being flat
+    fn folded_quad(angle: f32) -> (Vec<f32>, Vec<u32>) {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let positions = vec![
+            0.0, 0.0, 0.0,
+            1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+            0.0, cos, sin,
+        ];
+        (positions, vec![0, 1, 2, 1, 0, 3])
+    }
+
+    fn normal(generated: &GeneratedNormals, index: u32) -> glm::Vec3 {
+        let offset = index as usize * 3;
+        glm::make_vec3(&generated.normals[offset..offset + 3])
+    }
+
+    #[test]
+    fn smooth_normals_weld_the_fold() {
+        let (positions, indices) = folded_quad(90.0);
+        let generated = NormalGeneration::smooth().generate(&positions, &indices);
+        assert_eq!(generated.vertex_map.len(), 4);
+        assert_eq!(generated.indices, vec![0, 1, 2, 1, 0, 3]);
+        let shared = normal(&generated, 0);
+        assert!(glm::distance(&shared, &glm::normalize(&glm::vec3(0.0, 1.0, 1.0))) < 1e-5);
+    }
+
+    #[test]
+    fn flat_normals_split_every_fold() {
+        let (positions, indices) = folded_quad(170.0);
+        let generated = NormalGeneration::flat().generate(&positions, &indices);
+        assert_eq!(generated.vertex_map, vec![0, 1, 2, 1, 0, 3]);
+        assert!(glm::distance(&normal(&generated, 0), &glm::vec3(0.0, 0.0, 1.0)) < 1e-5);
+        assert!(glm::distance(&normal(&generated, 2), &glm::vec3(0.0, 0.0, 1.0)) < 1e-5);
+    }
+
+    #[test]
+    fn crease_angle_splits_only_sharper_folds() {
+        let normal_generation = NormalGeneration::with_crease_angle(60f32.to_radians());
+        // faces 30 degrees apart are smoothed
+        let (positions, indices) = folded_quad(150.0);
+        let generated = normal_generation.generate(&positions, &indices);
+        assert_eq!(generated.vertex_map.len(), 4);
+        // faces 90 degrees apart keep their own normals along the edge
+        let (positions, indices) = folded_quad(90.0);
+        let generated = normal_generation.generate(&positions, &indices);
+        assert_eq!(generated.vertex_map, vec![0, 1, 2, 1, 0, 3]);
+        assert!(glm::distance(&normal(&generated, 0), &glm::vec3(0.0, 0.0, 1.0)) < 1e-5);
+        assert!(glm::distance(&normal(&generated, 3), &glm::vec3(0.0, 1.0, 0.0)) < 1e-5);
+    }
+
+    #[test]
+    fn zero_weights_keep_the_bind_pose() {
+        let positions = vec![
+            1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+        ];
+        let joints = vec![
+            0, 1, 0, 0,
+            1, 0, 0, 0,
+        ];
+        let weights = vec![
+            0.5, 0.5, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0,
+        ];
+        let joint_matrices = vec![
+            glm::translation(&glm::vec3(0.0, 0.0, 2.0)),
+            glm::translation(&glm::vec3(0.0, 0.0, 4.0)),
+        ];
+        let skinned = skin_positions(&positions, &joints, &weights, &joint_matrices);
+        assert_eq!(skinned, vec![1.0, 0.0, 3.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn coplanar_faces_share_vertices_even_when_flat() {
+        let (positions, indices) = folded_quad(180.0);
+        let generated = NormalGeneration::flat().generate(&positions, &indices);
+        assert_eq!(generated.vertex_map.len(), 4);
+    }
+}
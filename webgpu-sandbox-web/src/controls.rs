@@ -0,0 +1,398 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use nalgebra_glm as glm;
+
+use wasm_bindgen::prelude::*;
+
+use crate::scene::SceneContext;
+use crate::skybox::{SkyboxMode, Sun};
+use crate::post_process::Tonemapping;
+use crate::depth_of_field::{Focus, BokehShape};
+
+// functions exported to the page, which reaches them as window.sandbox once the module loaded.
+// they do nothing until the scene finished loading.
+thread_local! {
+    static SCENE_CONTEXT: RefCell<Option<Arc<SceneContext>>> = const { RefCell::new(None) };
+}
+
+// the scene the exported functions control
+pub fn attach(scene_context: &Arc<SceneContext>) {
+    SCENE_CONTEXT.with(|v| *v.borrow_mut() = Some(Arc::clone(scene_context)));
+}
+
+fn with_scene_context<T>(func: impl FnOnce(&SceneContext) -> T) -> Option<T> {
+    SCENE_CONTEXT.with(|v| v.borrow().as_deref().map(func))
+}
+
+fn index_array(values: Vec<usize>) -> Vec<u32> {
+    values.into_iter()
+        .map(|v| v as u32)
+        .collect()
+}
+
+// applies the change to a copy of the settings and hands it back to the scene
+fn update_settings<S>(get: fn(&SceneContext) -> S, set: fn(&SceneContext, S), update: impl FnOnce(&mut S)) {
+    with_scene_context(|v| {
+        let mut settings = get(v);
+        update(&mut settings);
+        set(v, settings);
+    });
+}
+
+fn string_array(values: Vec<String>) -> js_sys::Array {
+    values.into_iter()
+        .map(JsValue::from)
+        .collect::<js_sys::Array>()
+}
+
+// glTF indices of the nodes, parents before their children
+#[wasm_bindgen]
+pub fn node_indices() -> Vec<u32> {
+    index_array(with_scene_context(|v| v.node_indices()).unwrap_or_default())
+}
+
+#[wasm_bindgen]
+pub fn node_name(node_index: u32) -> Option<String> {
+    with_scene_context(|v| v.node_name(node_index as usize)).flatten()
+}
+
+// undefined for the root nodes
+#[wasm_bindgen]
+pub fn node_parent_index(node_index: u32) -> Option<u32> {
+    with_scene_context(|v| v.node_parent_index(node_index as usize))
+        .flatten()
+        .map(|v| v as u32)
+}
+
+#[wasm_bindgen]
+pub fn node_children_indices(node_index: u32) -> Vec<u32> {
+    index_array(with_scene_context(|v| v.node_children_indices(node_index as usize)).unwrap_or_default())
+}
+
+// local translation as [x, y, z]
+#[wasm_bindgen]
+pub fn node_translation(node_index: u32) -> Option<Vec<f32>> {
+    with_scene_context(|v| v.node_translation(node_index as usize))
+        .flatten()
+        .map(|v| v.as_slice().to_vec())
+}
+
+#[wasm_bindgen]
+pub fn set_node_translation(node_index: u32, x: f32, y: f32, z: f32) {
+    with_scene_context(|v| v.set_node_translation(node_index as usize, glm::vec3(x, y, z)));
+}
+
+// local rotation as the quaternion [x, y, z, w]
+#[wasm_bindgen]
+pub fn node_rotation(node_index: u32) -> Option<Vec<f32>> {
+    with_scene_context(|v| v.node_rotation(node_index as usize))
+        .flatten()
+        .map(|v| v.coords.as_slice().to_vec())
+}
+
+#[wasm_bindgen]
+pub fn set_node_rotation(node_index: u32, x: f32, y: f32, z: f32, w: f32) {
+    with_scene_context(|v| v.set_node_rotation(node_index as usize, glm::quat(x, y, z, w).normalize()));
+}
+
+// local scale as [x, y, z]
+#[wasm_bindgen]
+pub fn node_scale(node_index: u32) -> Option<Vec<f32>> {
+    with_scene_context(|v| v.node_scale(node_index as usize))
+        .flatten()
+        .map(|v| v.as_slice().to_vec())
+}
+
+#[wasm_bindgen]
+pub fn set_node_scale(node_index: u32, x: f32, y: f32, z: f32) {
+    with_scene_context(|v| v.set_node_scale(node_index as usize, glm::vec3(x, y, z)));
+}
+
+// morph target weights, empty when the mesh of the node has no targets
+#[wasm_bindgen]
+pub fn node_weights(node_index: u32) -> Vec<f32> {
+    with_scene_context(|v| v.node_weights(node_index as usize)).unwrap_or_default()
+}
+
+#[wasm_bindgen]
+pub fn set_node_weights(node_index: u32, weights: Vec<f32>) {
+    with_scene_context(|v| v.set_node_weights(node_index as usize, weights));
+}
+
+// world transform as a column-major 4x4 matrix
+#[wasm_bindgen]
+pub fn node_world_transform(node_index: u32) -> Option<Vec<f32>> {
+    with_scene_context(|v| v.world_transform(node_index as usize))
+        .flatten()
+        .map(|v| v.as_slice().to_vec())
+}
+
+#[wasm_bindgen]
+pub fn animation_clip_names() -> js_sys::Array {
+    string_array(with_scene_context(|v| v.animation_clip_names()).unwrap_or_default())
+}
+
+#[wasm_bindgen]
+pub fn animation_clip_index() -> Option<u32> {
+    with_scene_context(|v| v.animation_clip_index())
+        .flatten()
+        .map(|v| v as u32)
+}
+
+// starts the clip from its beginning, undefined stops the animation
+#[wasm_bindgen]
+pub fn play_animation_clip(clip_index: Option<u32>) {
+    with_scene_context(|v| v.play_animation_clip(clip_index.map(|v| v as usize)));
+}
+
+#[wasm_bindgen]
+pub fn is_animation_playing() -> bool {
+    with_scene_context(|v| v.is_animation_playing()).unwrap_or(false)
+}
+
+// a clip that ran to its end plays again from the beginning
+#[wasm_bindgen]
+pub fn set_animation_playing(playing: bool) {
+    with_scene_context(|v| v.set_animation_playing(playing));
+}
+
+#[wasm_bindgen]
+pub fn is_animation_looping() -> bool {
+    with_scene_context(|v| v.is_animation_looping()).unwrap_or(false)
+}
+
+#[wasm_bindgen]
+pub fn set_animation_looping(looping: bool) {
+    with_scene_context(|v| v.set_animation_looping(looping));
+}
+
+#[wasm_bindgen]
+pub fn camera_names() -> js_sys::Array {
+    string_array(with_scene_context(|v| v.camera_names()).unwrap_or_default())
+}
+
+#[wasm_bindgen]
+pub fn camera_index() -> Option<u32> {
+    with_scene_context(|v| v.camera_index())
+        .flatten()
+        .map(|v| v as u32)
+}
+
+// one of the authored cameras, undefined orbits around the model
+#[wasm_bindgen]
+pub fn select_camera(camera_index: Option<u32>) {
+    with_scene_context(|v| v.select_camera(camera_index.map(|v| v as usize)));
+}
+
+// gradient, environment, cubemap or procedural
+#[wasm_bindgen]
+pub fn skybox_mode() -> String {
+    let mode = with_scene_context(|v| v.skybox_settings().mode).unwrap_or(SkyboxMode::Gradient);
+    match mode {
+        SkyboxMode::Gradient => "gradient",
+        SkyboxMode::Environment => "environment",
+        SkyboxMode::Cubemap => "cubemap",
+        SkyboxMode::Procedural => "procedural",
+    }.into()
+}
+
+// the gradient shows when the environment or the cubemap is unavailable
+#[wasm_bindgen]
+pub fn set_skybox_mode(mode: &str) {
+    let mode = match mode {
+        "gradient" => SkyboxMode::Gradient,
+        "environment" => SkyboxMode::Environment,
+        "cubemap" => SkyboxMode::Cubemap,
+        "procedural" => SkyboxMode::Procedural,
+        _ => return,
+    };
+    with_scene_context(|v| v.set_skybox_mode(mode));
+}
+
+// radians around +Y, turning the image-based lighting with the sky
+#[wasm_bindgen]
+pub fn set_skybox_rotation(rotation: f32) {
+    with_scene_context(|v| v.set_skybox_rotation(rotation));
+}
+
+#[wasm_bindgen]
+pub fn set_skybox_intensity(intensity: f32) {
+    with_scene_context(|v| v.set_skybox_intensity(intensity));
+}
+
+// sun of the procedural sky in radians, azimuth from +Z towards +X and elevation from the horizon.
+// turbidity ranges from 2 (clear) to 10 (hazy).
+#[wasm_bindgen]
+pub fn set_sun(azimuth: f32, elevation: f32, turbidity: f32, intensity: f32) {
+    let sun = Sun {
+        azimuth,
+        elevation,
+        turbidity: turbidity.clamp(2.0, 10.0),
+        intensity: intensity.max(0.0),
+    };
+    with_scene_context(|v| v.set_sun(sun));
+}
+
+// hours from 0 to 24, the sun rising at 6:00 and setting at 18:00
+#[wasm_bindgen]
+pub fn set_time_of_day(hours: f32) {
+    with_scene_context(|v| v.set_time_of_day(hours));
+}
+
+#[wasm_bindgen]
+pub fn set_shadows_enabled(enabled: bool) {
+    update_settings(SceneContext::shadow_settings, SceneContext::set_shadow_settings, |v| v.enabled = enabled);
+}
+
+// one to four cascades split between uniform (0) and logarithmic (1) distances
+#[wasm_bindgen]
+pub fn set_shadow_cascades(cascade_count: u32, split_lambda: f32) {
+    update_settings(SceneContext::shadow_settings, SceneContext::set_shadow_settings, |v| {
+        v.cascade_count = cascade_count as usize;
+        v.split_lambda = split_lambda.clamp(0.0, 1.0);
+    });
+}
+
+// depth bias in depth units, normal bias and filter radius in texels
+#[wasm_bindgen]
+pub fn set_shadow_bias(depth_bias: f32, normal_bias: f32, filter_radius: u32) {
+    update_settings(SceneContext::shadow_settings, SceneContext::set_shadow_settings, |v| {
+        v.depth_bias = depth_bias;
+        v.normal_bias = normal_bias;
+        v.filter_radius = filter_radius;
+    });
+}
+
+// tints the meshes by the cascade they sample
+#[wasm_bindgen]
+pub fn set_shadow_debug_cascades(debug_cascades: bool) {
+    update_settings(SceneContext::shadow_settings, SceneContext::set_shadow_settings, |v| v.debug_cascades = debug_cascades);
+}
+
+// multisampling of the scene, rounded down to what the device supports
+#[wasm_bindgen]
+pub fn set_antialiasing_sample_count(sample_count: u32) {
+    update_settings(SceneContext::antialiasing_settings, SceneContext::set_antialiasing_settings, |v| v.sample_count = sample_count);
+}
+
+// aces, agx or reinhard
+#[wasm_bindgen]
+pub fn set_tonemapping(tonemapping: &str) {
+    let tonemapping = match tonemapping {
+        "aces" => Tonemapping::Aces,
+        "agx" => Tonemapping::Agx,
+        "reinhard" => Tonemapping::Reinhard,
+        _ => return,
+    };
+    update_settings(SceneContext::post_process_settings, SceneContext::set_post_process_settings, |v| v.tonemapping = tonemapping);
+}
+
+// in stops
+#[wasm_bindgen]
+pub fn set_exposure(exposure: f32) {
+    update_settings(SceneContext::post_process_settings, SceneContext::set_post_process_settings, |v| v.exposure = exposure);
+}
+
+#[wasm_bindgen]
+pub fn set_bloom(enabled: bool, intensity: f32) {
+    update_settings(SceneContext::post_process_settings, SceneContext::set_post_process_settings, |v| {
+        v.bloom = enabled;
+        v.bloom_intensity = intensity.max(0.0);
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_fxaa(enabled: bool) {
+    update_settings(SceneContext::post_process_settings, SceneContext::set_post_process_settings, |v| v.fxaa = enabled);
+}
+
+#[wasm_bindgen]
+pub fn set_vignette(enabled: bool, intensity: f32) {
+    update_settings(SceneContext::post_process_settings, SceneContext::set_post_process_settings, |v| {
+        v.vignette = enabled;
+        v.vignette_intensity = intensity.clamp(0.0, 1.0);
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_grain(enabled: bool, intensity: f32) {
+    update_settings(SceneContext::post_process_settings, SceneContext::set_post_process_settings, |v| {
+        v.grain = enabled;
+        v.grain_intensity = intensity.clamp(0.0, 1.0);
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_ambient_occlusion_enabled(enabled: bool) {
+    update_settings(SceneContext::ambient_occlusion_settings, SceneContext::set_ambient_occlusion_settings, |v| v.enabled = enabled);
+}
+
+// radius as a fraction of the model size, intensity as the exponent of the unoccluded
+// fraction and 1 to 64 samples per pixel
+#[wasm_bindgen]
+pub fn set_ambient_occlusion(radius: f32, intensity: f32, sample_count: u32) {
+    update_settings(SceneContext::ambient_occlusion_settings, SceneContext::set_ambient_occlusion_settings, |v| {
+        v.radius = radius.max(0.0);
+        v.intensity = intensity.max(0.0);
+        v.sample_count = sample_count.clamp(1, 64);
+    });
+}
+
+// replaces the multisampling while enabled
+#[wasm_bindgen]
+pub fn set_temporal_antialiasing(enabled: bool) {
+    update_settings(SceneContext::antialiasing_settings, SceneContext::set_antialiasing_settings, |v| v.temporal = enabled);
+}
+
+// weight of the current frame against the history, lower is smoother but ghosts longer
+#[wasm_bindgen]
+pub fn set_temporal_blend(blend: f32) {
+    update_settings(SceneContext::antialiasing_settings, SceneContext::set_antialiasing_settings, |v| {
+        v.temporal_blend = blend.clamp(0.0, 1.0);
+    });
+}
+
+// clicks focus the lens while enabled
+#[wasm_bindgen]
+pub fn set_depth_of_field_enabled(enabled: bool) {
+    update_settings(SceneContext::depth_of_field_settings, SceneContext::set_depth_of_field_settings, |v| v.enabled = enabled);
+}
+
+// picked for the point under the last click or center for the model's bounding box center
+#[wasm_bindgen]
+pub fn set_focus(focus: &str) {
+    let focus = match focus {
+        "picked" => Focus::PickedPoint,
+        "center" => Focus::BoundingBoxCenter,
+        _ => return,
+    };
+    update_settings(SceneContext::depth_of_field_settings, SceneContext::set_depth_of_field_settings, |v| v.focus = focus);
+}
+
+// view depth in scene units
+#[wasm_bindgen]
+pub fn set_focus_distance(distance: f32) {
+    update_settings(SceneContext::depth_of_field_settings, SceneContext::set_depth_of_field_settings, |v| {
+        v.focus = Focus::Distance(distance.max(0.0));
+    });
+}
+
+// f-number of the aperture, smaller values blur more
+#[wasm_bindgen]
+pub fn set_aperture(f_number: f32) {
+    update_settings(SceneContext::depth_of_field_settings, SceneContext::set_depth_of_field_settings, |v| {
+        v.f_number = f_number.max(0.1);
+    });
+}
+
+// polygonal highlights of the aperture blades turned by the rotation in radians. fewer than
+// three blades give round highlights.
+#[wasm_bindgen]
+pub fn set_bokeh(blade_count: u32, rotation: f32) {
+    let bokeh_shape = match blade_count {
+        0..=2 => BokehShape::Circle,
+        _ => BokehShape::Polygon { blade_count, rotation },
+    };
+    update_settings(SceneContext::depth_of_field_settings, SceneContext::set_depth_of_field_settings, |v| v.bokeh_shape = bokeh_shape);
+}
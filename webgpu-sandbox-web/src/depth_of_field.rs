@@ -0,0 +1,46 @@
+// distance the lens is focused at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Focus {
+    // the center of the model's bounding box
+    BoundingBoxCenter,
+    // the surface point under the last click, the bounding box center until there is one
+    PickedPoint,
+    // view depth in scene units
+    Distance(f32),
+}
+
+// outline of the out of focus highlights
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BokehShape {
+    Circle,
+    // regular polygon of aperture blades turned by the rotation in radians
+    Polygon { blade_count: u32, rotation: f32 },
+}
+
+// thin lens blur from the depth of the nodes. the focal length follows the vertical field of
+// view on a sensor of the given height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthOfFieldSettings {
+    pub enabled: bool,
+    // f-number of the aperture, smaller values blur more
+    pub f_number: f32,
+    // in scene units, 0.024 for a full frame camera in a scene measured in meters
+    pub sensor_height: f32,
+    pub focus: Focus,
+    pub bokeh_shape: BokehShape,
+    // blur radius limit in pixels
+    pub max_radius: f32,
+}
+
+impl Default for DepthOfFieldSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            f_number: 2.8,
+            sensor_height: 0.024,
+            focus: Focus::PickedPoint,
+            bokeh_shape: BokehShape::Polygon { blade_count: 6, rotation: 0.0 },
+            max_radius: 16.0,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+
+use nalgebra_glm as glm;
+use std::sync::Arc;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{fetch::fetch, console_log};
+use crate::skybox::Sun;
+
+// equirectangular environment decoded from a Radiance HDR (RGBE) file into linear RGB
+pub struct Environment {
+    width: u32,
+    height: u32,
+    pixels: Vec<f32>,
+}
+
+impl Environment {
+    // none when the file is missing or not a Radiance HDR
+    pub async fn fetch(url: &str) -> Result<Option<Arc<Self>>, JsValue> {
+        let data = fetch(url).await?;
+        let this = Self::new(&data);
+        if this.is_none() {
+            console_log!("environment unavailable: {}", url);
+        }
+        Ok(this)
+    }
+
+    fn new(data: &[u8]) -> Option<Arc<Self>> {
+        let mut reader = HdrReader { data, offset: 0 };
+        // header
+        let signature = reader.read_line()?;
+        if !signature.starts_with("#?") {
+            return None
+        }
+        loop {
+            let line = reader.read_line()?;
+            if line.is_empty() {
+                break
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return None
+                }
+            }
+        }
+        // only the standard top-down, left-to-right orientation
+        let resolution = reader.read_line()?;
+        let tokens: Vec<&str> = resolution.split_whitespace().collect();
+        let ["-Y", height, "+X", width] = tokens.as_slice() else { return None };
+        let width: u32 = width.parse().ok()?;
+        let height: u32 = height.parse().ok()?;
+        // scanlines
+        let mut pixels: Vec<f32> = Vec::with_capacity((width * height * 3) as usize);
+        for _ in 0..height {
+            let scanline = reader.read_scanline(width as usize)?;
+            pixels.extend(scanline.chunks(4).flat_map(Self::decode));
+        }
+        let this = Self {
+            width,
+            height,
+            pixels,
+        };
+        Some(Arc::new(this))
+    }
+
+    // studio-like sky with a warm key light and a dark ground, lighting the scene without an HDR file
+    pub fn procedural() -> Arc<Self> {
+        let (width, height) = (128u32, 64u32);
+        // the key light comes from the default sun
+        let sun = Sun::default().direction();
+        let mut pixels: Vec<f32> = Vec::with_capacity((width * height * 3) as usize);
+        for y in 0..height {
+            // the same mapping as the equirectangular lookup
+            let theta = (y as f32 + 0.5) / height as f32 * std::f32::consts::PI;
+            for x in 0..width {
+                let phi = ((x as f32 + 0.5) / width as f32 - 0.5) * 2.0 * std::f32::consts::PI;
+                let direction = glm::vec3(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+                let color = if direction.y >= 0.0 {
+                    let horizon = glm::vec3(0.9, 0.92, 0.95);
+                    let zenith = glm::vec3(0.25, 0.42, 0.8);
+                    glm::mix(&horizon, &zenith, direction.y.sqrt())
+                } else {
+                    glm::vec3(0.18, 0.16, 0.14)
+                };
+                let glow = glm::dot(&direction, &sun).max(0.0).powf(512.0);
+                let color = color + glm::vec3(1.0, 0.95, 0.85) * glow * 50.0;
+                pixels.extend(color.iter());
+            }
+        }
+        let this = Self {
+            width,
+            height,
+            pixels,
+        };
+        Arc::new(this)
+    }
+
+    fn decode(rgbe: &[u8]) -> [f32; 3] {
+        if rgbe[3] == 0 {
+            return [0.0; 3]
+        }
+        let scale = 2.0f32.powi(rgbe[3] as i32 - 136);
+        [rgbe[0] as f32 * scale, rgbe[1] as f32 * scale, rgbe[2] as f32 * scale]
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    // linear RGB rows from top to bottom
+    pub fn pixels(&self) -> &Vec<f32> {
+        &self.pixels
+    }
+}
+
+struct HdrReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> HdrReader<'a> {
+    fn read_line(&mut self) -> Option<String> {
+        let rest = self.data.get(self.offset..)?;
+        let length = rest.iter().position(|&v| v == b'\n')?;
+        self.offset += length + 1;
+        let line = String::from_utf8_lossy(&rest[..length]);
+        Some(line.trim_end().into())
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        let byte = *self.data.get(self.offset)?;
+        self.offset += 1;
+        Some(byte)
+    }
+
+    fn read_bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset + count)?;
+        self.offset += count;
+        Some(bytes)
+    }
+
+    // RGBE bytes of a scanline, decoding the adaptive run-length encoding when present
+    fn read_scanline(&mut self, width: usize) -> Option<Vec<u8>> {
+        let header = self.data.get(self.offset..self.offset + 4)?;
+        let encoded = (8..0x8000).contains(&width)
+            && header[0] == 2 && header[1] == 2
+            && ((header[2] as usize) << 8 | header[3] as usize) == width;
+        if !encoded {
+            return self.read_bytes(width * 4).map(|v| v.to_vec())
+        }
+        self.offset += 4;
+        // channels are stored one after another
+        let mut scanline = vec![0u8; width * 4];
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = self.read_byte()? as usize;
+                if count > 128 {
+                    let count = count - 128;
+                    let value = self.read_byte()?;
+                    if x + count > width {
+                        return None
+                    }
+                    for i in 0..count {
+                        scanline[(x + i) * 4 + channel] = value;
+                    }
+                    x += count;
+                } else {
+                    if count == 0 || x + count > width {
+                        return None
+                    }
+                    let values = self.read_bytes(count)?;
+                    for (i, &value) in values.iter().enumerate() {
+                        scanline[(x + i) * 4 + channel] = value;
+                    }
+                    x += count;
+                }
+            }
+        }
+        Some(scanline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: usize, height: usize) -> Vec<u8> {
+        format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width).into_bytes()
+    }
+
+    #[test]
+    fn flat_scanlines_decode_in_order() {
+        let mut data = header(2, 2);
+        // 1.0, 2.0, 0.5 and black
+        data.extend([128, 128, 128, 129, 128, 128, 128, 130, 128, 128, 128, 128, 0, 0, 0, 0]);
+        let environment = Environment::new(&data).unwrap();
+        assert_eq!((environment.width(), environment.height()), (2, 2));
+        assert_eq!(environment.pixels(), &vec![
+            1.0, 1.0, 1.0, 2.0, 2.0, 2.0,
+            0.5, 0.5, 0.5, 0.0, 0.0, 0.0,
+        ]);
+    }
+
+    #[test]
+    fn rle_scanlines_expand_runs_and_literals() {
+        let width = 8;
+        let mut data = header(width, 1);
+        data.extend([2, 2, 0, width as u8]);
+        // red: a run of six then two literals
+        data.extend([128 + 6, 64, 2, 128, 255]);
+        // green, blue and exponent: a run over the whole scanline
+        data.extend([128 + 8, 32]);
+        data.extend([128 + 8, 0]);
+        data.extend([128 + 8, 129]);
+        let environment = Environment::new(&data).unwrap();
+        let red: Vec<f32> = environment.pixels().chunks(3).map(|v| v[0]).collect();
+        assert_eq!(red, vec![0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 1.0, 255.0 / 128.0]);
+        assert!(environment.pixels().chunks(3).all(|v| v[1] == 0.25 && v[2] == 0.0));
+    }
+
+    #[test]
+    fn rle_runs_past_the_scanline_are_rejected() {
+        let mut data = header(8, 1);
+        data.extend([2, 2, 0, 8, 128 + 9, 0]);
+        assert!(Environment::new(&data).is_none());
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        // header without the blank line and resolution
+        assert!(Environment::new(b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n").is_none());
+        // missing the last pixel
+        let mut data = header(2, 1);
+        data.extend([128, 128, 128, 129]);
+        assert!(Environment::new(&data).is_none());
+        // not a Radiance file
+        assert!(Environment::new(b"P6\n2 1\n255\n").is_none());
+    }
+
+    #[test]
+    fn procedural_environment_is_brightest_at_the_sun() {
+        let environment = Environment::procedural();
+        let pixels = environment.pixels();
+        assert_eq!(pixels.len(), (environment.width() * environment.height() * 3) as usize);
+        let luminance: Vec<f32> = pixels.chunks(3).map(|v| v[0] + v[1] + v[2]).collect();
+        let brightest = luminance.iter().cloned().fold(0.0, f32::max);
+        assert!(brightest > 10.0);
+        // the bottom row is ground
+        let bottom = &luminance[luminance.len() - environment.width() as usize..];
+        assert!(bottom.iter().all(|&v| v < 1.0));
+    }
+}
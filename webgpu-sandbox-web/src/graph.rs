@@ -0,0 +1,201 @@
+
+use std::collections::HashMap;
+
+use nalgebra_glm as glm;
+
+use crate::asset::{Model, Node, Skin};
+
+// scene graph of the model nodes holding local TRS components. world transforms are
+// recomputed on update only for the nodes whose own or ancestor transform has changed.
+pub struct SceneGraph {
+    nodes: Vec<GraphNode>,
+    node_lookup: HashMap<usize, usize>,
+}
+
+impl SceneGraph {
+    pub fn new(model: &Model) -> Self {
+        // follows the model node order, which lists parents before their children
+        let node_lookup: HashMap<usize, usize> = model.nodes().iter()
+            .enumerate()
+            .map(|(local_index, v)| (v.node_index(), local_index))
+            .collect();
+        let nodes = model.nodes().iter()
+            .map(|v| GraphNode {
+                weights: Self::initial_weights(model, v),
+                node_index: v.node_index(),
+                name: v.name().map(Into::into),
+                parent: v.parent_index().and_then(|v| node_lookup.get(&v).copied()),
+                children: v.children_indices().iter()
+                    .filter_map(|v| node_lookup.get(v).copied())
+                    .collect(),
+                translation: *v.translation(),
+                rotation: *v.rotation(),
+                scale: *v.scale(),
+                world_transform: *v.transform(),
+                dirty: false,
+                updated: false,
+                // uploads every transform on the first frame
+                changed: true,
+            })
+            .collect();
+        Self {
+            nodes,
+            node_lookup,
+        }
+    }
+
+    // node weights or the mesh default weights, sized to the morph targets of the mesh
+    fn initial_weights(model: &Model, node: &Node) -> Vec<f32> {
+        let Some(mesh) = node.mesh_index().and_then(|v| model.meshes().iter().find(|m| m.mesh_index() == v)) else {
+            return vec![]
+        };
+        let mut weights = node.weights()
+            .unwrap_or(mesh.default_weights())
+            .clone();
+        weights.resize(mesh.morph_target_count(), 0.0);
+        weights
+    }
+
+    fn node_mut(&mut self, node_index: usize) -> Option<&mut GraphNode> {
+        let local_index = *self.node_lookup.get(&node_index)?;
+        self.nodes.get_mut(local_index)
+    }
+
+    fn node(&self, node_index: usize) -> Option<&GraphNode> {
+        let local_index = *self.node_lookup.get(&node_index)?;
+        self.nodes.get(local_index)
+    }
+
+    pub fn node_indices(&self) -> Vec<usize> {
+        self.nodes.iter()
+            .map(|v| v.node_index)
+            .collect()
+    }
+
+    pub fn set_translation(&mut self, node_index: usize, translation: glm::Vec3) {
+        let Some(node) = self.node_mut(node_index) else { return };
+        node.translation = translation;
+        node.dirty = true;
+    }
+
+    pub fn set_rotation(&mut self, node_index: usize, rotation: glm::Quat) {
+        let Some(node) = self.node_mut(node_index) else { return };
+        node.rotation = rotation;
+        node.dirty = true;
+    }
+
+    pub fn set_scale(&mut self, node_index: usize, scale: glm::Vec3) {
+        let Some(node) = self.node_mut(node_index) else { return };
+        node.scale = scale;
+        node.dirty = true;
+    }
+
+    // morph target weights, ignoring the ones beyond the targets of the mesh
+    pub fn set_weights(&mut self, node_index: usize, weights: Vec<f32>) {
+        let Some(node) = self.node_mut(node_index) else { return };
+        let count = node.weights.len().min(weights.len());
+        node.weights[..count].copy_from_slice(&weights[..count]);
+    }
+
+    pub fn weights(&self, node_index: usize) -> Option<&Vec<f32>> {
+        self.node(node_index).map(|v| &v.weights)
+    }
+
+    pub fn translation(&self, node_index: usize) -> Option<glm::Vec3> {
+        self.node(node_index).map(|v| v.translation)
+    }
+
+    pub fn rotation(&self, node_index: usize) -> Option<glm::Quat> {
+        self.node(node_index).map(|v| v.rotation)
+    }
+
+    pub fn scale(&self, node_index: usize) -> Option<glm::Vec3> {
+        self.node(node_index).map(|v| v.scale)
+    }
+
+    pub fn name(&self, node_index: usize) -> Option<&str> {
+        self.node(node_index).and_then(|v| v.name.as_deref())
+    }
+
+    pub fn parent_index(&self, node_index: usize) -> Option<usize> {
+        let parent = self.node(node_index)?.parent?;
+        Some(self.nodes[parent].node_index)
+    }
+
+    pub fn children_indices(&self, node_index: usize) -> Vec<usize> {
+        let Some(node) = self.node(node_index) else { return vec![] };
+        node.children.iter()
+            .map(|&v| self.nodes[v].node_index)
+            .collect()
+    }
+
+    pub fn world_transform(&self, node_index: usize) -> Option<glm::Mat4> {
+        self.node(node_index).map(|v| v.world_transform)
+    }
+
+    // joint matrices relative to the skinned node so that the shader keeps applying the node transform
+    pub fn joint_matrices(&self, node_index: usize, skin: &Skin) -> Vec<glm::Mat4> {
+        let inverse_transform = self.world_transform(node_index)
+            .map(|v| glm::inverse(&v))
+            .unwrap_or_else(glm::identity);
+        skin.joint_indices().iter()
+            .zip(skin.inverse_bind_matrices())
+            .map(|(&joint_index, inverse_bind_matrix)| {
+                let joint_transform = self.world_transform(joint_index)
+                    .unwrap_or_else(glm::identity);
+                inverse_transform * joint_transform * inverse_bind_matrix
+            })
+            .collect()
+    }
+
+    // propagates dirty local transforms down to the descendants
+    pub fn update(&mut self) {
+        for local_index in 0..self.nodes.len() {
+            let parent = self.nodes[local_index].parent;
+            let parent_updated = parent.map(|v| self.nodes[v].updated).unwrap_or(false);
+            let node = &self.nodes[local_index];
+            if !(node.dirty || parent_updated) {
+                self.nodes[local_index].updated = false;
+                continue
+            }
+            let parent_transform = parent
+                .map(|v| self.nodes[v].world_transform)
+                .unwrap_or_else(glm::identity);
+            let local_transform = Node::compose(&node.translation, &node.rotation, &node.scale);
+            let node = &mut self.nodes[local_index];
+            node.world_transform = parent_transform * local_transform;
+            node.dirty = false;
+            node.updated = true;
+            node.changed = true;
+        }
+    }
+
+    // world transforms changed since the last call, keyed by the position in the model nodes
+    pub fn take_changed_transforms(&mut self) -> Vec<(usize, glm::Mat4)> {
+        self.nodes.iter_mut()
+            .enumerate()
+            .filter(|(_, v)| v.changed)
+            .map(|(local_index, v)| {
+                v.changed = false;
+                (local_index, v.world_transform)
+            })
+            .collect()
+    }
+}
+
+struct GraphNode {
+    node_index: usize,
+    name: Option<String>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    translation: glm::Vec3,
+    rotation: glm::Quat,
+    scale: glm::Vec3,
+    world_transform: glm::Mat4,
+    weights: Vec<f32>,
+    dirty: bool,
+    // recomputed during the latest update
+    updated: bool,
+    // waiting for the upload
+    changed: bool,
+}
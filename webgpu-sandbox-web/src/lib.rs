@@ -3,12 +3,24 @@ mod console;
 mod global;
 mod animation;
 mod scene;
+mod graph;
+mod animator;
 mod render;
 mod renderer;
 mod fetch;
 mod asset;
+mod environment;
+mod skybox;
+mod shadow;
+mod antialiasing;
+mod post_process;
+mod ambient_occlusion;
+mod depth_of_field;
 mod web;
 mod preset;
+mod controls;
+
+use std::sync::Arc;
 
 use wasm_bindgen::{prelude::*, JsCast};
 use wasm_bindgen_futures::{spawn_local, JsFuture};
@@ -20,18 +32,38 @@ use crate::render::{
 use crate::renderer::Renderer;
 use crate::scene::SceneContext;
 use crate::asset::Model;
+use crate::environment::Environment;
+use crate::skybox::Cubemap;
 use crate::preset::ScenePreset;
 
 async fn main() -> Result<(), JsValue> {
     console_log!("fetching model...");
-    let model_name = ScenePreset::default().model_name();
-    let model = Model::fetch(&model_name).await?
+    let preset = ScenePreset::default();
+    let model_name = preset.model_name();
+    let model = Model::fetch(&model_name, preset.normal_generation()).await?
         .unwrap();
     console_log!("fetch model complete");
+    // a procedural studio environment stands in for a missing HDR file
+    let environment = match preset.environment_name() {
+        Some(name) => Environment::fetch(&name).await.ok().flatten(),
+        None => None,
+    }.or_else(|| Some(Environment::procedural()));
+    let cubemap = match preset.cubemap_source() {
+        Some(source) => Cubemap::fetch(&source).await.ok().flatten(),
+        None => None,
+    };
     let device = Device::acquire().await?;
     let surface = Surface::acquire().await?;
     surface.configure(&device);
-    let scene_context = SceneContext::new(&model);
+    surface.observe_resize();
+    let scene_context = SceneContext::new(&model, environment.as_ref(), cubemap.as_ref(), preset.skybox_settings());
+    scene_context.set_shadow_settings(preset.shadow_settings());
+    let scene_context_weak = Arc::downgrade(&scene_context);
+    surface.observe_click(move |position| {
+        let Some(scene_context) = scene_context_weak.upgrade() else { return };
+        scene_context.request_focus_pick(position);
+    });
+    controls::attach(&scene_context);
     let renderer = Renderer::new(&device, &surface, &scene_context);
     let run_loop = animation::FrameRunLoop::new(global::window(), move || {
         renderer.render_frame();
@@ -0,0 +1,48 @@
+
+// curve mapping the HDR scene into the displayable range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemapping {
+    // Stephen Hill's fit of the ACES reference and output transforms
+    Aces,
+    // Troy Sobotka's AgX with the default look
+    Agx,
+    // on luminance, which keeps the hues
+    Reinhard,
+}
+
+// fullscreen effects applied to the HDR scene before it reaches the swapchain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostProcessSettings {
+    pub tonemapping: Tonemapping,
+    // in stops, 0.0 leaves the scene as it is
+    pub exposure: f32,
+    pub bloom: bool,
+    // share of the blurred scene mixed into the image
+    pub bloom_intensity: f32,
+    // radius of the upsampling filter in texture coordinates
+    pub bloom_radius: f32,
+    pub fxaa: bool,
+    pub vignette: bool,
+    // darkening at the corners
+    pub vignette_intensity: f32,
+    pub grain: bool,
+    // amplitude of the noise in display values
+    pub grain_intensity: f32,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            tonemapping: Tonemapping::Aces,
+            exposure: 0.0,
+            bloom: true,
+            bloom_intensity: 0.04,
+            bloom_radius: 0.005,
+            fxaa: false,
+            vignette: false,
+            vignette_intensity: 0.35,
+            grain: false,
+            grain_intensity: 0.04,
+        }
+    }
+}
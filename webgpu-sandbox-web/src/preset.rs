@@ -1,4 +1,7 @@
 
+use crate::asset::NormalGeneration;
+use crate::skybox::{SkyboxMode, SkyboxSettings, CubemapSource};
+use crate::shadow::ShadowSettings;
 use crate::web::CurrentQueryParameters;
 
 pub enum ScenePreset {
@@ -8,7 +11,7 @@ pub enum ScenePreset {
 
 impl Default for ScenePreset {
     fn default() -> Self {
-        let query_parameters = CurrentQueryParameters::value();
+        let query_parameters = CurrentQueryParameters::name();
         match query_parameters.as_deref() {
             Some("2024") => Self::ChineseDragon,
             Some("2023") => Self::StanfordBunny,
@@ -24,4 +27,79 @@ impl ScenePreset {
             Self::ChineseDragon => "dragon.glb".into(),
         }
     }
+
+    // Radiance HDR lighting the scene when the query names one, such as environment=environment.hdr.
+    // the procedural studio environment lights it otherwise.
+    pub fn environment_name(&self) -> Option<String> {
+        CurrentQueryParameters::get("environment").filter(|v| !v.is_empty())
+    }
+
+    // the gradient unless the query asks for another sky. skybox=environment shows the environment,
+    // skybox=procedural the sun-lit sky, skybox=cubemap the six faces in skybox/ and any other
+    // value a cubemap cross image. the skybox falls back to the gradient when its source is
+    // unavailable. the scans have no lights, so the sun lights them and casts their shadow.
+    pub fn skybox_settings(&self) -> SkyboxSettings {
+        let mode = match Self::skybox_query().as_deref() {
+            Some("environment") => SkyboxMode::Environment,
+            Some("procedural") => SkyboxMode::Procedural,
+            Some(_) => SkyboxMode::Cubemap,
+            None => SkyboxMode::Gradient,
+        };
+        SkyboxSettings {
+            mode,
+            key_light: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn cubemap_source(&self) -> Option<CubemapSource> {
+        match Self::skybox_query().as_deref() {
+            Some("cubemap") => Some(CubemapSource::Faces([
+                "skybox/px.jpg".into(),
+                "skybox/nx.jpg".into(),
+                "skybox/py.jpg".into(),
+                "skybox/ny.jpg".into(),
+                "skybox/pz.jpg".into(),
+                "skybox/nz.jpg".into(),
+            ])),
+            Some("environment" | "procedural") | None => None,
+            Some(url) => Some(CubemapSource::Cross(url.into())),
+        }
+    }
+
+    fn skybox_query() -> Option<String> {
+        CurrentQueryParameters::get("skybox").filter(|v| !v.is_empty())
+    }
+
+    // scans sit on a ground plane catching their shadow
+    pub fn shadow_settings(&self) -> ShadowSettings {
+        match self {
+            Self::StanfordBunny => ShadowSettings {
+                ground_plane: true,
+                ..Default::default()
+            },
+            Self::ChineseDragon => ShadowSettings {
+                ground_plane: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    // scans are smoothed across every edge. normals=flat, normals=smooth or a crease angle in
+    // degrees such as normals=45 in the query override it.
+    pub fn normal_generation(&self) -> NormalGeneration {
+        let query_parameter = CurrentQueryParameters::get("normals");
+        match query_parameter.as_deref() {
+            Some("flat") => return NormalGeneration::flat(),
+            Some("smooth") => return NormalGeneration::smooth(),
+            Some(v) => if let Ok(degrees) = v.parse::<f32>() {
+                return NormalGeneration::with_crease_angle(degrees.to_radians())
+            },
+            None => {},
+        }
+        match self {
+            Self::StanfordBunny => NormalGeneration::smooth(),
+            Self::ChineseDragon => NormalGeneration::smooth(),
+        }
+    }
 }
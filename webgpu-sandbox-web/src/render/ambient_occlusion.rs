@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use crate::render::{
+    Device,
+    Shaders,
+    PipelineLayouts,
+    RenderGraph,
+    PassDescription,
+    ColorAttachment,
+    TextureHandle,
+};
+use crate::ambient_occlusion::AmbientOcclusionSettings;
+
+use nalgebra_glm as glm;
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuTextureFormat,
+    GpuRenderPipelineDescriptor,
+    GpuVertexState,
+    GpuFragmentState,
+    GpuColorTargetState,
+    GpuPrimitiveState,
+    GpuPrimitiveTopology,
+    GpuBufferDescriptor,
+    gpu_buffer_usage,
+    GpuBindGroupEntry,
+    GpuBindGroupDescriptor,
+    GpuBufferBinding,
+    GpuRenderPipeline,
+    GpuBuffer,
+    GpuTextureDescriptor,
+    gpu_texture_usage,
+    GpuTextureView,
+};
+
+// occlusion from the depth prepass, blurred along both axes while keeping the depth edges
+pub struct AmbientOcclusionPasses {
+    device: Arc<Device>,
+    occlusion: GpuRenderPipeline,
+    blur_horizontal: GpuRenderPipeline,
+    blur_vertical: GpuRenderPipeline,
+    uniform_buffer: GpuBuffer,
+    // stands in for the occlusion before there is one
+    empty_view: GpuTextureView,
+}
+
+impl AmbientOcclusionPasses {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>) -> Arc<Self> {
+        let shader_module = Shaders::ambient_occlusion(device);
+        let layout = PipelineLayouts::ambient_occlusion(device);
+        let create_render_pipeline = |entry_point: &str| {
+            let vertex_state = GpuVertexState::new("vert_main", shader_module.shader_module());
+            let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
+            let target = GpuColorTargetState::new(GpuTextureFormat::R8unorm);
+            let fragment_targets: Vec<JsValue> = vec![target.into()];
+            let fragment_targets = fragment_targets.into_iter().collect::<js_sys::Array>();
+            let fragment_state = GpuFragmentState::new(entry_point, shader_module.shader_module(), &fragment_targets);
+            render_descriptor.fragment(&fragment_state);
+            let mut primitive_state = GpuPrimitiveState::new();
+            primitive_state.topology(GpuPrimitiveTopology::TriangleList);
+            render_descriptor.primitive(&primitive_state);
+            device.device().create_render_pipeline(&render_descriptor)
+        };
+        // uniform: projection, inverse projection and parameters
+        let uniform_buffer_descriptor = GpuBufferDescriptor::new(
+            (std::mem::size_of::<f32>() * (16 + 16 + 4)) as f64,
+            gpu_buffer_usage::UNIFORM | gpu_buffer_usage::COPY_DST);
+        let uniform_buffer = device.device().create_buffer(&uniform_buffer_descriptor);
+        let extent: Vec<JsValue> = vec![1.0, 1.0].into_iter().map(|v: f64| v.into()).collect();
+        let extent = extent.into_iter().collect::<js_sys::Array>();
+        let texture_descriptor = GpuTextureDescriptor::new(GpuTextureFormat::R8unorm, &extent, gpu_texture_usage::TEXTURE_BINDING);
+        let empty_view = device.device().create_texture(&texture_descriptor).create_view();
+        let this = Self {
+            device: Arc::clone(device),
+            occlusion: create_render_pipeline("frag_main"),
+            blur_horizontal: create_render_pipeline("frag_blur_horizontal_main"),
+            blur_vertical: create_render_pipeline("frag_blur_vertical_main"),
+            uniform_buffer,
+            empty_view,
+        };
+        Arc::new(this)
+    }
+
+    // writes the blurred occlusion of the depth into the target, both sized like the frame
+    pub fn add_passes(&self,
+        graph: &mut RenderGraph,
+        depth: TextureHandle,
+        target: TextureHandle,
+        projection_matrix: &glm::Mat4,
+        radius: f32,
+        settings: &AmbientOcclusionSettings) {
+        let Some(description) = graph.texture_description(target) else { return };
+        let occlusion = graph.create_texture("ambient occlusion", description);
+        let blurred = graph.create_texture("ambient occlusion", description);
+        let passes = [
+            ("ambient occlusion", &self.occlusion, None, occlusion),
+            ("ambient occlusion blur", &self.blur_horizontal, Some(occlusion), blurred),
+            ("ambient occlusion blur", &self.blur_vertical, Some(blurred), target),
+        ];
+        for (name, render_pipeline, source, destination) in passes {
+            let pass = PassDescription::new(name)
+                .color(ColorAttachment::new(destination))
+                .read(depth);
+            let pass = source.into_iter().fold(pass, PassDescription::read);
+            let device = Arc::clone(&self.device);
+            let render_pipeline = render_pipeline.clone();
+            let uniform_buffer = self.uniform_buffer.clone();
+            let empty_view = self.empty_view.clone();
+            graph.add_pass(pass, move |render_pass_encoder, textures| {
+                let Some(depth_view) = textures.view(depth) else { return };
+                let buffer_binding: JsValue = GpuBufferBinding::new(&uniform_buffer).into();
+                let bind_entries: Vec<JsValue> = vec![
+                    GpuBindGroupEntry::new(0, &buffer_binding).into(),
+                    GpuBindGroupEntry::new(1, depth_view).into(),
+                    GpuBindGroupEntry::new(2, source.and_then(|v| textures.view(v)).unwrap_or(&empty_view)).into(),
+                ];
+                let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+                let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &render_pipeline.get_bind_group_layout(0));
+                let bind_group = device.device().create_bind_group(&bind_group_descriptor);
+                render_pass_encoder.set_pipeline(&render_pipeline);
+                render_pass_encoder.set_bind_group(0, Some(&bind_group));
+                render_pass_encoder.draw(3);
+            });
+        }
+
+        // write
+        let inverse_projection_matrix = projection_matrix.try_inverse().unwrap_or_else(glm::identity);
+        let parameters = [
+            radius,
+            settings.intensity,
+            settings.sample_count.clamp(1, 64) as f32,
+            0.0,
+        ];
+        let uniform_values: Vec<f32> = [projection_matrix.as_slice(), inverse_projection_matrix.as_slice(), &parameters]
+            .concat();
+        let uniform_data = js_sys::Float32Array::new_with_length(16 + 16 + 4);
+        uniform_data.copy_from(uniform_values.as_slice());
+        self.device.device().queue().write_buffer_with_u32_and_buffer_source(&self.uniform_buffer, 0, &uniform_data);
+    }
+}
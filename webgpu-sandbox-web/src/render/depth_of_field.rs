@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use crate::render::{
+    Device,
+    Shaders,
+    PipelineLayouts,
+    RenderGraph,
+    PassDescription,
+    ColorAttachment,
+    TextureHandle,
+    TextureDescription,
+    HDR_FORMAT,
+};
+use crate::depth_of_field::{DepthOfFieldSettings, BokehShape};
+
+use nalgebra_glm as glm;
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuRenderPipelineDescriptor,
+    GpuVertexState,
+    GpuFragmentState,
+    GpuColorTargetState,
+    GpuPrimitiveState,
+    GpuPrimitiveTopology,
+    GpuBufferDescriptor,
+    gpu_buffer_usage,
+    GpuBindGroupDescriptor,
+    GpuBindGroupEntry,
+    GpuBufferBinding,
+    GpuRenderPipeline,
+    GpuBuffer,
+    GpuSampler,
+    GpuSamplerDescriptor,
+    GpuFilterMode,
+};
+
+// thin lens blur of the HDR image gathered around each pixel by the circles of confusion
+pub struct DepthOfFieldPass {
+    device: Arc<Device>,
+    render_pipeline: GpuRenderPipeline,
+    uniform_buffer: GpuBuffer,
+    sampler: GpuSampler,
+}
+
+impl DepthOfFieldPass {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>) -> Arc<Self> {
+        let shader_module = Shaders::depth_of_field(device);
+        let layout = PipelineLayouts::depth_of_field(device);
+        let vertex_state = GpuVertexState::new("vert_main", shader_module.shader_module());
+        let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
+        let target = GpuColorTargetState::new(HDR_FORMAT);
+        let fragment_targets: Vec<JsValue> = vec![target.into()];
+        let fragment_targets = fragment_targets.into_iter().collect::<js_sys::Array>();
+        let fragment_state = GpuFragmentState::new("frag_main", shader_module.shader_module(), &fragment_targets);
+        render_descriptor.fragment(&fragment_state);
+        let mut primitive_state = GpuPrimitiveState::new();
+        primitive_state.topology(GpuPrimitiveTopology::TriangleList);
+        render_descriptor.primitive(&primitive_state);
+        let render_pipeline = device.device().create_render_pipeline(&render_descriptor);
+        // uniform: inverse projection, lens and bokeh
+        let uniform_buffer_descriptor = GpuBufferDescriptor::new(
+            (std::mem::size_of::<f32>() * (16 + 4 + 4)) as f64,
+            gpu_buffer_usage::UNIFORM | gpu_buffer_usage::COPY_DST);
+        let uniform_buffer = device.device().create_buffer(&uniform_buffer_descriptor);
+        // the spiral lands between texels
+        let mut sampler_descriptor = GpuSamplerDescriptor::new();
+        sampler_descriptor.mag_filter(GpuFilterMode::Linear);
+        sampler_descriptor.min_filter(GpuFilterMode::Linear);
+        let sampler = device.device().create_sampler_with_descriptor(&sampler_descriptor);
+        let this = Self {
+            device: Arc::clone(device),
+            render_pipeline,
+            uniform_buffer,
+            sampler,
+        };
+        Arc::new(this)
+    }
+
+    // returns the blurred copy of the source. the focal length follows the vertical field of
+    // view of the projection on the sensor.
+    pub fn add_pass(&self,
+        graph: &mut RenderGraph,
+        source: TextureHandle,
+        depth: TextureHandle,
+        projection_matrix: &glm::Mat4,
+        focus_distance: f32,
+        settings: &DepthOfFieldSettings) -> TextureHandle {
+        let Some(description) = graph.texture_description(source) else { return source };
+        let target = graph.create_texture("depth of field", TextureDescription::new(HDR_FORMAT, description.width, description.height));
+        let pass = PassDescription::new("depth of field")
+            .color(ColorAttachment::new(target))
+            .read(source)
+            .read(depth);
+        let device = Arc::clone(&self.device);
+        let render_pipeline = self.render_pipeline.clone();
+        let uniform_buffer = self.uniform_buffer.clone();
+        let sampler = self.sampler.clone();
+        graph.add_pass(pass, move |render_pass_encoder, textures| {
+            let (Some(source_view), Some(depth_view)) = (textures.view(source), textures.view(depth)) else { return };
+            let buffer_binding: JsValue = GpuBufferBinding::new(&uniform_buffer).into();
+            let bind_entries: Vec<JsValue> = vec![
+                GpuBindGroupEntry::new(0, &buffer_binding).into(),
+                GpuBindGroupEntry::new(1, &sampler).into(),
+                GpuBindGroupEntry::new(2, source_view).into(),
+                GpuBindGroupEntry::new(3, depth_view).into(),
+            ];
+            let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+            let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &render_pipeline.get_bind_group_layout(0));
+            let bind_group = device.device().create_bind_group(&bind_group_descriptor);
+            render_pass_encoder.set_pipeline(&render_pipeline);
+            render_pass_encoder.set_bind_group(0, Some(&bind_group));
+            render_pass_encoder.draw(3);
+        });
+
+        // write
+        let inverse_projection_matrix = projection_matrix.try_inverse().unwrap_or_else(glm::identity);
+        let sensor_height = settings.sensor_height.max(f32::EPSILON);
+        let focal_length = sensor_height * 0.5 * projection_matrix[(1, 1)];
+        let lens = [
+            focus_distance,
+            focal_length / settings.f_number.max(f32::EPSILON),
+            focal_length,
+            description.height as f32 / sensor_height,
+        ];
+        let (blade_count, rotation) = match settings.bokeh_shape {
+            BokehShape::Circle => (0, 0.0),
+            BokehShape::Polygon { blade_count, rotation } => (blade_count, rotation),
+        };
+        let bokeh = [
+            blade_count as f32,
+            rotation,
+            settings.max_radius.clamp(1.0, 64.0),
+            0.0,
+        ];
+        let uniform_values: Vec<f32> = [inverse_projection_matrix.as_slice(), &lens, &bokeh].concat();
+        let uniform_data = js_sys::Float32Array::new_with_length(16 + 4 + 4);
+        uniform_data.copy_from(uniform_values.as_slice());
+        self.device.device().queue().write_buffer_with_u32_and_buffer_source(&self.uniform_buffer, 0, &uniform_data);
+        target
+    }
+}
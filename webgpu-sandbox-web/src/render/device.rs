@@ -45,4 +45,9 @@ impl Device {
     pub fn device(&self) -> &GpuDevice {
         &self.device
     }
+
+    // features granted when the device was requested
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.device.features().has(feature)
+    }
 }
@@ -0,0 +1,340 @@
+
+use std::sync::Arc;
+
+use crate::environment::Environment;
+use crate::render::device::Device;
+use crate::render::shader::ShaderModule;
+use crate::render::Shaders;
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuTexture,
+    GpuTextureView,
+    GpuTextureFormat,
+    GpuTextureDescriptor,
+    GpuTextureViewDescriptor,
+    GpuTextureViewDimension,
+    gpu_texture_usage,
+    GpuSampler,
+    GpuSamplerDescriptor,
+    GpuFilterMode,
+    GpuMipmapFilterMode,
+    GpuImageCopyTexture,
+    GpuImageDataLayout,
+    GpuBufferDescriptor,
+    gpu_buffer_usage,
+    GpuBufferBinding,
+    GpuBindGroupDescriptor,
+    GpuBindGroupEntry,
+    GpuComputePipeline,
+    GpuComputePipelineDescriptor,
+    GpuProgrammableStage,
+    GpuCommandEncoder,
+};
+
+const ENVIRONMENT_SIZE: u32 = 512;
+const SPECULAR_SIZE: u32 = 128;
+const SPECULAR_MIP_COUNT: u32 = 5;
+const IRRADIANCE_SIZE: u32 = 32;
+const BRDF_LUT_SIZE: u32 = 256;
+
+// image-based lighting maps baked on the GPU from an equirectangular environment:
+// the environment cubemap with its mips, GGX prefiltered specular mips, diffuse
+// irradiance and the split-sum BRDF lookup table.
+pub struct EnvironmentMap {
+    environment_view: GpuTextureView,
+    specular_view: GpuTextureView,
+    irradiance_view: GpuTextureView,
+    brdf_lut_view: GpuTextureView,
+    sampler: GpuSampler,
+    available: bool,
+}
+
+impl EnvironmentMap {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>, environment: Option<&Environment>) -> Arc<Self> {
+        let Some(environment) = environment else { return Self::placeholder(device) };
+        let sampler = Self::create_sampler(device);
+        let shader_module = Shaders::environment(device);
+        let command_encoder = device.device().create_command_encoder();
+        // equirectangular source
+        let equirect_texture = Self::create_equirect_texture(device, environment);
+        // environment cube with the full mip chain
+        let environment_mip_count = ENVIRONMENT_SIZE.ilog2() + 1;
+        let environment_texture = Self::create_cube_texture(device, ENVIRONMENT_SIZE, environment_mip_count);
+        {
+            let pipeline = Self::create_pipeline(device, &shader_module, "equirect_to_cube");
+            let equirect_view = equirect_texture.create_view();
+            let output_view = Self::storage_view(&environment_texture, 0);
+            let entries = [
+                GpuBindGroupEntry::new(0, &equirect_view),
+                GpuBindGroupEntry::new(1, &sampler),
+                GpuBindGroupEntry::new(2, &output_view),
+            ];
+            Self::dispatch(device, &command_encoder, &pipeline, &entries, ENVIRONMENT_SIZE, ENVIRONMENT_SIZE);
+        }
+        {
+            let pipeline = Self::create_pipeline(device, &shader_module, "downsample");
+            for mip_level in 1..environment_mip_count {
+                let source_view = Self::storage_view(&environment_texture, mip_level - 1);
+                let output_view = Self::storage_view(&environment_texture, mip_level);
+                let entries = [
+                    GpuBindGroupEntry::new(2, &output_view),
+                    GpuBindGroupEntry::new(3, &source_view),
+                ];
+                let size = ENVIRONMENT_SIZE >> mip_level;
+                Self::dispatch(device, &command_encoder, &pipeline, &entries, size, size);
+            }
+        }
+        let environment_view = Self::cube_view(&environment_texture, environment_mip_count);
+        // specular, a mip level per roughness step
+        let specular_texture = Self::create_cube_texture(device, SPECULAR_SIZE, SPECULAR_MIP_COUNT);
+        {
+            let pipeline = Self::create_pipeline(device, &shader_module, "prefilter_specular");
+            for mip_level in 0..SPECULAR_MIP_COUNT {
+                let roughness = mip_level as f32 / (SPECULAR_MIP_COUNT - 1) as f32;
+                let parameter_buffer = Self::create_parameter_buffer(device, [roughness, ENVIRONMENT_SIZE as f32, 256.0, 0.0]);
+                let parameter_binding: JsValue = GpuBufferBinding::new(&parameter_buffer).into();
+                let output_view = Self::storage_view(&specular_texture, mip_level);
+                let entries = [
+                    GpuBindGroupEntry::new(1, &sampler),
+                    GpuBindGroupEntry::new(2, &output_view),
+                    GpuBindGroupEntry::new(4, &environment_view),
+                    GpuBindGroupEntry::new(5, &parameter_binding),
+                ];
+                let size = SPECULAR_SIZE >> mip_level;
+                Self::dispatch(device, &command_encoder, &pipeline, &entries, size, size);
+            }
+        }
+        // irradiance
+        let irradiance_texture = Self::create_cube_texture(device, IRRADIANCE_SIZE, 1);
+        {
+            let pipeline = Self::create_pipeline(device, &shader_module, "convolve_irradiance");
+            let output_view = Self::storage_view(&irradiance_texture, 0);
+            let entries = [
+                GpuBindGroupEntry::new(1, &sampler),
+                GpuBindGroupEntry::new(2, &output_view),
+                GpuBindGroupEntry::new(4, &environment_view),
+            ];
+            Self::dispatch(device, &command_encoder, &pipeline, &entries, IRRADIANCE_SIZE, IRRADIANCE_SIZE);
+        }
+        // BRDF lookup table
+        let brdf_lut_texture = Self::create_texture(device, BRDF_LUT_SIZE, 1, 1);
+        let brdf_lut_view = brdf_lut_texture.create_view();
+        {
+            let pipeline = Self::create_pipeline(device, &shader_module, "integrate_brdf");
+            let entries = [
+                GpuBindGroupEntry::new(6, &brdf_lut_view),
+            ];
+            let pass_encoder = command_encoder.begin_compute_pass();
+            Self::encode(device, &pass_encoder, &pipeline, &entries, BRDF_LUT_SIZE, BRDF_LUT_SIZE, 1);
+            pass_encoder.end();
+        }
+        // submit
+        let command_buffers: Vec<JsValue> = vec![command_encoder.finish().into()];
+        let command_buffers = command_buffers.into_iter().collect::<js_sys::Array>();
+        device.device().queue().submit(&command_buffers);
+        let this = Self {
+            environment_view,
+            specular_view: Self::cube_view(&specular_texture, SPECULAR_MIP_COUNT),
+            irradiance_view: Self::cube_view(&irradiance_texture, 1),
+            brdf_lut_view,
+            sampler,
+            available: true,
+        };
+        Arc::new(this)
+    }
+
+    // black maps so that the bindings stay valid without an environment
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn placeholder(device: &Arc<Device>) -> Arc<Self> {
+        let cube_texture = Self::create_cube_texture(device, 1, 1);
+        let brdf_lut_texture = Self::create_texture(device, 1, 1, 1);
+        let this = Self {
+            environment_view: Self::cube_view(&cube_texture, 1),
+            specular_view: Self::cube_view(&cube_texture, 1),
+            irradiance_view: Self::cube_view(&cube_texture, 1),
+            brdf_lut_view: brdf_lut_texture.create_view(),
+            sampler: Self::create_sampler(device),
+            available: false,
+        };
+        Arc::new(this)
+    }
+
+    fn create_equirect_texture(device: &Arc<Device>, environment: &Environment) -> GpuTexture {
+        let width = environment.width();
+        let height = environment.height();
+        let texture = Self::create_texture(device, width, height, 1);
+        // half floats keep the texture filterable
+        let pixels: Vec<u8> = environment.pixels().chunks(3)
+            .flat_map(|v| [v[0], v[1], v[2], 1.0])
+            .flat_map(|v| f32_to_f16(v).to_le_bytes())
+            .collect();
+        let destination = GpuImageCopyTexture::new(&texture);
+        let mut data_layout = GpuImageDataLayout::new();
+        data_layout.bytes_per_row(width * 8);
+        data_layout.rows_per_image(height);
+        let size = Self::extent(width, height, 1);
+        device.device().queue().write_texture_with_u8_array_and_u32_sequence(&destination, &pixels, &data_layout, &size);
+        texture
+    }
+
+    fn create_texture(device: &Arc<Device>, width: u32, height: u32, mip_level_count: u32) -> GpuTexture {
+        let size = Self::extent(width, height, 1);
+        let mut texture_descriptor = GpuTextureDescriptor::new(
+            GpuTextureFormat::Rgba16float,
+            &size,
+            gpu_texture_usage::TEXTURE_BINDING | gpu_texture_usage::STORAGE_BINDING | gpu_texture_usage::COPY_DST);
+        texture_descriptor.mip_level_count(mip_level_count);
+        device.device().create_texture(&texture_descriptor)
+    }
+
+    fn create_cube_texture(device: &Arc<Device>, size: u32, mip_level_count: u32) -> GpuTexture {
+        let extent = Self::extent(size, size, 6);
+        let mut texture_descriptor = GpuTextureDescriptor::new(
+            GpuTextureFormat::Rgba16float,
+            &extent,
+            gpu_texture_usage::TEXTURE_BINDING | gpu_texture_usage::STORAGE_BINDING);
+        texture_descriptor.mip_level_count(mip_level_count);
+        device.device().create_texture(&texture_descriptor)
+    }
+
+    // six faces of a single mip level
+    fn storage_view(texture: &GpuTexture, mip_level: u32) -> GpuTextureView {
+        let mut view_descriptor = GpuTextureViewDescriptor::new();
+        view_descriptor.dimension(GpuTextureViewDimension::N2dArray);
+        view_descriptor.base_mip_level(mip_level);
+        view_descriptor.mip_level_count(1);
+        view_descriptor.array_layer_count(6);
+        texture.create_view_with_descriptor(&view_descriptor)
+    }
+
+    fn cube_view(texture: &GpuTexture, mip_level_count: u32) -> GpuTextureView {
+        let mut view_descriptor = GpuTextureViewDescriptor::new();
+        view_descriptor.dimension(GpuTextureViewDimension::Cube);
+        view_descriptor.mip_level_count(mip_level_count);
+        texture.create_view_with_descriptor(&view_descriptor)
+    }
+
+    fn create_sampler(device: &Arc<Device>) -> GpuSampler {
+        let mut sampler_descriptor = GpuSamplerDescriptor::new();
+        sampler_descriptor.mag_filter(GpuFilterMode::Linear);
+        sampler_descriptor.min_filter(GpuFilterMode::Linear);
+        sampler_descriptor.mipmap_filter(GpuMipmapFilterMode::Linear);
+        device.device().create_sampler_with_descriptor(&sampler_descriptor)
+    }
+
+    fn create_parameter_buffer(device: &Arc<Device>, values: [f32; 4]) -> web_sys::GpuBuffer {
+        let mut buffer_descriptor = GpuBufferDescriptor::new(
+            std::mem::size_of_val(&values) as f64,
+            gpu_buffer_usage::UNIFORM);
+        buffer_descriptor.mapped_at_creation(true);
+        let buffer = device.device().create_buffer(&buffer_descriptor);
+        let array = js_sys::Float32Array::new(&buffer.get_mapped_range());
+        array.copy_from(&values);
+        buffer.unmap();
+        buffer
+    }
+
+    fn create_pipeline(device: &Arc<Device>, shader_module: &ShaderModule, entry_point: &str) -> GpuComputePipeline {
+        let stage = GpuProgrammableStage::new(entry_point, shader_module.shader_module());
+        let pipeline_descriptor = GpuComputePipelineDescriptor::new(&JsValue::from_str("auto"), &stage);
+        device.device().create_compute_pipeline(&pipeline_descriptor)
+    }
+
+    // a workgroup layer per cube face
+    fn dispatch(
+        device: &Arc<Device>,
+        command_encoder: &GpuCommandEncoder,
+        pipeline: &GpuComputePipeline,
+        entries: &[GpuBindGroupEntry],
+        width: u32,
+        height: u32,
+    ) {
+        let pass_encoder = command_encoder.begin_compute_pass();
+        Self::encode(device, &pass_encoder, pipeline, entries, width, height, 6);
+        pass_encoder.end();
+    }
+
+    fn encode(
+        device: &Arc<Device>,
+        pass_encoder: &web_sys::GpuComputePassEncoder,
+        pipeline: &GpuComputePipeline,
+        entries: &[GpuBindGroupEntry],
+        width: u32,
+        height: u32,
+        layers: u32,
+    ) {
+        let bind_entries = entries.iter()
+            .map(JsValue::from)
+            .collect::<js_sys::Array>();
+        let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &pipeline.get_bind_group_layout(0));
+        let bind_group = device.device().create_bind_group(&bind_group_descriptor);
+        pass_encoder.set_pipeline(pipeline);
+        pass_encoder.set_bind_group(0, Some(&bind_group));
+        pass_encoder.dispatch_workgroups_with_workgroup_count_y_and_workgroup_count_z(
+            width.div_ceil(8), height.div_ceil(8), layers);
+    }
+
+    fn extent(width: u32, height: u32, layers: u32) -> js_sys::Array {
+        let extent: Vec<JsValue> = vec![width as f64, height as f64, layers as f64].into_iter().map(Into::into).collect();
+        extent.into_iter().collect::<js_sys::Array>()
+    }
+
+    // false for the black placeholder
+    pub fn available(&self) -> bool {
+        self.available
+    }
+
+    pub fn environment_view(&self) -> &GpuTextureView {
+        &self.environment_view
+    }
+
+    pub fn specular_view(&self) -> &GpuTextureView {
+        &self.specular_view
+    }
+
+    pub fn specular_mip_count(&self) -> u32 {
+        if self.available { SPECULAR_MIP_COUNT } else { 1 }
+    }
+
+    pub fn irradiance_view(&self) -> &GpuTextureView {
+        &self.irradiance_view
+    }
+
+    pub fn brdf_lut_view(&self) -> &GpuTextureView {
+        &self.brdf_lut_view
+    }
+
+    pub fn sampler(&self) -> &GpuSampler {
+        &self.sampler
+    }
+}
+
+// IEEE half precision, rounding to nearest and saturating to infinity
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign
+        }
+        // subnormal
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = (mantissa >> shift) + ((mantissa >> (shift - 1)) & 1);
+        return sign | half as u16
+    }
+    if exponent >= 0x1f {
+        // infinity and NaN
+        let nan = if (bits & 0x7fff_ffff) > 0x7f80_0000 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan
+    }
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    // the carry of the rounding may overflow into the exponent, which stays correct
+    let half = half + ((mantissa >> 12) & 1);
+    sign | half.min(0x7c00) as u16
+}
@@ -1,5 +1,4 @@
 
-
 use std::sync::Arc;
 
 use super::PipelineLayouts;
@@ -10,43 +9,149 @@ use wasm_bindgen::{prelude::*};
 
 use web_sys::{
     GpuPipelineLayoutDescriptor,
+    GpuBindGroupLayout,
     GpuBindGroupLayoutEntry,
     gpu_shader_stage,
     GpuBufferBindingLayout,
     GpuBindGroupLayoutDescriptor,
     GpuBufferBindingType,
+    GpuTextureBindingLayout,
+    GpuSamplerBindingLayout,
+    GpuSamplerBindingType,
+    GpuTextureSampleType,
+    GpuTextureViewDimension,
 };
 
 impl PipelineLayouts {
     pub fn common(device: &Arc<Device>) -> Arc<PipelineLayout> {
-        // primary
-        let primary_layout = {
-            let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::VERTEX);
-            let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
-            buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
-            bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
-            let bind_group_layout_entries: Vec<JsValue> = vec![bind_group_layout_entry.into()];
-            let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
-            let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
-            let bind_group_layout = device.device().create_bind_group_layout(&bind_group_layout_descriptor);
-            bind_group_layout
-        };
-        // secondary 
-        let secondary_layout = {
-            let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::VERTEX);
-            let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
-            buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
-            bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
-            let bind_group_layout_entries: Vec<JsValue> = vec![bind_group_layout_entry.into()];
-            let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
-            let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
-            let bind_group_layout = device.device().create_bind_group_layout(&bind_group_layout_descriptor);
-            bind_group_layout
-        };
-        let bind_group_layouts: Vec<JsValue> = vec![primary_layout.into(), secondary_layout.into()];
+        let bind_group_layouts: Vec<JsValue> = Self::common_bind_group_layouts(device);
+        Self::with_bind_group_layouts(device, bind_group_layouts)
+    }
+
+    // common layout followed by the joint matrices, morph targets and weights of deformed meshes
+    pub fn deformed(device: &Arc<Device>) -> Arc<PipelineLayout> {
+        let bind_group_layouts: Vec<JsValue> = Self::common_bind_group_layouts(device).into_iter()
+            .chain(std::iter::once(Self::deformation_bind_group_layout(device).into()))
+            .collect();
+        Self::with_bind_group_layouts(device, bind_group_layouts)
+    }
+
+    // the ground plane reads the primary bind group only
+    pub fn ground(device: &Arc<Device>) -> Arc<PipelineLayout> {
+        let bind_group_layouts: Vec<JsValue> = vec![Self::primary_bind_group_layout(device).into()];
+        Self::with_bind_group_layouts(device, bind_group_layouts)
+    }
+
+    pub(super) fn with_bind_group_layouts(device: &Arc<Device>, bind_group_layouts: Vec<JsValue>) -> Arc<PipelineLayout> {
         let bind_group_layouts = bind_group_layouts.into_iter().collect::<js_sys::Array>();
         let layout_descriptor = GpuPipelineLayoutDescriptor::new(&bind_group_layouts);
         let layout = device.device().create_pipeline_layout(&layout_descriptor);
         PipelineLayout::new(device, layout)
     }
+
+    fn common_bind_group_layouts(device: &Arc<Device>) -> Vec<JsValue> {
+        vec![
+            Self::primary_bind_group_layout(device).into(),
+            Self::secondary_bind_group_layout(device).into(),
+            Self::material_bind_group_layout(device).into(),
+        ]
+    }
+
+    fn primary_bind_group_layout(device: &Arc<Device>) -> GpuBindGroupLayout {
+        let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::VERTEX | gpu_shader_stage::FRAGMENT);
+        let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
+        buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
+        bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
+        // lights
+        let mut light_layout_entry = GpuBindGroupLayoutEntry::new(1, gpu_shader_stage::FRAGMENT);
+        let mut light_buffer_layout_entry = GpuBufferBindingLayout::new();
+        light_buffer_layout_entry.type_(GpuBufferBindingType::ReadOnlyStorage);
+        light_layout_entry.buffer(&light_buffer_layout_entry);
+        // environment specular, irradiance, BRDF lookup table and sampler
+        let environment_layout_entries = [GpuTextureViewDimension::Cube, GpuTextureViewDimension::Cube, GpuTextureViewDimension::N2d]
+            .into_iter()
+            .enumerate()
+            .map(|(index, view_dimension)| {
+                let mut texture_layout_entry = GpuBindGroupLayoutEntry::new(2 + index as u32, gpu_shader_stage::FRAGMENT);
+                let mut texture_binding_layout = GpuTextureBindingLayout::new();
+                texture_binding_layout.view_dimension(view_dimension);
+                texture_layout_entry.texture(&texture_binding_layout);
+                texture_layout_entry
+            });
+        let mut sampler_layout_entry = GpuBindGroupLayoutEntry::new(5, gpu_shader_stage::FRAGMENT);
+        sampler_layout_entry.sampler(&GpuSamplerBindingLayout::new());
+        // shadow map and comparison sampler
+        let mut shadow_layout_entry = GpuBindGroupLayoutEntry::new(6, gpu_shader_stage::FRAGMENT);
+        let mut shadow_binding_layout = GpuTextureBindingLayout::new();
+        shadow_binding_layout.sample_type(GpuTextureSampleType::Depth);
+        shadow_binding_layout.view_dimension(GpuTextureViewDimension::N2dArray);
+        shadow_layout_entry.texture(&shadow_binding_layout);
+        let mut shadow_sampler_layout_entry = GpuBindGroupLayoutEntry::new(7, gpu_shader_stage::FRAGMENT);
+        let mut shadow_sampler_binding_layout = GpuSamplerBindingLayout::new();
+        shadow_sampler_binding_layout.type_(GpuSamplerBindingType::Comparison);
+        shadow_sampler_layout_entry.sampler(&shadow_sampler_binding_layout);
+        // screen-space ambient occlusion of the frame
+        let mut ambient_occlusion_layout_entry = GpuBindGroupLayoutEntry::new(8, gpu_shader_stage::FRAGMENT);
+        ambient_occlusion_layout_entry.texture(&GpuTextureBindingLayout::new());
+        let bind_group_layout_entries: Vec<JsValue> = [bind_group_layout_entry, light_layout_entry].into_iter()
+            .chain(environment_layout_entries)
+            .chain([sampler_layout_entry, shadow_layout_entry, shadow_sampler_layout_entry, ambient_occlusion_layout_entry])
+            .map(Into::into)
+            .collect();
+        let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
+        device.device().create_bind_group_layout(&bind_group_layout_descriptor)
+    }
+
+    // per node transforms
+    pub(super) fn secondary_bind_group_layout(device: &Arc<Device>) -> GpuBindGroupLayout {
+        let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::VERTEX);
+        let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
+        buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
+        bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
+        let bind_group_layout_entries: Vec<JsValue> = vec![bind_group_layout_entry.into()];
+        let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
+        device.device().create_bind_group_layout(&bind_group_layout_descriptor)
+    }
+
+    pub(super) fn material_bind_group_layout(device: &Arc<Device>) -> GpuBindGroupLayout {
+        let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::FRAGMENT);
+        let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
+        buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
+        bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
+        // base color, metallic roughness, normal, occlusion and emissive
+        let texture_count = 5;
+        let texture_layout_entries = (0..texture_count)
+            .flat_map(|slot_index| {
+                let binding = 1 + slot_index * 2;
+                let mut texture_layout_entry = GpuBindGroupLayoutEntry::new(binding, gpu_shader_stage::FRAGMENT);
+                texture_layout_entry.texture(&GpuTextureBindingLayout::new());
+                let mut sampler_layout_entry = GpuBindGroupLayoutEntry::new(binding + 1, gpu_shader_stage::FRAGMENT);
+                sampler_layout_entry.sampler(&GpuSamplerBindingLayout::new());
+                [texture_layout_entry, sampler_layout_entry]
+            });
+        let bind_group_layout_entries: Vec<JsValue> = std::iter::once(bind_group_layout_entry)
+            .chain(texture_layout_entries)
+            .map(Into::into)
+            .collect();
+        let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
+        device.device().create_bind_group_layout(&bind_group_layout_descriptor)
+    }
+
+    pub(super) fn deformation_bind_group_layout(device: &Arc<Device>) -> GpuBindGroupLayout {
+        let bind_group_layout_entries: Vec<JsValue> = (0..3)
+            .map(|binding| {
+                let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(binding, gpu_shader_stage::VERTEX);
+                let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
+                buffer_bind_group_layout_entry.type_(GpuBufferBindingType::ReadOnlyStorage);
+                bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
+                bind_group_layout_entry.into()
+            })
+            .collect();
+        let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
+        device.device().create_bind_group_layout(&bind_group_layout_descriptor)
+    }
 }
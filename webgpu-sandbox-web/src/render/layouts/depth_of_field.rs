@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use super::PipelineLayouts;
+use crate::render::device::Device;
+use crate::render::layout::PipelineLayout;
+
+use wasm_bindgen::{prelude::*};
+
+use web_sys::{
+    GpuBindGroupLayoutEntry,
+    gpu_shader_stage,
+    GpuBufferBindingLayout,
+    GpuBindGroupLayoutDescriptor,
+    GpuBufferBindingType,
+    GpuTextureBindingLayout,
+    GpuTextureSampleType,
+    GpuSamplerBindingLayout,
+};
+
+impl PipelineLayouts {
+    // lens settings, a linear sampler, the HDR image and the depth of the prepass
+    pub fn depth_of_field(device: &Arc<Device>) -> Arc<PipelineLayout> {
+        let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::FRAGMENT);
+        let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
+        buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
+        bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
+        let mut sampler_layout_entry = GpuBindGroupLayoutEntry::new(1, gpu_shader_stage::FRAGMENT);
+        sampler_layout_entry.sampler(&GpuSamplerBindingLayout::new());
+        let mut color_layout_entry = GpuBindGroupLayoutEntry::new(2, gpu_shader_stage::FRAGMENT);
+        color_layout_entry.texture(&GpuTextureBindingLayout::new());
+        let mut depth_layout_entry = GpuBindGroupLayoutEntry::new(3, gpu_shader_stage::FRAGMENT);
+        let mut depth_binding_layout = GpuTextureBindingLayout::new();
+        depth_binding_layout.sample_type(GpuTextureSampleType::Depth);
+        depth_layout_entry.texture(&depth_binding_layout);
+        let bind_group_layout_entries: Vec<JsValue> = [bind_group_layout_entry, sampler_layout_entry, color_layout_entry, depth_layout_entry].into_iter()
+            .map(Into::into)
+            .collect();
+        let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
+        let bind_group_layout = device.device().create_bind_group_layout(&bind_group_layout_descriptor);
+        let bind_group_layouts: Vec<JsValue> = vec![bind_group_layout.into()];
+        Self::with_bind_group_layouts(device, bind_group_layouts)
+    }
+}
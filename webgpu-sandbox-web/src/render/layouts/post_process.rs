@@ -0,0 +1,45 @@
+
+use std::sync::Arc;
+
+use super::PipelineLayouts;
+use crate::render::device::Device;
+use crate::render::layout::PipelineLayout;
+
+use wasm_bindgen::{prelude::*};
+
+use web_sys::{
+    GpuBindGroupLayoutEntry,
+    gpu_shader_stage,
+    GpuBufferBindingLayout,
+    GpuBindGroupLayoutDescriptor,
+    GpuBufferBindingType,
+    GpuTextureBindingLayout,
+    GpuSamplerBindingLayout,
+};
+
+impl PipelineLayouts {
+    // settings, a linear sampler, the source image and the bloom shared by every fullscreen pass
+    pub fn post_process(device: &Arc<Device>) -> Arc<PipelineLayout> {
+        let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::FRAGMENT);
+        let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
+        buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
+        bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
+        let mut sampler_layout_entry = GpuBindGroupLayoutEntry::new(1, gpu_shader_stage::FRAGMENT);
+        sampler_layout_entry.sampler(&GpuSamplerBindingLayout::new());
+        let texture_layout_entries = (0..2)
+            .map(|index| {
+                let mut texture_layout_entry = GpuBindGroupLayoutEntry::new(2 + index, gpu_shader_stage::FRAGMENT);
+                texture_layout_entry.texture(&GpuTextureBindingLayout::new());
+                texture_layout_entry
+            });
+        let bind_group_layout_entries: Vec<JsValue> = [bind_group_layout_entry, sampler_layout_entry].into_iter()
+            .chain(texture_layout_entries)
+            .map(Into::into)
+            .collect();
+        let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
+        let bind_group_layout = device.device().create_bind_group_layout(&bind_group_layout_descriptor);
+        let bind_group_layouts: Vec<JsValue> = vec![bind_group_layout.into()];
+        Self::with_bind_group_layouts(device, bind_group_layouts)
+    }
+}
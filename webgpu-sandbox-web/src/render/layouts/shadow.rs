@@ -0,0 +1,61 @@
+
+use std::sync::Arc;
+
+use super::PipelineLayouts;
+use crate::render::device::Device;
+use crate::render::layout::PipelineLayout;
+
+use wasm_bindgen::{prelude::*};
+
+use web_sys::{
+    GpuBindGroupLayout,
+    GpuBindGroupLayoutEntry,
+    gpu_shader_stage,
+    GpuBufferBindingLayout,
+    GpuBindGroupLayoutDescriptor,
+    GpuBufferBindingType,
+};
+
+impl PipelineLayouts {
+    // depth only pass of the mesh vertex stages. the primary group holds just the uniform with
+    // the light projection, the node transforms and deformations stay compatible with the mesh
+    // pipelines and the material group is left empty.
+    pub fn shadow(device: &Arc<Device>, deformed: bool) -> Arc<PipelineLayout> {
+        let mut bind_group_layouts: Vec<JsValue> = vec![
+            Self::shadow_primary_bind_group_layout(device).into(),
+            Self::secondary_bind_group_layout(device).into(),
+        ];
+        if deformed {
+            let empty_entries = js_sys::Array::new();
+            let empty_layout = device.device().create_bind_group_layout(&GpuBindGroupLayoutDescriptor::new(&empty_entries));
+            bind_group_layouts.push(empty_layout.into());
+            bind_group_layouts.push(Self::deformation_bind_group_layout(device).into());
+        }
+        Self::with_bind_group_layouts(device, bind_group_layouts)
+    }
+
+    // depth prepass from the camera. like the shadow pass, with the material group so masked
+    // materials can test their alpha.
+    pub fn prepass(device: &Arc<Device>, deformed: bool) -> Arc<PipelineLayout> {
+        let mut bind_group_layouts: Vec<JsValue> = vec![
+            Self::shadow_primary_bind_group_layout(device).into(),
+            Self::secondary_bind_group_layout(device).into(),
+            Self::material_bind_group_layout(device).into(),
+        ];
+        if deformed {
+            bind_group_layouts.push(Self::deformation_bind_group_layout(device).into());
+        }
+        Self::with_bind_group_layouts(device, bind_group_layouts)
+    }
+
+    fn shadow_primary_bind_group_layout(device: &Arc<Device>) -> GpuBindGroupLayout {
+        let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::VERTEX | gpu_shader_stage::FRAGMENT);
+        let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
+        buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
+        bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
+        let bind_group_layout_entries: Vec<JsValue> = vec![bind_group_layout_entry.into()];
+        let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
+        device.device().create_bind_group_layout(&bind_group_layout_descriptor)
+    }
+}
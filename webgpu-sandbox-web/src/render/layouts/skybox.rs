@@ -14,16 +14,39 @@ use web_sys::{
     GpuBufferBindingLayout,
     GpuBindGroupLayoutDescriptor,
     GpuBufferBindingType,
+    GpuTextureBindingLayout,
+    GpuSamplerBindingLayout,
+    GpuTextureViewDimension,
 };
 
 impl PipelineLayouts {
     pub fn skybox(device: &Arc<Device>) -> Arc<PipelineLayout> {
         // layout
-        let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::VERTEX);
+        let mut bind_group_layout_entry = GpuBindGroupLayoutEntry::new(0, gpu_shader_stage::VERTEX | gpu_shader_stage::FRAGMENT);
         let mut buffer_bind_group_layout_entry = GpuBufferBindingLayout::new();
         buffer_bind_group_layout_entry.type_(GpuBufferBindingType::Uniform);
         bind_group_layout_entry.buffer(&buffer_bind_group_layout_entry);
-        let bind_group_layout_entries: Vec<JsValue> = vec![bind_group_layout_entry.into()];
+        // environment cubemap
+        let mut texture_layout_entry = GpuBindGroupLayoutEntry::new(1, gpu_shader_stage::FRAGMENT);
+        let mut texture_binding_layout = GpuTextureBindingLayout::new();
+        texture_binding_layout.view_dimension(GpuTextureViewDimension::Cube);
+        texture_layout_entry.texture(&texture_binding_layout);
+        let mut sampler_layout_entry = GpuBindGroupLayoutEntry::new(2, gpu_shader_stage::FRAGMENT);
+        sampler_layout_entry.sampler(&GpuSamplerBindingLayout::new());
+        // cubemap skybox
+        let mut cubemap_layout_entry = GpuBindGroupLayoutEntry::new(3, gpu_shader_stage::FRAGMENT);
+        let mut cubemap_binding_layout = GpuTextureBindingLayout::new();
+        cubemap_binding_layout.view_dimension(GpuTextureViewDimension::Cube);
+        cubemap_layout_entry.texture(&cubemap_binding_layout);
+        let mut cubemap_sampler_layout_entry = GpuBindGroupLayoutEntry::new(4, gpu_shader_stage::FRAGMENT);
+        cubemap_sampler_layout_entry.sampler(&GpuSamplerBindingLayout::new());
+        let bind_group_layout_entries: Vec<JsValue> = vec![
+            bind_group_layout_entry.into(),
+            texture_layout_entry.into(),
+            sampler_layout_entry.into(),
+            cubemap_layout_entry.into(),
+            cubemap_sampler_layout_entry.into(),
+        ];
         let bind_group_layout_entries = bind_group_layout_entries.into_iter().collect::<js_sys::Array>();
         let bind_group_layout_descriptor = GpuBindGroupLayoutDescriptor::new(&bind_group_layout_entries);
         let bind_group_layout = device.device().create_bind_group_layout(&bind_group_layout_descriptor);
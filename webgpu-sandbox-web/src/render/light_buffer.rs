@@ -0,0 +1,115 @@
+
+use std::sync::Arc;
+
+use crate::asset::{Light, LightKind};
+use crate::skybox::Sun;
+use crate::render::device::Device;
+
+use nalgebra_glm as glm;
+
+use web_sys::{
+    GpuBufferDescriptor,
+    gpu_buffer_usage,
+    GpuBuffer,
+};
+
+// storage buffer of the scene lights: the light count followed by the lights
+// in world space, the procedural sun last. the shader falls back to a headlight when the
+// count is zero.
+pub struct LightBuffer {
+    device: Arc<Device>,
+    storage_buffer: GpuBuffer,
+    capacity: usize,
+}
+
+impl LightBuffer {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>, capacity: usize) -> Arc<Self> {
+        // one more slot for the sun
+        let size = Self::size(capacity + 1);
+        let storage_buffer_descriptor = GpuBufferDescriptor::new(
+            size as f64,
+            gpu_buffer_usage::STORAGE | gpu_buffer_usage::COPY_DST);
+        let storage_buffer = device.device().create_buffer(&storage_buffer_descriptor);
+        let this = Self {
+            device: Arc::clone(device),
+            storage_buffer,
+            capacity,
+        };
+        Arc::new(this)
+    }
+
+    fn size(light_count: usize) -> usize {
+        std::mem::size_of::<[u32; 4]>() + std::mem::size_of::<LightData>() * light_count
+    }
+
+    pub fn buffer(&self) -> &GpuBuffer {
+        &self.storage_buffer
+    }
+
+    // lights paired with the world transforms of their nodes
+    pub fn write(&self, lights: &[(Arc<Light>, glm::Mat4)], sun: Option<&Sun>) {
+        let lights = &lights[..lights.len().min(self.capacity)];
+        let light_data: Vec<LightData> = lights.iter()
+            .map(|(light, transform)| LightData::new(light, transform))
+            .chain(sun.map(LightData::with_sun))
+            .collect();
+        let header: [u32; 4] = [light_data.len() as u32, 0, 0, 0];
+        let header_bytes = unsafe {
+            std::slice::from_raw_parts(header.as_ptr() as *const u8, std::mem::size_of_val(&header))
+        };
+        let light_bytes = unsafe {
+            std::slice::from_raw_parts(light_data.as_ptr() as *const u8, std::mem::size_of::<LightData>() * light_data.len())
+        };
+        let bytes = [header_bytes, light_bytes].concat();
+        let array = js_sys::Uint8Array::new_with_length(bytes.len() as u32);
+        array.copy_from(&bytes);
+        let queue = self.device.device().queue();
+        queue.write_buffer_with_u32_and_buffer_source(&self.storage_buffer, 0, &array);
+    }
+}
+
+#[repr(C)]
+struct LightData {
+    // xyz position, light type
+    pub position: glm::Vec4,
+    // xyz direction, range or zero when infinite
+    pub direction: glm::Vec4,
+    // rgb color premultiplied by the intensity
+    pub color: glm::Vec4,
+    // cosines of the inner and outer cone angles
+    pub cone: glm::Vec4,
+}
+
+impl LightData {
+    fn new(light: &Light, transform: &glm::Mat4) -> Self {
+        let position = transform * glm::vec4(0.0, 0.0, 0.0, 1.0);
+        let direction = glm::normalize(&(transform * glm::vec4(0.0, 0.0, -1.0, 0.0)).xyz());
+        let (light_type, cone) = match light.kind() {
+            LightKind::Directional => (0.0, glm::vec4(0.0, 0.0, 0.0, 0.0)),
+            LightKind::Point => (1.0, glm::vec4(0.0, 0.0, 0.0, 0.0)),
+            LightKind::Spot { inner_cone_angle, outer_cone_angle } => {
+                (2.0, glm::vec4(inner_cone_angle.cos(), outer_cone_angle.cos(), 0.0, 0.0))
+            },
+        };
+        let color = light.color() * light.intensity();
+        Self {
+            position: glm::vec4(position.x, position.y, position.z, light_type),
+            direction: glm::vec4(direction.x, direction.y, direction.z, light.range().unwrap_or(0.0)),
+            color: glm::vec4(color.x, color.y, color.z, 0.0),
+            cone,
+        }
+    }
+
+    // directional light shining away from the sun
+    fn with_sun(sun: &Sun) -> Self {
+        let direction = -sun.direction();
+        let color = sun.radiance();
+        Self {
+            position: glm::vec4(0.0, 0.0, 0.0, 0.0),
+            direction: glm::vec4(direction.x, direction.y, direction.z, 0.0),
+            color: glm::vec4(color.x, color.y, color.z, 0.0),
+            cone: glm::vec4(0.0, 0.0, 0.0, 0.0),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+
+use std::sync::Arc;
+
+use crate::asset::{Material, AlphaMode, TextureReference};
+use crate::render::device::Device;
+use crate::render::texture::{Texture, TextureCache};
+
+use nalgebra_glm as glm;
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuBufferDescriptor,
+    gpu_buffer_usage,
+    GpuBindGroup,
+    GpuBindGroupLayout,
+    GpuBindGroupDescriptor,
+    GpuBindGroupEntry,
+    GpuBufferBinding,
+};
+
+// material bind group: uniform followed by texture and sampler pairs of
+// base color, metallic roughness, normal, occlusion and emissive
+pub struct MaterialBuffer {
+    bind_group: GpuBindGroup,
+}
+
+impl MaterialBuffer {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>, material: &Material, layout: &GpuBindGroupLayout, texture_cache: &TextureCache) -> Arc<Self> {
+        // uniform
+        let size = std::mem::size_of::<MaterialData>();
+        let mut uniform_buffer_descriptor = GpuBufferDescriptor::new(
+            size as f64,
+            gpu_buffer_usage::UNIFORM);
+        uniform_buffer_descriptor.mapped_at_creation(true);
+        let uniform_buffer = device.device().create_buffer(&uniform_buffer_descriptor);
+        let uniform_data = MaterialData::new(material);
+        let ptr = (&uniform_data as *const _) as *const u8;
+        let slice = unsafe {
+            std::slice::from_raw_parts(ptr, size)
+        };
+        let uniform_array = js_sys::Uint8Array::new(&uniform_buffer.get_mapped_range());
+        uniform_array.copy_from(slice);
+        uniform_buffer.unmap();
+        // entries
+        let buffer_binding = GpuBufferBinding::new(&uniform_buffer);
+        let buffer_binding: JsValue = buffer_binding.into();
+        let buffer_bind_entry = GpuBindGroupEntry::new(0, &buffer_binding);
+        // textures
+        let texture = |reference: Option<&TextureReference>, srgb: bool, fallback: &Arc<Texture>| {
+            reference
+                .map(|v| texture_cache.texture(v.texture(), srgb))
+                .unwrap_or_else(|| Arc::clone(fallback))
+        };
+        let textures = [
+            texture(material.base_color_texture(), true, texture_cache.white()),
+            texture(material.metallic_roughness_texture(), false, texture_cache.white()),
+            texture(material.normal_texture(), false, texture_cache.flat_normal()),
+            texture(material.occlusion_texture(), false, texture_cache.white()),
+            texture(material.emissive_texture(), true, texture_cache.white()),
+        ];
+        let texture_bind_entries = textures.iter()
+            .enumerate()
+            .flat_map(|(slot_index, texture)| {
+                let binding = 1 + (slot_index as u32) * 2;
+                let view_bind_entry = GpuBindGroupEntry::new(binding, texture.view());
+                let sampler_bind_entry = GpuBindGroupEntry::new(binding + 1, texture.sampler());
+                [view_bind_entry, sampler_bind_entry]
+            });
+        let bind_entries: Vec<JsValue> = std::iter::once(buffer_bind_entry)
+            .chain(texture_bind_entries)
+            .map(Into::into)
+            .collect();
+        let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, layout);
+        let bind_group = device.device().create_bind_group(&bind_group_descriptor);
+        // this
+        let this = Self {
+            bind_group,
+        };
+        Arc::new(this)
+    }
+
+    pub fn bind_group(&self) -> &GpuBindGroup {
+        &self.bind_group
+    }
+}
+
+#[repr(C)]
+struct MaterialData {
+    pub base_color_factor: glm::Vec4,
+    pub emissive_factor: glm::Vec4,
+    // metallic, roughness, alpha cutoff, alpha mode
+    pub parameters: glm::Vec4,
+    // texture coordinate sets of base color, metallic roughness, normal and occlusion
+    pub tex_coords: glm::Vec4,
+    // emissive texture coordinate set, normal scale, occlusion strength
+    pub texture_parameters: glm::Vec4,
+}
+
+impl MaterialData {
+    fn new(material: &Material) -> Self {
+        let emissive_factor = material.emissive_factor();
+        let alpha_mode = match material.alpha_mode() {
+            AlphaMode::Opaque => 0.0,
+            AlphaMode::Mask => 1.0,
+            AlphaMode::Blend => 2.0,
+        };
+        let tex_coord = |reference: Option<&TextureReference>| {
+            reference.map(|v| v.tex_coord() as f32).unwrap_or(0.0)
+        };
+        Self {
+            base_color_factor: *material.base_color_factor(),
+            emissive_factor: glm::vec4(emissive_factor.x, emissive_factor.y, emissive_factor.z, 0.0),
+            parameters: glm::vec4(
+                material.metallic_factor(),
+                material.roughness_factor(),
+                material.alpha_cutoff(),
+                alpha_mode),
+            tex_coords: glm::vec4(
+                tex_coord(material.base_color_texture()),
+                tex_coord(material.metallic_roughness_texture()),
+                tex_coord(material.normal_texture()),
+                tex_coord(material.occlusion_texture())),
+            texture_parameters: glm::vec4(
+                tex_coord(material.emissive_texture()),
+                material.normal_scale(),
+                material.occlusion_strength(),
+                0.0),
+        }
+    }
+}
@@ -4,15 +4,37 @@ mod surface;
 mod shader;
 mod shaders;
 mod vertex_buffer;
+mod material_buffer;
+mod light_buffer;
+mod environment_map;
+mod skybox_texture;
+mod shadow_map;
+mod texture;
 mod vertices;
 mod layout;
 mod layouts;
+mod render_graph;
+mod ambient_occlusion;
+mod temporal_antialiasing;
+mod depth_of_field;
+mod post_process;
 
 pub use device::*;
 pub use surface::*;
 pub use shader::*;
 pub use shaders::*;
 pub use vertex_buffer::*;
+pub use material_buffer::*;
+pub use light_buffer::*;
+pub use environment_map::*;
+pub use skybox_texture::*;
+pub use shadow_map::*;
+pub use texture::*;
 pub use vertices::*;
 pub use layout::*;
 pub use layouts::*;
+pub use render_graph::*;
+pub use ambient_occlusion::*;
+pub use temporal_antialiasing::*;
+pub use depth_of_field::*;
+pub use post_process::*;
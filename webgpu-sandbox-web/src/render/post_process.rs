@@ -0,0 +1,255 @@
+use std::sync::{Arc, Mutex};
+
+use crate::render::{
+    Device,
+    Shaders,
+    PipelineLayouts,
+    RenderGraph,
+    PassDescription,
+    ColorAttachment,
+    TextureHandle,
+    TextureDescription,
+};
+use crate::post_process::{PostProcessSettings, Tonemapping};
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuTextureFormat,
+    GpuRenderPipelineDescriptor,
+    GpuVertexState,
+    GpuFragmentState,
+    GpuColorTargetState,
+    GpuPrimitiveState,
+    GpuPrimitiveTopology,
+    GpuBufferDescriptor,
+    gpu_buffer_usage,
+    GpuBindGroupDescriptor,
+    GpuBindGroupEntry,
+    GpuBufferBinding,
+    GpuBindGroup,
+    GpuRenderPipeline,
+    GpuBuffer,
+    GpuBlendState,
+    GpuBlendComponent,
+    GpuBlendFactor,
+    GpuBlendOperation,
+    GpuTextureDescriptor,
+    gpu_texture_usage,
+    GpuTextureView,
+    GpuSampler,
+    GpuSamplerDescriptor,
+    GpuFilterMode,
+};
+
+// the scene is lit and blended in linear HDR values, then tonemapped into the swapchain
+pub const HDR_FORMAT: GpuTextureFormat = GpuTextureFormat::Rgba16float;
+
+// bloom, tonemapping and the finishing passes from the HDR scene into the presentation format
+pub struct PostProcessPasses {
+    device: Arc<Device>,
+    presentation_format: GpuTextureFormat,
+    pipelines: PostProcessPipelines,
+    uniform_buffer: GpuBuffer,
+    sampler: GpuSampler,
+    // black stand-in for the bloom when it is off
+    empty_view: GpuTextureView,
+    frame_count: Mutex<u32>,
+}
+
+impl PostProcessPasses {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>, presentation_format: GpuTextureFormat) -> Arc<Self> {
+        let shader_module = Shaders::post_process(device);
+        let layout = PipelineLayouts::post_process(device);
+        // fullscreen triangles without vertex buffers
+        let create_render_pipeline = |entry_point: &str, format: GpuTextureFormat, blend: Option<&GpuBlendState>| {
+            let vertex_state = GpuVertexState::new("vert_main", shader_module.shader_module());
+            let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
+            let mut target = GpuColorTargetState::new(format);
+            if let Some(blend) = blend {
+                target.blend(blend);
+            }
+            let fragment_targets: Vec<JsValue> = vec![target.into()];
+            let fragment_targets = fragment_targets.into_iter().collect::<js_sys::Array>();
+            let fragment_state = GpuFragmentState::new(entry_point, shader_module.shader_module(), &fragment_targets);
+            render_descriptor.fragment(&fragment_state);
+            let mut primitive_state = GpuPrimitiveState::new();
+            primitive_state.topology(GpuPrimitiveTopology::TriangleList);
+            render_descriptor.primitive(&primitive_state);
+            device.device().create_render_pipeline(&render_descriptor)
+        };
+        // each upsampled level is added onto the downsampled level above
+        let mut additive = GpuBlendComponent::new();
+        additive.operation(GpuBlendOperation::Add);
+        additive.src_factor(GpuBlendFactor::One);
+        additive.dst_factor(GpuBlendFactor::One);
+        let additive = GpuBlendState::new(&additive, &additive);
+        let pipelines = PostProcessPipelines {
+            bloom_prefilter: create_render_pipeline("frag_bloom_prefilter_main", HDR_FORMAT, None),
+            bloom_downsample: create_render_pipeline("frag_bloom_downsample_main", HDR_FORMAT, None),
+            bloom_upsample: create_render_pipeline("frag_bloom_upsample_main", HDR_FORMAT, Some(&additive)),
+            tonemap: create_render_pipeline("frag_tonemap_main", presentation_format, None),
+            fxaa: create_render_pipeline("frag_fxaa_main", presentation_format, None),
+        };
+
+        // uniform: tone and finish
+        let uniform_buffer_descriptor = GpuBufferDescriptor::new(
+            (std::mem::size_of::<f32>() * (4 + 4)) as f64,
+            gpu_buffer_usage::UNIFORM | gpu_buffer_usage::COPY_DST);
+        let uniform_buffer = device.device().create_buffer(&uniform_buffer_descriptor);
+        // filtered taps between texels, clamped at the borders
+        let mut sampler_descriptor = GpuSamplerDescriptor::new();
+        sampler_descriptor.mag_filter(GpuFilterMode::Linear);
+        sampler_descriptor.min_filter(GpuFilterMode::Linear);
+        let sampler = device.device().create_sampler_with_descriptor(&sampler_descriptor);
+        let extent: Vec<JsValue> = vec![1.0, 1.0].into_iter().map(|v: f64| v.into()).collect();
+        let extent = extent.into_iter().collect::<js_sys::Array>();
+        let texture_descriptor = GpuTextureDescriptor::new(HDR_FORMAT, &extent, gpu_texture_usage::TEXTURE_BINDING);
+        let empty_view = device.device().create_texture(&texture_descriptor).create_view();
+        let this = Self {
+            device: Arc::clone(device),
+            presentation_format,
+            pipelines,
+            uniform_buffer,
+            sampler,
+            empty_view,
+            frame_count: Mutex::new(0),
+        };
+        Arc::new(this)
+    }
+
+    // tonemaps the scene into the target, which takes the presentation format
+    pub fn add_passes(&self,
+        graph: &mut RenderGraph,
+        scene: TextureHandle,
+        target: TextureHandle,
+        settings: &PostProcessSettings) {
+        let Some(description) = graph.texture_description(scene) else { return };
+
+        // bloom from a chain of halved levels, blurred back up into the first one
+        let mut bloom_levels = vec![];
+        if settings.bloom {
+            let mut width = description.width >> 1;
+            let mut height = description.height >> 1;
+            while bloom_levels.len() < 6 && width >= 8 && height >= 8 {
+                bloom_levels.push(graph.create_texture("bloom", TextureDescription::new(HDR_FORMAT, width, height)));
+                width >>= 1;
+                height >>= 1;
+            }
+        }
+        let mut source = scene;
+        for (level_index, bloom_level) in bloom_levels.iter().enumerate() {
+            let render_pipeline = if level_index == 0 { &self.pipelines.bloom_prefilter } else { &self.pipelines.bloom_downsample };
+            let pass = PassDescription::new("bloom downsample")
+                .color(ColorAttachment::new(*bloom_level));
+            self.add_pass(graph, pass, render_pipeline, source, None);
+            source = *bloom_level;
+        }
+        for level in bloom_levels.windows(2).rev() {
+            let pass = PassDescription::new("bloom upsample")
+                .color(ColorAttachment::new(level[0]));
+            self.add_pass(graph, pass, &self.pipelines.bloom_upsample, level[1], None);
+        }
+
+        // FXAA works on display values, so the tonemapped image goes through another target
+        let ldr = if settings.fxaa {
+            let ldr = graph.create_texture("ldr", TextureDescription::new(self.presentation_format, description.width, description.height));
+            Some(ldr)
+        } else {
+            None
+        };
+        let pass = PassDescription::new("tonemap")
+            .color(ColorAttachment::new(ldr.unwrap_or(target)));
+        self.add_pass(graph, pass, &self.pipelines.tonemap, scene, bloom_levels.first().copied());
+        if let Some(ldr) = ldr {
+            let pass = PassDescription::new("fxaa")
+                .color(ColorAttachment::new(target));
+            self.add_pass(graph, pass, &self.pipelines.fxaa, ldr, None);
+        }
+
+        // write
+        let Ok(mut frame_count) = self.frame_count.lock() else { return };
+        // the grain changes every frame
+        *frame_count = (*frame_count + 1) % 1024;
+        let tonemapping = match settings.tonemapping {
+            Tonemapping::Aces => 0.0,
+            Tonemapping::Agx => 1.0,
+            Tonemapping::Reinhard => 2.0,
+        };
+        let tone = [
+            2.0f32.powf(settings.exposure),
+            tonemapping,
+            if bloom_levels.is_empty() { 0.0 } else { settings.bloom_intensity },
+            settings.bloom_radius,
+        ];
+        let finish = [
+            if settings.vignette { settings.vignette_intensity } else { 0.0 },
+            if settings.grain { settings.grain_intensity } else { 0.0 },
+            *frame_count as f32,
+            if settings.fxaa { 1.0 } else { 0.0 },
+        ];
+        let uniform_values: Vec<f32> = [tone, finish].concat();
+        let uniform_data = js_sys::Float32Array::new_with_length(4 + 4);
+        uniform_data.copy_from(uniform_values.as_slice());
+        self.device.device().queue().write_buffer_with_u32_and_buffer_source(&self.uniform_buffer, 0, &uniform_data);
+    }
+
+    // the source of each pass is only known once the graph allocated it
+    fn add_pass(&self,
+        graph: &mut RenderGraph,
+        pass: PassDescription,
+        render_pipeline: &GpuRenderPipeline,
+        source: TextureHandle,
+        bloom: Option<TextureHandle>) {
+        let pass = bloom.into_iter().fold(pass.read(source), PassDescription::read);
+        let bind_group = PostProcessBindGroup {
+            device: Arc::clone(&self.device),
+            uniform_buffer: self.uniform_buffer.clone(),
+            sampler: self.sampler.clone(),
+            empty_view: self.empty_view.clone(),
+        };
+        let render_pipeline = render_pipeline.clone();
+        graph.add_pass(pass, move |render_pass_encoder, textures| {
+            let Some(source_view) = textures.view(source) else { return };
+            let bloom_view = bloom.and_then(|v| textures.view(v));
+            let bind_group = bind_group.create(&render_pipeline, source_view, bloom_view);
+            render_pass_encoder.set_pipeline(&render_pipeline);
+            render_pass_encoder.set_bind_group(0, Some(&bind_group));
+            render_pass_encoder.draw(3);
+        });
+    }
+}
+
+// fullscreen passes of the post processing
+struct PostProcessPipelines {
+    bloom_prefilter: GpuRenderPipeline,
+    bloom_downsample: GpuRenderPipeline,
+    bloom_upsample: GpuRenderPipeline,
+    tonemap: GpuRenderPipeline,
+    fxaa: GpuRenderPipeline,
+}
+
+// bindings shared by the post processing passes around the textures of one pass
+struct PostProcessBindGroup {
+    device: Arc<Device>,
+    uniform_buffer: GpuBuffer,
+    sampler: GpuSampler,
+    empty_view: GpuTextureView,
+}
+
+impl PostProcessBindGroup {
+    fn create(&self, render_pipeline: &GpuRenderPipeline, source_view: &GpuTextureView, bloom_view: Option<&GpuTextureView>) -> GpuBindGroup {
+        let buffer_binding = GpuBufferBinding::new(&self.uniform_buffer);
+        let buffer_binding: JsValue = buffer_binding.into();
+        let bind_entries: Vec<JsValue> = vec![
+            GpuBindGroupEntry::new(0, &buffer_binding).into(),
+            GpuBindGroupEntry::new(1, &self.sampler).into(),
+            GpuBindGroupEntry::new(2, source_view).into(),
+            GpuBindGroupEntry::new(3, bloom_view.unwrap_or(&self.empty_view)).into(),
+        ];
+        let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+        let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &render_pipeline.get_bind_group_layout(0));
+        self.device.device().create_bind_group(&bind_group_descriptor)
+    }
+}
@@ -0,0 +1,495 @@
+
+use std::sync::{Arc, Mutex};
+
+use crate::render::device::Device;
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuTexture,
+    GpuTextureView,
+    GpuTextureFormat,
+    GpuTextureDescriptor,
+    gpu_texture_usage,
+    GpuRenderPassDescriptor,
+    GpuRenderPassColorAttachment,
+    GpuRenderPassDepthStencilAttachment,
+    GpuRenderPassEncoder,
+    GpuLoadOp,
+    GpuStoreOp,
+    GpuColorDict,
+};
+
+// texture declared in a render graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureHandle(usize);
+
+// transient texture allocated by the graph for the frame. the usage follows from the passes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureDescription {
+    pub format: GpuTextureFormat,
+    pub width: u32,
+    pub height: u32,
+    pub sample_count: u32,
+}
+
+impl TextureDescription {
+    pub fn new(format: GpuTextureFormat, width: u32, height: u32) -> Self {
+        Self {
+            format,
+            width: width.max(1),
+            height: height.max(1),
+            sample_count: 1,
+        }
+    }
+
+    pub fn with_sample_count(self, sample_count: u32) -> Self {
+        Self { sample_count, ..self }
+    }
+}
+
+// color target of a pass. it is cleared when nothing earlier in the frame wrote it, or always
+// with an explicit clear value.
+#[derive(Debug, Clone, Copy)]
+pub struct ColorAttachment {
+    pub texture: TextureHandle,
+    pub resolve_target: Option<TextureHandle>,
+    pub clear_value: Option<[f64; 4]>,
+}
+
+impl ColorAttachment {
+    pub fn new(texture: TextureHandle) -> Self {
+        Self {
+            texture,
+            resolve_target: None,
+            clear_value: None,
+        }
+    }
+
+    pub fn resolve(self, resolve_target: Option<TextureHandle>) -> Self {
+        Self { resolve_target, ..self }
+    }
+
+    // red, green, blue and alpha
+    pub fn clear(self, clear_value: [f64; 4]) -> Self {
+        Self { clear_value: Some(clear_value), ..self }
+    }
+}
+
+// depth target of a pass, cleared to 1.0 like a color attachment
+#[derive(Debug, Clone, Copy)]
+pub struct DepthAttachment {
+    pub texture: TextureHandle,
+    pub clear: bool,
+}
+
+impl DepthAttachment {
+    pub fn new(texture: TextureHandle) -> Self {
+        Self {
+            texture,
+            clear: false,
+        }
+    }
+
+    pub fn clear(self) -> Self {
+        Self { clear: true, ..self }
+    }
+}
+
+// what a pass writes and samples
+#[derive(Debug, Clone)]
+pub struct PassDescription {
+    pub name: &'static str,
+    pub color_attachments: Vec<ColorAttachment>,
+    pub depth_attachment: Option<DepthAttachment>,
+    pub reads: Vec<TextureHandle>,
+}
+
+impl PassDescription {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            color_attachments: vec![],
+            depth_attachment: None,
+            reads: vec![],
+        }
+    }
+
+    pub fn color(mut self, color_attachment: ColorAttachment) -> Self {
+        self.color_attachments.push(color_attachment);
+        self
+    }
+
+    pub fn depth(self, depth_attachment: DepthAttachment) -> Self {
+        Self { depth_attachment: Some(depth_attachment), ..self }
+    }
+
+    pub fn read(mut self, texture: TextureHandle) -> Self {
+        self.reads.push(texture);
+        self
+    }
+
+    fn writes(&self, texture: TextureHandle) -> bool {
+        self.color_attachments.iter().any(|v| v.texture == texture || v.resolve_target == Some(texture)) ||
+            self.depth_attachment.is_some_and(|v| v.texture == texture)
+    }
+
+    fn uses(&self, texture: TextureHandle) -> bool {
+        self.writes(texture) || self.reads.contains(&texture)
+    }
+
+    // the pass needs the previous contents of the texture
+    fn loads(&self, texture: TextureHandle) -> bool {
+        let color_loads = self.color_attachments.iter()
+            .any(|v| v.texture == texture && v.clear_value.is_none());
+        let depth_loads = self.depth_attachment.is_some_and(|v| v.texture == texture && !v.clear);
+        color_loads || depth_loads || self.reads.contains(&texture)
+    }
+}
+
+enum TextureResource {
+    Transient(TextureDescription),
+    // owned outside the graph like the swapchain, always stored
+    Imported(GpuTextureView),
+}
+
+struct TextureNode {
+    name: &'static str,
+    resource: TextureResource,
+}
+
+type RecordPass = Box<dyn FnOnce(&GpuRenderPassEncoder, &GraphTextures)>;
+
+// passes of a frame recorded into one command encoder. transient textures whose lifetimes do
+// not overlap share the same allocation.
+pub struct RenderGraph {
+    textures: Vec<TextureNode>,
+    passes: Vec<PassDescription>,
+    records: Vec<RecordPass>,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self {
+            textures: vec![],
+            passes: vec![],
+            records: vec![],
+        }
+    }
+
+    pub fn create_texture(&mut self, name: &'static str, description: TextureDescription) -> TextureHandle {
+        self.textures.push(TextureNode { name, resource: TextureResource::Transient(description) });
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    pub fn import_texture(&mut self, name: &'static str, view: &GpuTextureView) -> TextureHandle {
+        self.textures.push(TextureNode { name, resource: TextureResource::Imported(view.clone()) });
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    pub fn texture_description(&self, texture: TextureHandle) -> Option<TextureDescription> {
+        match self.textures.get(texture.0)?.resource {
+            TextureResource::Transient(description) => Some(description),
+            TextureResource::Imported(_) => None,
+        }
+    }
+
+    // passes run in the order they are added
+    pub fn add_pass(&mut self, pass: PassDescription, record: impl FnOnce(&GpuRenderPassEncoder, &GraphTextures) + 'static) {
+        self.passes.push(pass);
+        self.records.push(Box::new(record));
+    }
+
+    pub fn execute(mut self, device: &Arc<Device>, transient_textures: &TransientTextures) {
+        let textures = self.allocate(device, transient_textures);
+        let command_encoder = device.device().create_command_encoder();
+        let records = std::mem::take(&mut self.records);
+        for (pass_index, (pass, record)) in self.passes.iter().zip(records).enumerate() {
+            // every attachment of a pass has an allocation
+            let color_attachments: Option<Vec<JsValue>> = pass.color_attachments.iter()
+                .map(|v| {
+                    let (load_op, clear_value) = self.color_load_op(pass_index, v);
+                    let mut color_attachment = GpuRenderPassColorAttachment::new(load_op, self.store_op(pass_index, v.texture), textures.view(v.texture)?);
+                    if let Some([r, g, b, a]) = clear_value {
+                        let clear_value: JsValue = GpuColorDict::new(a, b, g, r).into();
+                        color_attachment.clear_value(&clear_value);
+                    }
+                    if let Some(resolve_target) = v.resolve_target {
+                        color_attachment.resolve_target(textures.view(resolve_target)?);
+                    }
+                    Some(color_attachment.into())
+                })
+                .collect();
+            let Some(color_attachments) = color_attachments else { continue };
+            let color_attachments = color_attachments.into_iter().collect::<js_sys::Array>();
+            let mut render_pass_descriptor = GpuRenderPassDescriptor::new(&color_attachments);
+            render_pass_descriptor.label(pass.name);
+            if let Some(depth_attachment) = pass.depth_attachment {
+                let Some(view) = textures.view(depth_attachment.texture) else { continue };
+                let mut depth_stencil_attachment = GpuRenderPassDepthStencilAttachment::new(view);
+                depth_stencil_attachment.depth_load_op(self.depth_load_op(pass_index, &depth_attachment));
+                depth_stencil_attachment.depth_store_op(self.store_op(pass_index, depth_attachment.texture));
+                depth_stencil_attachment.depth_clear_value(1.0);
+                render_pass_descriptor.depth_stencil_attachment(&depth_stencil_attachment);
+            }
+            let render_pass_encoder = command_encoder.begin_render_pass(&render_pass_descriptor);
+            record(&render_pass_encoder, &textures);
+            render_pass_encoder.end();
+        }
+        // submit
+        let command_buffer = command_encoder.finish();
+        let command_buffers: Vec<JsValue> = vec![
+            command_buffer.into(),
+        ];
+        let command_buffers = command_buffers.into_iter().collect::<js_sys::Array>();
+        device.device().queue().submit(&command_buffers);
+    }
+
+    fn written_before(&self, pass_index: usize, texture: TextureHandle) -> bool {
+        self.passes[..pass_index].iter().any(|v| v.writes(texture))
+    }
+
+    // cleared with the clear value of the attachment, or black when nothing wrote it earlier
+    fn color_load_op(&self, pass_index: usize, color_attachment: &ColorAttachment) -> (GpuLoadOp, Option<[f64; 4]>) {
+        let clear_value = color_attachment.clear_value
+            .or_else(|| (!self.written_before(pass_index, color_attachment.texture)).then_some([0.0, 0.0, 0.0, 0.0]));
+        let load_op = if clear_value.is_some() { GpuLoadOp::Clear } else { GpuLoadOp::Load };
+        (load_op, clear_value)
+    }
+
+    fn depth_load_op(&self, pass_index: usize, depth_attachment: &DepthAttachment) -> GpuLoadOp {
+        let clear = depth_attachment.clear || !self.written_before(pass_index, depth_attachment.texture);
+        if clear { GpuLoadOp::Clear } else { GpuLoadOp::Load }
+    }
+
+    // kept when it outlives the frame or the next pass using it needs the contents
+    fn store_op(&self, pass_index: usize, texture: TextureHandle) -> GpuStoreOp {
+        let imported = matches!(self.textures[texture.0].resource, TextureResource::Imported(_));
+        let loaded_later = self.passes[pass_index + 1..].iter()
+            .find(|v| v.uses(texture))
+            .is_some_and(|v| v.loads(texture));
+        if imported || loaded_later { GpuStoreOp::Store } else { GpuStoreOp::Discard }
+    }
+
+    fn allocate(&self, device: &Arc<Device>, transient_textures: &TransientTextures) -> GraphTextures {
+        let (keys, assignments) = self.assign_allocations();
+        let names: Vec<&'static str> = (0..keys.len())
+            .map(|allocation_index| {
+                let texture_index = assignments.iter().position(|v| *v == Some(allocation_index));
+                texture_index.map(|v| self.textures[v].name).unwrap_or("transient")
+            })
+            .collect();
+        let allocated = transient_textures.acquire(device, &keys, &names);
+        let views = self.textures.iter().zip(assignments)
+            .map(|(node, assignment)| match (&node.resource, assignment) {
+                (TextureResource::Imported(view), _) => Some(view.clone()),
+                (TextureResource::Transient(_), Some(allocation_index)) => Some(allocated[allocation_index].create_view()),
+                (TextureResource::Transient(_), None) => None,
+            })
+            .collect();
+        GraphTextures { views }
+    }
+
+    // assigns the transient textures to allocations in order of their first pass. an allocation
+    // is reused once the last pass of its previous texture ran. the allocation of each texture,
+    // none for imported textures and those no pass uses.
+    fn assign_allocations(&self) -> (Vec<TransientKey>, Vec<Option<usize>>) {
+        let mut allocations: Vec<(TransientKey, usize)> = vec![];
+        let mut assignments: Vec<Option<usize>> = vec![None; self.textures.len()];
+        let mut lifetimes: Vec<(usize, usize, usize)> = (0..self.textures.len())
+            .filter_map(|texture_index| {
+                let texture = TextureHandle(texture_index);
+                let mut pass_indices = self.passes.iter().enumerate()
+                    .filter(|(_, v)| v.uses(texture))
+                    .map(|(pass_index, _)| pass_index);
+                let first = pass_indices.next()?;
+                let last = pass_indices.next_back().unwrap_or(first);
+                Some((texture_index, first, last))
+            })
+            .collect();
+        lifetimes.sort_by_key(|(_, first, _)| *first);
+        for (texture_index, first, last) in lifetimes {
+            let TextureResource::Transient(description) = self.textures[texture_index].resource else { continue };
+            let key = TransientKey {
+                description,
+                usage: self.usage(TextureHandle(texture_index)),
+            };
+            let allocation_index = match allocations.iter().position(|(v, until)| *v == key && *until < first) {
+                Some(allocation_index) => {
+                    allocations[allocation_index].1 = last;
+                    allocation_index
+                },
+                None => {
+                    allocations.push((key, last));
+                    allocations.len() - 1
+                },
+            };
+            assignments[texture_index] = Some(allocation_index);
+        }
+        let keys: Vec<TransientKey> = allocations.iter().map(|(v, _)| *v).collect();
+        (keys, assignments)
+    }
+
+    fn usage(&self, texture: TextureHandle) -> u32 {
+        self.passes.iter().fold(0, |usage, pass| {
+            let attachment = if pass.writes(texture) { gpu_texture_usage::RENDER_ATTACHMENT } else { 0 };
+            let binding = if pass.reads.contains(&texture) { gpu_texture_usage::TEXTURE_BINDING } else { 0 };
+            usage | attachment | binding
+        })
+    }
+}
+
+// views of the graph textures while the passes are recorded
+pub struct GraphTextures {
+    views: Vec<Option<GpuTextureView>>,
+}
+
+impl GraphTextures {
+    // none for transient textures the pass did not declare and no other pass uses
+    pub fn view(&self, texture: TextureHandle) -> Option<&GpuTextureView> {
+        self.views.get(texture.0)?.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TransientKey {
+    description: TextureDescription,
+    usage: u32,
+}
+
+// allocations of the transient textures kept across frames. those a frame does not use are
+// destroyed, which releases the targets of the previous size after a resize.
+pub struct TransientTextures {
+    textures: Mutex<Vec<(TransientKey, GpuTexture)>>,
+}
+
+impl TransientTextures {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new() -> Arc<Self> {
+        let this = Self {
+            textures: Mutex::new(vec![]),
+        };
+        Arc::new(this)
+    }
+
+    fn acquire(&self, device: &Arc<Device>, keys: &[TransientKey], names: &[&str]) -> Vec<GpuTexture> {
+        let Ok(mut textures) = self.textures.lock() else { return vec![] };
+        let mut available = std::mem::take(&mut *textures);
+        let acquired: Vec<GpuTexture> = keys.iter().zip(names)
+            .map(|(key, name)| {
+                if let Some(index) = available.iter().position(|(v, _)| v == key) {
+                    return available.swap_remove(index).1
+                }
+                let description = &key.description;
+                let size: Vec<JsValue> = vec![description.width as f64, description.height as f64].into_iter().map(Into::into).collect();
+                let size = size.into_iter().collect::<js_sys::Array>();
+                let mut texture_descriptor = GpuTextureDescriptor::new(description.format, &size, key.usage);
+                texture_descriptor.label(name);
+                texture_descriptor.sample_count(description.sample_count);
+                device.device().create_texture(&texture_descriptor)
+            })
+            .collect();
+        for (_, texture) in available {
+            texture.destroy();
+        }
+        *textures = keys.iter().copied().zip(acquired.iter().cloned()).collect();
+        acquired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn description() -> TextureDescription {
+        TextureDescription::new(GpuTextureFormat::Rgba16float, 4, 4)
+    }
+
+    fn add_pass(graph: &mut RenderGraph, pass: PassDescription) {
+        graph.add_pass(pass, |_, _| {});
+    }
+
+    #[test]
+    fn first_writes_clear_and_later_writes_load() {
+        let mut graph = RenderGraph::new();
+        let color = graph.create_texture("color", description());
+        let first = ColorAttachment::new(color);
+        let explicit = ColorAttachment::new(color).clear([1.0, 0.0, 0.0, 1.0]);
+        add_pass(&mut graph, PassDescription::new("first").color(first));
+        add_pass(&mut graph, PassDescription::new("second").color(first));
+        add_pass(&mut graph, PassDescription::new("third").color(explicit));
+        assert_eq!(graph.color_load_op(0, &first), (GpuLoadOp::Clear, Some([0.0, 0.0, 0.0, 0.0])));
+        assert_eq!(graph.color_load_op(1, &first), (GpuLoadOp::Load, None));
+        assert_eq!(graph.color_load_op(2, &explicit), (GpuLoadOp::Clear, Some([1.0, 0.0, 0.0, 1.0])));
+    }
+
+    #[test]
+    fn depth_loads_only_after_a_write() {
+        let mut graph = RenderGraph::new();
+        let depth = graph.create_texture("depth", TextureDescription::new(GpuTextureFormat::Depth24plus, 4, 4));
+        let color = graph.create_texture("color", description());
+        let attachment = DepthAttachment::new(depth);
+        add_pass(&mut graph, PassDescription::new("prepass").color(ColorAttachment::new(color)).depth(attachment));
+        add_pass(&mut graph, PassDescription::new("meshes").color(ColorAttachment::new(color)).depth(attachment));
+        add_pass(&mut graph, PassDescription::new("overlay").color(ColorAttachment::new(color)).depth(attachment.clear()));
+        assert_eq!(graph.depth_load_op(0, &attachment), GpuLoadOp::Clear);
+        assert_eq!(graph.depth_load_op(1, &attachment), GpuLoadOp::Load);
+        assert_eq!(graph.depth_load_op(2, &attachment.clear()), GpuLoadOp::Clear);
+    }
+
+    #[test]
+    fn stores_only_what_a_later_pass_loads() {
+        let mut graph = RenderGraph::new();
+        let sampled = graph.create_texture("sampled", description());
+        let overwritten = graph.create_texture("overwritten", description());
+        let unused = graph.create_texture("unused", description());
+        let output = graph.create_texture("output", description());
+        add_pass(&mut graph, PassDescription::new("first")
+            .color(ColorAttachment::new(sampled))
+            .color(ColorAttachment::new(overwritten))
+            .color(ColorAttachment::new(unused)));
+        add_pass(&mut graph, PassDescription::new("second")
+            .color(ColorAttachment::new(overwritten).clear([0.0; 4]))
+            .color(ColorAttachment::new(output))
+            .read(sampled));
+        assert_eq!(graph.store_op(0, sampled), GpuStoreOp::Store);
+        assert_eq!(graph.store_op(0, overwritten), GpuStoreOp::Discard);
+        assert_eq!(graph.store_op(0, unused), GpuStoreOp::Discard);
+        assert_eq!(graph.store_op(1, output), GpuStoreOp::Discard);
+    }
+
+    #[test]
+    fn textures_alias_once_their_lifetimes_end() {
+        let mut graph = RenderGraph::new();
+        let a = graph.create_texture("a", description());
+        let b = graph.create_texture("b", description());
+        let c = graph.create_texture("c", description());
+        let half = graph.create_texture("half", TextureDescription::new(GpuTextureFormat::Rgba16float, 2, 2));
+        let never = graph.create_texture("never", description());
+        // a lives in the first two passes, b overlaps it, c starts after a ended
+        add_pass(&mut graph, PassDescription::new("write a").color(ColorAttachment::new(a)));
+        add_pass(&mut graph, PassDescription::new("a into b").color(ColorAttachment::new(b)).read(a));
+        add_pass(&mut graph, PassDescription::new("b into c").color(ColorAttachment::new(c)).read(b));
+        add_pass(&mut graph, PassDescription::new("c into half").color(ColorAttachment::new(half)).read(c));
+        let (keys, assignments) = graph.assign_allocations();
+        assert_eq!(assignments[c.0], assignments[a.0]);
+        assert_ne!(assignments[b.0], assignments[a.0]);
+        // another size never shares an allocation
+        assert!(assignments[half.0].is_some());
+        assert_ne!(assignments[half.0], assignments[a.0]);
+        assert_ne!(assignments[half.0], assignments[b.0]);
+        assert_eq!(assignments[never.0], None);
+        assert_eq!(keys.len(), 3);
+    }
+
+    #[test]
+    fn usage_follows_how_the_passes_use_a_texture() {
+        let mut graph = RenderGraph::new();
+        let written = graph.create_texture("written", description());
+        let sampled = graph.create_texture("sampled", description());
+        add_pass(&mut graph, PassDescription::new("first").color(ColorAttachment::new(sampled)));
+        add_pass(&mut graph, PassDescription::new("second").color(ColorAttachment::new(written)).read(sampled));
+        assert_eq!(graph.usage(written), gpu_texture_usage::RENDER_ATTACHMENT);
+        assert_eq!(graph.usage(sampled), gpu_texture_usage::RENDER_ATTACHMENT | gpu_texture_usage::TEXTURE_BINDING);
+    }
+}
@@ -0,0 +1,146 @@
+
+use std::sync::Arc;
+
+use super::Shaders;
+use crate::render::device::Device;
+use crate::render::shader::ShaderModule;
+
+impl Shaders {
+    pub fn ambient_occlusion(device: &Arc<Device>) -> Arc<ShaderModule> {
+        let code = "
+struct Settings {
+    projection: mat4x4<f32>,
+    inverse_projection: mat4x4<f32>,
+    // view-space radius, intensity exponent, sample count
+    parameters: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> settings: Settings;
+
+@group(0) @binding(1)
+var depth_texture: texture_depth_2d;
+
+@group(0) @binding(2)
+var occlusion_texture: texture_2d<f32>;
+
+const PI: f32 = 3.141592653589793;
+
+// one triangle covering the target
+@vertex
+fn vert_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    var tex_coord = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(tex_coord.x * 2.0 - 1.0, 1.0 - tex_coord.y * 2.0, 0.0, 1.0);
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+    var p3 = fract(vec3<f32>(p.x, p.y, p.x) * 0.1031);
+    p3 += dot(p3, p3.yzx + 33.33);
+    return fract((p3.x + p3.y) * p3.z);
+}
+
+fn depth_at(coord: vec2<i32>) -> f32 {
+    var size = vec2<i32>(textureDimensions(depth_texture));
+    return textureLoad(depth_texture, clamp(coord, vec2<i32>(0), size - 1), 0);
+}
+
+// unprojects the depth of the prepass
+fn view_position(coord: vec2<i32>) -> vec3<f32> {
+    var size = vec2<f32>(textureDimensions(depth_texture));
+    var uv = (vec2<f32>(coord) + 0.5) / size;
+    var ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth_at(coord), 1.0);
+    var p = settings.inverse_projection * ndc;
+    return p.xyz / p.w;
+}
+
+// faces from the neighbors on the same surface, picking the smaller depth step on each axis so
+// silhouettes keep their normals
+fn view_normal(coord: vec2<i32>, center: vec3<f32>) -> vec3<f32> {
+    var left = view_position(coord + vec2<i32>(-1, 0));
+    var right = view_position(coord + vec2<i32>(1, 0));
+    var up = view_position(coord + vec2<i32>(0, -1));
+    var down = view_position(coord + vec2<i32>(0, 1));
+    var dx = select(center - left, right - center, abs(right.z - center.z) < abs(center.z - left.z));
+    var dy = select(center - up, down - center, abs(down.z - center.z) < abs(center.z - up.z));
+    var n = normalize(cross(dy, dx));
+    return select(n, -n, dot(n, center) > 0.0);
+}
+
+// fraction of a cosine weighted hemisphere around the normal left open by the depth buffer
+@fragment
+fn frag_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    var coord = vec2<i32>(position.xy);
+    if (depth_at(coord) >= 1.0) {
+        return vec4<f32>(1.0);
+    }
+    var center = view_position(coord);
+    var n = view_normal(coord, center);
+    var radius = settings.parameters.x;
+    var sample_count = u32(settings.parameters.z);
+    var size = vec2<f32>(textureDimensions(depth_texture));
+    // basis around the normal turned by the noise of the pixel
+    var angle = hash(position.xy) * 2.0 * PI;
+    var helper = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(n.x) > 0.9);
+    var t = normalize(cross(helper, n));
+    var b = cross(n, t);
+    var tangent = t * cos(angle) + b * sin(angle);
+    var bitangent = cross(n, tangent);
+    var bias = radius * 0.025;
+    var occlusion = 0.0;
+    for (var i = 0u; i < sample_count; i++) {
+        var stratum = (f32(i) + 0.5) / f32(sample_count);
+        var phi = f32(i) * 2.399963;
+        var sin_theta = sqrt(fract(stratum + hash(position.yx + f32(i))));
+        var cos_theta = sqrt(1.0 - sin_theta * sin_theta);
+        var direction = tangent * (cos(phi) * sin_theta) + bitangent * (sin(phi) * sin_theta) + n * cos_theta;
+        // more samples close to the surface
+        var scale = mix(0.1, 1.0, stratum * stratum);
+        var sample_position = center + direction * radius * scale;
+        var clip = settings.projection * vec4<f32>(sample_position, 1.0);
+        var uv = clip.xy / clip.w * vec2<f32>(0.5, -0.5) + 0.5;
+        if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0))) {
+            continue;
+        }
+        var scene = view_position(vec2<i32>(uv * size));
+        // geometry far in front of the sample does not occlude it
+        var range = smoothstep(0.0, 1.0, radius / max(abs(center.z - scene.z), 1e-6));
+        occlusion += select(0.0, 1.0, scene.z >= sample_position.z + bias) * range;
+    }
+    var visibility = 1.0 - occlusion / f32(max(sample_count, 1u));
+    return vec4<f32>(pow(visibility, settings.parameters.y), 0.0, 0.0, 1.0);
+}
+
+// gaussian weights falling off with the relative depth difference so the blur stops at edges
+fn blur(coord: vec2<i32>, direction: vec2<i32>) -> f32 {
+    if (depth_at(coord) >= 1.0) {
+        return 1.0;
+    }
+    var center_depth = -view_position(coord).z;
+    var size = vec2<i32>(textureDimensions(occlusion_texture));
+    var sum = 0.0;
+    var weight_sum = 0.0;
+    for (var offset = -4; offset <= 4; offset++) {
+        var sample_coord = clamp(coord + direction * offset, vec2<i32>(0), size - 1);
+        var sample_depth = -view_position(sample_coord).z;
+        var spatial = exp(-f32(offset * offset) / 8.0);
+        var range = exp(-abs(sample_depth - center_depth) * 20.0 / max(abs(center_depth), 1e-4));
+        var weight = spatial * range;
+        sum += textureLoad(occlusion_texture, sample_coord, 0).r * weight;
+        weight_sum += weight;
+    }
+    return sum / max(weight_sum, 1e-4);
+}
+
+@fragment
+fn frag_blur_horizontal_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(blur(vec2<i32>(position.xy), vec2<i32>(1, 0)), 0.0, 0.0, 1.0);
+}
+
+@fragment
+fn frag_blur_vertical_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(blur(vec2<i32>(position.xy), vec2<i32>(0, 1)), 0.0, 0.0, 1.0);
+}
+        ";
+        ShaderModule::with_code(device, code)
+    }
+}
@@ -11,51 +11,563 @@ impl Shaders {
 struct Uniforms {
     projection_view: mat4x4<f32>,
     camera_position: vec4<f32>,
+    // specular mip count, intensity, available, rotation around +Y like the skybox
+    environment: vec4<f32>,
+    // projection views of the shadow cascades
+    shadow_projection_views: array<mat4x4<f32>, 4>,
+    // casting light index or -1, depth bias, normal bias and filter radius in texels
+    shadow: vec4<f32>,
+    // view depths where the cascades end
+    shadow_splits: vec4<f32>,
+    // world-space texel widths of the cascades
+    shadow_texel_sizes: vec4<f32>,
+    // cascade count, blend fraction, debug view, view depth where the first cascade starts
+    shadow_cascades: vec4<f32>,
+    // view depth of a world position as a dot product
+    camera_depth: vec4<f32>,
+    // ground center x and z, height, half extent or zero without the ground plane
+    ground: vec4<f32>,
+    // projection view of the previous frame without the jitter
+    previous_projection_view: mat4x4<f32>,
+    // sub-pixel offset of the projection in normalized device coordinates
+    jitter: vec4<f32>,
 }
 @binding(0) @group(0) var<uniform> uniforms: Uniforms;
+@binding(2) @group(0) var specular_environment: texture_cube<f32>;
+@binding(3) @group(0) var irradiance_environment: texture_cube<f32>;
+@binding(4) @group(0) var brdf_lut: texture_2d<f32>;
+@binding(5) @group(0) var environment_sampler: sampler;
+@binding(6) @group(0) var shadow_map: texture_depth_2d_array;
+@binding(7) @group(0) var shadow_sampler: sampler_comparison;
+@binding(8) @group(0) var ambient_occlusion_texture: texture_2d<f32>;
+
+struct Light {
+    // xyz position, light type
+    position: vec4<f32>,
+    // xyz direction, range or zero when infinite
+    direction: vec4<f32>,
+    // rgb color premultiplied by the intensity
+    color: vec4<f32>,
+    // cosines of the inner and outer cone angles
+    cone: vec4<f32>,
+}
+struct Lights {
+    count: vec4<u32>,
+    lights: array<Light>,
+}
+@binding(1) @group(0) var<storage, read> lights: Lights;
+
+const LIGHT_TYPE_DIRECTIONAL: f32 = 0.0;
+const LIGHT_TYPE_SPOT: f32 = 2.0;
 
 struct Object {
     model: mat4x4<f32>,
-    padding0: mat4x4<f32>,
-    padding1: mat4x4<f32>,
+    normal_matrix: mat4x4<f32>,
+    // model matrix of the previous frame
+    previous_model: mat4x4<f32>,
     padding2: mat4x4<f32>,
 }
 @binding(0) @group(1) var<uniform> object: Object;
 
+struct Material {
+    base_color_factor: vec4<f32>,
+    emissive_factor: vec4<f32>,
+    // metallic, roughness, alpha cutoff, alpha mode
+    parameters: vec4<f32>,
+    // texture coordinate sets of base color, metallic roughness, normal and occlusion
+    tex_coords: vec4<f32>,
+    // emissive texture coordinate set, normal scale, occlusion strength
+    texture_parameters: vec4<f32>,
+}
+@binding(0) @group(2) var<uniform> material: Material;
+@binding(1) @group(2) var base_color_texture: texture_2d<f32>;
+@binding(2) @group(2) var base_color_sampler: sampler;
+@binding(3) @group(2) var metallic_roughness_texture: texture_2d<f32>;
+@binding(4) @group(2) var metallic_roughness_sampler: sampler;
+@binding(5) @group(2) var normal_texture: texture_2d<f32>;
+@binding(6) @group(2) var normal_sampler: sampler;
+@binding(7) @group(2) var occlusion_texture: texture_2d<f32>;
+@binding(8) @group(2) var occlusion_sampler: sampler;
+@binding(9) @group(2) var emissive_texture: texture_2d<f32>;
+@binding(10) @group(2) var emissive_sampler: sampler;
+
+// read by the deformed vertex stage only
+struct Deformation {
+    // skinned, morph target count
+    parameters: vec4<u32>,
+    weights: array<f32>,
+}
+// joint matrices of the skin relative to the mesh node
+@binding(0) @group(3) var<storage, read> joint_matrices: array<mat4x4<f32>>;
+// position and normal deltas laid out per vertex then per target
+@binding(1) @group(3) var<storage, read> morph_targets: array<vec4<f32>>;
+@binding(2) @group(3) var<storage, read> deformation: Deformation;
+
+const ALPHA_MODE_MASK: f32 = 1.0;
+const ALPHA_MODE_BLEND: f32 = 2.0;
+const PI: f32 = 3.14159265359;
+
 struct VertexOut {
     @builtin(position) position: vec4<f32>,
     @location(0) object_normal: vec4<f32>,
     @location(1) object_position: vec4<f32>,
     @location(2) camera_position: vec4<f32>,
+    @location(3) tex_coord_0: vec2<f32>,
+    @location(4) tex_coord_1: vec2<f32>,
+    @location(5) object_tangent: vec4<f32>,
+    @location(6) color: vec4<f32>,
+    @location(7) clip_position: vec4<f32>,
+    @location(8) previous_clip_position: vec4<f32>,
 }
 
 @vertex
 fn vert_main(
     @location(0) position: vec3<f32>,
     @location(1) normal: vec3<f32>,
+    @location(2) tex_coord_0: vec2<f32>,
+    @location(3) tex_coord_1: vec2<f32>,
+    @location(4) tangent: vec4<f32>,
+    @location(5) color: vec4<f32>,
 ) -> VertexOut {
     var p = vec4<f32>(position, 1.0);
     var n = vec4<f32>(normal, 0.0);
+    var t = vec4<f32>(tangent.xyz, 0.0);
+    return transform_vertex(p, n, t, tangent.w, tex_coord_0, tex_coord_1, color);
+}
+
+@vertex
+fn vert_deformed_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord_0: vec2<f32>,
+    @location(3) tex_coord_1: vec2<f32>,
+    @location(4) tangent: vec4<f32>,
+    @location(5) color: vec4<f32>,
+    @location(6) joints: vec4<u32>,
+    @location(7) weights: vec4<f32>,
+) -> VertexOut {
+    // morph targets
+    var morphed_position = position;
+    var morphed_normal = normal;
+    var target_count = deformation.parameters.y;
+    for (var i = 0u; i < target_count; i++) {
+        var weight = deformation.weights[i];
+        var offset = (vertex_index * target_count + i) * 2u;
+        morphed_position += weight * morph_targets[offset].xyz;
+        morphed_normal += weight * morph_targets[offset + 1u].xyz;
+    }
+    // skinning
+    var skin = mat4x4<f32>(
+        vec4<f32>(1.0, 0.0, 0.0, 0.0),
+        vec4<f32>(0.0, 1.0, 0.0, 0.0),
+        vec4<f32>(0.0, 0.0, 1.0, 0.0),
+        vec4<f32>(0.0, 0.0, 0.0, 1.0));
+    // vertices without any weight keep their bind pose
+    var weight_sum = weights.x + weights.y + weights.z + weights.w;
+    if (deformation.parameters.x == 1u && weight_sum > 0.0) {
+        skin = weights.x * joint_matrices[joints.x]
+            + weights.y * joint_matrices[joints.y]
+            + weights.z * joint_matrices[joints.z]
+            + weights.w * joint_matrices[joints.w];
+    }
+    var p = skin * vec4<f32>(morphed_position, 1.0);
+    var n = skin * vec4<f32>(morphed_normal, 0.0);
+    var t = skin * vec4<f32>(tangent.xyz, 0.0);
+    return transform_vertex(p, n, t, tangent.w, tex_coord_0, tex_coord_1, color);
+}
+
+fn transform_vertex(
+    p: vec4<f32>,
+    n: vec4<f32>,
+    t: vec4<f32>,
+    handedness: f32,
+    tex_coord_0: vec2<f32>,
+    tex_coord_1: vec2<f32>,
+    color: vec4<f32>,
+) -> VertexOut {
     var out: VertexOut;
     out.position = uniforms.projection_view * object.model * p;
-    out.object_normal = object.model * n;
+    out.object_normal = object.normal_matrix * n;
     out.object_position = object.model * p;
     out.camera_position = uniforms.camera_position;
+    out.tex_coord_0 = tex_coord_0;
+    out.tex_coord_1 = tex_coord_1;
+    out.object_tangent = vec4<f32>((object.model * t).xyz, handedness);
+    out.color = color;
+    // deformed vertices move with the node only, not with the joints and weights of the previous frame
+    out.clip_position = out.position;
+    out.previous_clip_position = uniforms.previous_projection_view * object.previous_model * p;
+    return out;
+}
+
+// direction towards the light and the radiance arriving at the position
+fn light_radiance(light: Light, position: vec3<f32>, l: ptr<function, vec3<f32>>) -> vec3<f32> {
+    if (light.position.w == LIGHT_TYPE_DIRECTIONAL) {
+        *l = -light.direction.xyz;
+        return light.color.rgb;
+    }
+    var to_light = light.position.xyz - position;
+    var distance_squared = max(dot(to_light, to_light), 1e-4);
+    *l = to_light * inverseSqrt(distance_squared);
+    // inverse square falloff windowed by the range
+    var attenuation = 1.0 / distance_squared;
+    var range = light.direction.w;
+    if (range > 0.0) {
+        var ratio = sqrt(distance_squared) / range;
+        var window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+        attenuation *= window * window;
+    }
+    if (light.position.w == LIGHT_TYPE_SPOT) {
+        var cos_angle = dot(light.direction.xyz, -*l);
+        attenuation *= smoothstep(light.cone.y, light.cone.x, cos_angle);
+    }
+    return light.color.rgb * attenuation;
+}
+
+// fraction of the shadow casting light reaching the position in one cascade, filtered over the kernel
+fn cascade_visibility(cascade: i32, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    var normal_offset = normal * uniforms.shadow.z * uniforms.shadow_texel_sizes[cascade];
+    var p = uniforms.shadow_projection_views[cascade] * vec4<f32>(position + normal_offset, 1.0);
+    var uv = p.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    var depth = p.z - uniforms.shadow.y;
+    var texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+    var radius = i32(uniforms.shadow.w);
+    var visibility = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            var offset = vec2<f32>(f32(x), f32(y)) * texel;
+            visibility += textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, cascade, depth);
+        }
+    }
+    var tap_count = f32((2 * radius + 1) * (2 * radius + 1));
+    // outside of the map stays lit
+    var inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0)) && depth <= 1.0;
+    return select(1.0, visibility / tap_count, inside);
+}
+
+// first cascade containing the view depth, -1 outside of them all
+fn shadow_cascade(view_depth: f32) -> i32 {
+    var cascade_count = i32(uniforms.shadow_cascades.x);
+    for (var cascade = 0; cascade < cascade_count; cascade++) {
+        if (view_depth <= uniforms.shadow_splits[cascade]) {
+            return cascade;
+        }
+    }
+    return -1;
+}
+
+// fades into the next cascade over the end of each slice, and out of the last one
+fn shadow_visibility(position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    var view_depth = dot(uniforms.camera_depth, vec4<f32>(position, 1.0));
+    var cascade = shadow_cascade(view_depth);
+    if (cascade < 0 || view_depth < uniforms.shadow_cascades.w) {
+        return 1.0;
+    }
+    var visibility = cascade_visibility(cascade, position, normal);
+    var slice_start = select(uniforms.shadow_splits[max(cascade - 1, 0)], uniforms.shadow_cascades.w, cascade == 0);
+    var slice_end = uniforms.shadow_splits[cascade];
+    var blend_width = max((slice_end - slice_start) * uniforms.shadow_cascades.y, 1e-5);
+    var t = clamp((view_depth - (slice_end - blend_width)) / blend_width, 0.0, 1.0);
+    if (t > 0.0) {
+        var next_visibility = 1.0;
+        if (cascade + 1 < i32(uniforms.shadow_cascades.x)) {
+            next_visibility = cascade_visibility(cascade + 1, position, normal);
+        }
+        visibility = mix(visibility, next_visibility, t);
+    }
+    return visibility;
+}
+
+// tints the surface with the color of its cascade in the debug view
+fn cascade_debug_color(position: vec3<f32>, color: vec3<f32>) -> vec3<f32> {
+    if (uniforms.shadow_cascades.z != 1.0 || uniforms.shadow.x < 0.0) {
+        return color;
+    }
+    var tints = array<vec3<f32>, 4>(
+        vec3<f32>(1.0, 0.25, 0.25),
+        vec3<f32>(0.25, 1.0, 0.25),
+        vec3<f32>(0.25, 0.25, 1.0),
+        vec3<f32>(1.0, 1.0, 0.25));
+    var cascade = shadow_cascade(dot(uniforms.camera_depth, vec4<f32>(position, 1.0)));
+    if (cascade < 0) {
+        return color;
+    }
+    return color * tints[cascade];
+}
+
+// radiance of the light with its shadow when it is the casting one
+fn shadowed_light_radiance(light_index: u32, position: vec3<f32>, normal: vec3<f32>, l: ptr<function, vec3<f32>>) -> vec3<f32> {
+    var radiance = light_radiance(lights.lights[light_index], position, l);
+    if (i32(light_index) == i32(uniforms.shadow.x)) {
+        radiance *= shadow_visibility(position, normal);
+    }
+    return radiance;
+}
+
+// GGX normal distribution
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32 {
+    var alpha2 = alpha * alpha;
+    var d = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    return alpha2 / (PI * d * d);
+}
+
+// height-correlated Smith visibility, including the 1 / (4 n.l n.v) denominator
+fn visibility_smith(n_dot_l: f32, n_dot_v: f32, alpha: f32) -> f32 {
+    var alpha2 = alpha * alpha;
+    var ggx_v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - alpha2) + alpha2);
+    var ggx_l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - alpha2) + alpha2);
+    return 0.5 / max(ggx_v + ggx_l, 1e-5);
+}
+
+fn fresnel_schlick(f0: vec3<f32>, v_dot_h: f32) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(1.0 - v_dot_h, 5.0);
+}
+
+// Cook-Torrance specular with a Lambertian diffuse lobe, multiplied by n.l
+fn brdf(
+    n: vec3<f32>,
+    v: vec3<f32>,
+    l: vec3<f32>,
+    base_color: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+) -> vec3<f32> {
+    var h = normalize(l + v);
+    var n_dot_l = clamp(dot(n, l), 0.0, 1.0);
+    var n_dot_v = clamp(abs(dot(n, v)), 1e-4, 1.0);
+    var n_dot_h = clamp(dot(n, h), 0.0, 1.0);
+    var v_dot_h = clamp(dot(v, h), 0.0, 1.0);
+    var alpha = roughness * roughness;
+    var f0 = mix(vec3<f32>(0.04), base_color, metallic);
+    var f = fresnel_schlick(f0, v_dot_h);
+    var specular = f * distribution_ggx(n_dot_h, alpha) * visibility_smith(n_dot_l, n_dot_v, alpha);
+    var diffuse = (1.0 - f) * (1.0 - metallic) * base_color / PI;
+    return (diffuse + specular) * n_dot_l;
+}
+
+fn fresnel_schlick_roughness(f0: vec3<f32>, n_dot_v: f32, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(1.0 - n_dot_v, 5.0);
+}
+
+// direction in the environment turned the same way as the skybox
+fn environment_direction(direction: vec3<f32>) -> vec3<f32> {
+    var c = cos(uniforms.environment.w);
+    var s = sin(uniforms.environment.w);
+    return vec3<f32>(c * direction.x - s * direction.z, direction.y, s * direction.x + c * direction.z);
+}
+
+// split-sum image-based lighting from the prefiltered environment
+fn environment_lighting(
+    n: vec3<f32>,
+    v: vec3<f32>,
+    base_color: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+) -> vec3<f32> {
+    var n_dot_v = clamp(dot(n, v), 1e-4, 1.0);
+    var f0 = mix(vec3<f32>(0.04), base_color, metallic);
+    var f = fresnel_schlick_roughness(f0, n_dot_v, roughness);
+    var irradiance = textureSampleLevel(irradiance_environment, environment_sampler, environment_direction(n), 0.0).rgb;
+    var diffuse = (1.0 - f) * (1.0 - metallic) * irradiance * base_color;
+    var r = reflect(-v, n);
+    var lod = roughness * (uniforms.environment.x - 1.0);
+    var prefiltered = textureSampleLevel(specular_environment, environment_sampler, environment_direction(r), lod).rgb;
+    var brdf = textureSampleLevel(brdf_lut, environment_sampler, vec2<f32>(n_dot_v, roughness), 0.0).rg;
+    var specular = prefiltered * (f0 * brdf.x + brdf.y);
+    return (diffuse + specular) * uniforms.environment.y;
+}
+
+// occlusion of the pixel from the screen-space pass, blurred at the size of the target
+fn screen_ambient_occlusion(position: vec4<f32>) -> f32 {
+    var size = vec2<i32>(textureDimensions(ambient_occlusion_texture));
+    var coord = clamp(vec2<i32>(position.xy), vec2<i32>(0), size - 1);
+    return textureLoad(ambient_occlusion_texture, coord, 0).r;
+}
+
+fn select_tex_coord(tex_coord_set: f32, tex_coord_0: vec2<f32>, tex_coord_1: vec2<f32>) -> vec2<f32> {
+    return select(tex_coord_0, tex_coord_1, tex_coord_set == 1.0);
+}
+
+// perturbs the normal with the normal texture, deriving the tangent frame
+// from screen-space derivatives when the vertices have no tangents
+fn perturb_normal(
+    n: vec3<f32>,
+    tangent: vec4<f32>,
+    position: vec3<f32>,
+    tex_coord: vec2<f32>,
+    sampled: vec3<f32>,
+) -> vec3<f32> {
+    var dp1 = dpdx(position);
+    var dp2 = dpdy(position);
+    var duv1 = dpdx(tex_coord);
+    var duv2 = dpdy(tex_coord);
+    var dp2perp = cross(dp2, n);
+    var dp1perp = cross(n, dp1);
+    var derived_t = dp2perp * duv1.x + dp1perp * duv2.x;
+    var derived_b = dp2perp * duv1.y + dp1perp * duv2.y;
+    var has_tangent = dot(tangent.xyz, tangent.xyz) > 0.0;
+    var t = select(derived_t, tangent.xyz, has_tangent);
+    var b = select(derived_b, cross(n, tangent.xyz) * tangent.w, has_tangent);
+    var scale = inverseSqrt(max(max(dot(t, t), dot(b, b)), 1e-12));
+    var tbn = mat3x3<f32>(t * scale, b * scale, n);
+    var normal_scale = material.texture_parameters.y;
+    var m = (sampled * 2.0 - 1.0) * vec3<f32>(normal_scale, normal_scale, 1.0);
+    return normalize(tbn * m);
+}
+
+struct SurfaceOut {
+    @location(0) color: vec4<f32>,
+    @location(1) velocity: vec2<f32>,
+}
+
+// motion in texture coordinates since the previous frame, without the jitter
+fn velocity(clip_position: vec4<f32>, previous_clip_position: vec4<f32>) -> vec2<f32> {
+    var current = clip_position.xy / clip_position.w - uniforms.jitter.xy;
+    var previous = previous_clip_position.xy / previous_clip_position.w;
+    return (current - previous) * vec2<f32>(0.5, -0.5);
+}
+
+@fragment
+fn frag_main(in: VertexOut, @builtin(front_facing) front_facing: bool) -> SurfaceOut {
+    var out: SurfaceOut;
+    out.color = shade_surface(in, front_facing, false);
+    out.velocity = velocity(in.clip_position, in.previous_clip_position);
     return out;
 }
 
+// masked materials of multisampled pipelines with alpha-to-coverage
 @fragment
-fn frag_main(
-    @builtin(position) coord_in: vec4<f32>,
-    @location(0) object_normal: vec4<f32>,
-    @location(1) object_position: vec4<f32>,
-    @location(2) camera_position: vec4<f32>,
-) -> @location(0) vec4<f32> {
-    var l = normalize(camera_position.xyz - object_position.xyz);
-    var n = normalize(object_normal.xyz);
-    var d = dot(n, l);
-    var intensity = vec3<f32>(max(d, 0.3));
-    return vec4<f32>(intensity, 1.0);
+fn frag_alpha_to_coverage_main(in: VertexOut, @builtin(front_facing) front_facing: bool) -> SurfaceOut {
+    var out: SurfaceOut;
+    out.color = shade_surface(in, front_facing, true);
+    out.velocity = velocity(in.clip_position, in.previous_clip_position);
+    return out;
+}
+
+// masked materials in the depth prepass, which only keeps the texels above the cutoff
+@fragment
+fn frag_alpha_test_main(in: VertexOut) {
+    var base_color_tex_coord = select_tex_coord(material.tex_coords.x, in.tex_coord_0, in.tex_coord_1);
+    var base_color = material.base_color_factor * in.color * textureSample(base_color_texture, base_color_sampler, base_color_tex_coord);
+    if (base_color.a < material.parameters.z) {
+        discard;
+    }
+}
+
+fn shade_surface(in: VertexOut, front_facing: bool, alpha_to_coverage: bool) -> vec4<f32> {
+    var object_normal = in.object_normal;
+    var object_position = in.object_position;
+    var camera_position = in.camera_position;
+    var tex_coord_0 = in.tex_coord_0;
+    var tex_coord_1 = in.tex_coord_1;
+    var object_tangent = in.object_tangent;
+    var vertex_color = in.color;
+    var base_color_tex_coord = select_tex_coord(material.tex_coords.x, tex_coord_0, tex_coord_1);
+    var metallic_roughness_tex_coord = select_tex_coord(material.tex_coords.y, tex_coord_0, tex_coord_1);
+    var normal_tex_coord = select_tex_coord(material.tex_coords.z, tex_coord_0, tex_coord_1);
+    var occlusion_tex_coord = select_tex_coord(material.tex_coords.w, tex_coord_0, tex_coord_1);
+    var emissive_tex_coord = select_tex_coord(material.texture_parameters.x, tex_coord_0, tex_coord_1);
+    var normal_sample = textureSample(normal_texture, normal_sampler, normal_tex_coord).xyz;
+    var metallic_roughness_sample = textureSample(metallic_roughness_texture, metallic_roughness_sampler, metallic_roughness_tex_coord);
+    var occlusion_sample = textureSample(occlusion_texture, occlusion_sampler, occlusion_tex_coord).r;
+    var v = normalize(camera_position.xyz - object_position.xyz);
+    // points and lines without normals face the camera
+    var has_normal = dot(object_normal.xyz, object_normal.xyz) > 0.0;
+    var geometric_normal = select(v, normalize(object_normal.xyz), has_normal);
+    // back faces only reach the fragment stage for double-sided materials
+    geometric_normal = select(-geometric_normal, geometric_normal, front_facing || !has_normal);
+    var n = perturb_normal(geometric_normal, object_tangent, object_position.xyz, normal_tex_coord, normal_sample);
+    // textures hold roughness in green and metallic in blue
+    var metallic = clamp(material.parameters.x * metallic_roughness_sample.b, 0.0, 1.0);
+    var roughness = clamp(material.parameters.y * metallic_roughness_sample.g, 0.04, 1.0);
+    var occlusion = 1.0 + material.texture_parameters.z * (occlusion_sample - 1.0);
+    var base_color = material.base_color_factor * vertex_color * textureSample(base_color_texture, base_color_sampler, base_color_tex_coord);
+    var emissive = material.emissive_factor.rgb * textureSample(emissive_texture, emissive_sampler, emissive_tex_coord).rgb;
+    // the coverage is sharpened around the cutoff so only the edges of the mask soften
+    var alpha = 1.0;
+    var alpha_width = max(fwidth(base_color.a), 1e-4);
+    if (material.parameters.w == ALPHA_MODE_MASK) {
+        if (alpha_to_coverage) {
+            alpha = clamp((base_color.a - material.parameters.z) / alpha_width + 0.5, 0.0, 1.0);
+        } else if (base_color.a < material.parameters.z) {
+            discard;
+        }
+    } else if (material.parameters.w == ALPHA_MODE_BLEND) {
+        alpha = base_color.a;
+    }
+    // headlight when the scene has no lights
+    var color = brdf(n, v, v, base_color.rgb, metallic, roughness) * PI;
+    var light_count = lights.count.x;
+    if (light_count > 0u) {
+        color = vec3<f32>(0.0);
+        for (var i = 0u; i < light_count; i++) {
+            var l: vec3<f32>;
+            var radiance = shadowed_light_radiance(i, object_position.xyz, geometric_normal, &l);
+            color += brdf(n, v, l, base_color.rgb, metallic, roughness) * radiance;
+        }
+    }
+    // ambient from the environment, or a constant term without one
+    var ambient = vec3<f32>(0.03) * base_color.rgb;
+    if (uniforms.environment.z == 1.0) {
+        ambient = environment_lighting(n, v, base_color.rgb, metallic, roughness);
+    }
+    color += ambient * occlusion * screen_ambient_occlusion(in.position);
+    color += emissive;
+    // linear HDR, tonemapped and encoded by the post processing
+    color = cascade_debug_color(object_position.xyz, color);
+    return vec4<f32>(color, alpha);
+}
+
+struct GroundOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) clip_position: vec4<f32>,
+    @location(2) previous_clip_position: vec4<f32>,
+}
+
+// quad under the model from two triangles without vertex buffers
+@vertex
+fn vert_ground_main(@builtin(vertex_index) vertex_index: u32) -> GroundOut {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(-1.0, 1.0), vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, -1.0), vec2<f32>(-1.0, 1.0), vec2<f32>(1.0, 1.0));
+    var corner = corners[vertex_index] * uniforms.ground.w;
+    var world_position = vec4<f32>(uniforms.ground.x + corner.x, uniforms.ground.z, uniforms.ground.y + corner.y, 1.0);
+    var out: GroundOut;
+    out.position = uniforms.projection_view * world_position;
+    out.world_position = world_position;
+    out.clip_position = out.position;
+    out.previous_clip_position = uniforms.previous_projection_view * world_position;
+    return out;
+}
+
+// matte surface receiving the lights and the shadow
+@fragment
+fn frag_ground_main(in: GroundOut) -> SurfaceOut {
+    var world_position = in.world_position;
+    var n = vec3<f32>(0.0, 1.0, 0.0);
+    var v = normalize(uniforms.camera_position.xyz - world_position.xyz);
+    var base_color = vec3<f32>(0.8);
+    var color = brdf(n, v, v, base_color, 0.0, 1.0) * PI;
+    var light_count = lights.count.x;
+    if (light_count > 0u) {
+        color = vec3<f32>(0.0);
+        for (var i = 0u; i < light_count; i++) {
+            var l: vec3<f32>;
+            var radiance = shadowed_light_radiance(i, world_position.xyz, n, &l);
+            color += brdf(n, v, l, base_color, 0.0, 1.0) * radiance;
+        }
+    }
+    var ambient = vec3<f32>(0.03) * base_color;
+    if (uniforms.environment.z == 1.0) {
+        ambient = environment_lighting(n, v, base_color, 0.0, 1.0);
+    }
+    ambient *= screen_ambient_occlusion(in.position);
+    var out: SurfaceOut;
+    out.color = vec4<f32>(cascade_debug_color(world_position.xyz, color + ambient), 1.0);
+    out.velocity = velocity(in.clip_position, in.previous_clip_position);
+    return out;
 }
         ";
         ShaderModule::with_code(device, code)
@@ -0,0 +1,105 @@
+
+use std::sync::Arc;
+
+use super::Shaders;
+use crate::render::device::Device;
+use crate::render::shader::ShaderModule;
+
+impl Shaders {
+    pub fn depth_of_field(device: &Arc<Device>) -> Arc<ShaderModule> {
+        let code = "
+struct Settings {
+    inverse_projection: mat4x4<f32>,
+    // focus distance, aperture diameter, focal length, pixels per scene unit on the sensor
+    lens: vec4<f32>,
+    // blade count (0 for a circle), blade rotation, maximum radius in pixels
+    bokeh: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> settings: Settings;
+
+@group(0) @binding(1)
+var color_sampler: sampler;
+
+@group(0) @binding(2)
+var color_texture: texture_2d<f32>;
+
+@group(0) @binding(3)
+var depth_texture: texture_depth_2d;
+
+const PI: f32 = 3.141592653589793;
+const GOLDEN_ANGLE: f32 = 2.39996323;
+// spacing of the spiral rings in pixels
+const RADIUS_STEP: f32 = 0.5;
+
+// one triangle covering the target
+@vertex
+fn vert_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    var tex_coord = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(tex_coord.x * 2.0 - 1.0, 1.0 - tex_coord.y * 2.0, 0.0, 1.0);
+}
+
+// distance from the camera plane of the prepass depth
+fn view_depth(coord: vec2<i32>) -> f32 {
+    var size = vec2<i32>(textureDimensions(depth_texture));
+    var depth = textureLoad(depth_texture, clamp(coord, vec2<i32>(0), size - 1), 0);
+    var p = settings.inverse_projection * vec4<f32>(0.0, 0.0, depth, 1.0);
+    return max(-p.z / p.w, 0.0001);
+}
+
+// thin lens circle of confusion as a radius in pixels
+fn circle_of_confusion(depth: f32) -> f32 {
+    var focus_distance = settings.lens.x;
+    var aperture = settings.lens.y;
+    var focal_length = settings.lens.z;
+    var diameter = aperture * focal_length * abs(1.0 - focus_distance / depth) / max(focus_distance - focal_length, 0.0001);
+    return min(diameter * settings.lens.w * 0.5, settings.bokeh.z);
+}
+
+// stretches the round spiral out to the edges of the aperture polygon
+fn bokeh_scale(angle: f32) -> f32 {
+    var blade_count = settings.bokeh.x;
+    if (blade_count < 3.0) {
+        return 1.0;
+    }
+    var segment = 2.0 * PI / blade_count;
+    var a = angle - settings.bokeh.y;
+    a = a - segment * floor(a / segment);
+    return cos(PI / blade_count) / cos(a - PI / blade_count);
+}
+
+// gathers along a golden angle spiral, letting samples in front of the pixel spread over it
+// while the background stays behind sharper surfaces (Bokeh depth of field in a single pass,
+// Gustafsson 2018)
+@fragment
+fn frag_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    var coord = vec2<i32>(position.xy);
+    var size = vec2<f32>(textureDimensions(color_texture));
+    var center_depth = view_depth(coord);
+    var center_radius = circle_of_confusion(center_depth);
+    var color = textureLoad(color_texture, coord, 0).rgb;
+    var total = 1.0;
+    var radius = RADIUS_STEP;
+    var angle = 0.0;
+    for (var i = 0; i < 1024 && radius < settings.bokeh.z; i++) {
+        var offset = vec2<f32>(cos(angle), sin(angle)) * radius * bokeh_scale(angle);
+        var sample_coord = position.xy + offset;
+        var sample_color = textureSampleLevel(color_texture, color_sampler, sample_coord / size, 0.0).rgb;
+        var sample_depth = view_depth(vec2<i32>(sample_coord));
+        var sample_radius = circle_of_confusion(sample_depth);
+        if (sample_depth > center_depth) {
+            sample_radius = min(sample_radius, center_radius * 2.0);
+        }
+        var weight = smoothstep(radius - RADIUS_STEP, radius + RADIUS_STEP, sample_radius);
+        color += mix(color / total, sample_color, weight);
+        total += 1.0;
+        radius += RADIUS_STEP / radius;
+        angle += GOLDEN_ANGLE;
+    }
+    return vec4<f32>(color / total, 1.0);
+}
+        ";
+        ShaderModule::with_code(device, code)
+    }
+}
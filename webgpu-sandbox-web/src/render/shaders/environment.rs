@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use super::Shaders;
+use crate::render::device::Device;
+use crate::render::shader::ShaderModule;
+
+impl Shaders {
+    // compute passes baking the image-based lighting maps from an equirectangular environment
+    pub fn environment(device: &Arc<Device>) -> Arc<ShaderModule> {
+        let code = "
+struct Parameters {
+    // roughness, environment cube size, sample count
+    values: vec4<f32>,
+}
+@binding(0) @group(0) var equirect_texture: texture_2d<f32>;
+@binding(1) @group(0) var linear_sampler: sampler;
+@binding(2) @group(0) var cube_output: texture_storage_2d_array<rgba16float, write>;
+@binding(3) @group(0) var source_mip: texture_2d_array<f32>;
+@binding(4) @group(0) var environment_cube: texture_cube<f32>;
+@binding(5) @group(0) var<uniform> parameters: Parameters;
+@binding(6) @group(0) var lut_output: texture_storage_2d<rgba16float, write>;
+
+const PI: f32 = 3.14159265359;
+
+// direction through the texel center of a cube face, following the cubemap face orientation
+fn cube_direction(face: u32, texel: vec2<u32>, size: u32) -> vec3<f32> {
+    var uv = (vec2<f32>(texel) + 0.5) / f32(size);
+    var c = uv * 2.0 - 1.0;
+    switch face {
+        case 0u: { return normalize(vec3<f32>(1.0, -c.y, -c.x)); }
+        case 1u: { return normalize(vec3<f32>(-1.0, -c.y, c.x)); }
+        case 2u: { return normalize(vec3<f32>(c.x, 1.0, c.y)); }
+        case 3u: { return normalize(vec3<f32>(c.x, -1.0, -c.y)); }
+        case 4u: { return normalize(vec3<f32>(c.x, -c.y, 1.0)); }
+        default: { return normalize(vec3<f32>(-c.x, -c.y, -1.0)); }
+    }
+}
+
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    var bits = reverseBits(i);
+    return vec2<f32>(f32(i) / f32(count), f32(bits) * 2.3283064365386963e-10);
+}
+
+// half vector around n distributed by GGX
+fn importance_sample_ggx(xi: vec2<f32>, n: vec3<f32>, alpha: f32) -> vec3<f32> {
+    var phi = 2.0 * PI * xi.x;
+    var cos_theta = sqrt((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y));
+    var sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    var h = vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+    var up = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 0.0, 1.0), abs(n.z) < 0.999);
+    var tangent = normalize(cross(up, n));
+    var bitangent = cross(n, tangent);
+    return normalize(tangent * h.x + bitangent * h.y + n * h.z);
+}
+
+fn distribution_ggx(n_dot_h: f32, alpha: f32) -> f32 {
+    var alpha2 = alpha * alpha;
+    var d = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    return alpha2 / (PI * d * d);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn equirect_to_cube(@builtin(global_invocation_id) id: vec3<u32>) {
+    var size = textureDimensions(cube_output).x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+    var d = cube_direction(id.z, id.xy, size);
+    var uv = vec2<f32>(atan2(d.z, d.x) / (2.0 * PI) + 0.5, acos(clamp(d.y, -1.0, 1.0)) / PI);
+    var color = textureSampleLevel(equirect_texture, linear_sampler, uv, 0.0);
+    textureStore(cube_output, id.xy, id.z, vec4<f32>(color.rgb, 1.0));
+}
+
+// box filters the previous mip level of each face
+@compute @workgroup_size(8, 8, 1)
+fn downsample(@builtin(global_invocation_id) id: vec3<u32>) {
+    var size = textureDimensions(cube_output).x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+    var texel = vec2<i32>(id.xy) * 2;
+    var layer = i32(id.z);
+    var color = textureLoad(source_mip, texel, layer, 0)
+        + textureLoad(source_mip, texel + vec2<i32>(1, 0), layer, 0)
+        + textureLoad(source_mip, texel + vec2<i32>(0, 1), layer, 0)
+        + textureLoad(source_mip, texel + vec2<i32>(1, 1), layer, 0);
+    textureStore(cube_output, id.xy, id.z, vec4<f32>(color.rgb * 0.25, 1.0));
+}
+
+// GGX prefiltered radiance assuming n = v = r, sampling lower mips by the sample footprint
+@compute @workgroup_size(8, 8, 1)
+fn prefilter_specular(@builtin(global_invocation_id) id: vec3<u32>) {
+    var size = textureDimensions(cube_output).x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+    var n = cube_direction(id.z, id.xy, size);
+    var roughness = parameters.values.x;
+    var environment_size = parameters.values.y;
+    var sample_count = u32(parameters.values.z);
+    var alpha = roughness * roughness;
+    var color = vec3<f32>(0.0);
+    var total_weight = 0.0;
+    for (var i = 0u; i < sample_count; i++) {
+        var h = importance_sample_ggx(hammersley(i, sample_count), n, alpha);
+        var l = normalize(2.0 * dot(n, h) * h - n);
+        var n_dot_l = dot(n, l);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+        var n_dot_h = max(dot(n, h), 0.0);
+        var pdf = distribution_ggx(n_dot_h, alpha) * 0.25;
+        var sample_solid_angle = 1.0 / (f32(sample_count) * pdf + 1e-4);
+        var texel_solid_angle = 4.0 * PI / (6.0 * environment_size * environment_size);
+        var lod = select(max(0.5 * log2(sample_solid_angle / texel_solid_angle) + 1.0, 0.0), 0.0, roughness == 0.0);
+        color += textureSampleLevel(environment_cube, linear_sampler, l, lod).rgb * n_dot_l;
+        total_weight += n_dot_l;
+    }
+    textureStore(cube_output, id.xy, id.z, vec4<f32>(color / max(total_weight, 1e-4), 1.0));
+}
+
+// cosine weighted convolution over the hemisphere
+@compute @workgroup_size(8, 8, 1)
+fn convolve_irradiance(@builtin(global_invocation_id) id: vec3<u32>) {
+    var size = textureDimensions(cube_output).x;
+    if (id.x >= size || id.y >= size) {
+        return;
+    }
+    var n = cube_direction(id.z, id.xy, size);
+    var up = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(n.y) < 0.999);
+    var right = normalize(cross(up, n));
+    up = cross(n, right);
+    var irradiance = vec3<f32>(0.0);
+    var sample_count = 0.0;
+    let delta = 0.05;
+    for (var phi = 0.0; phi < 2.0 * PI; phi += delta) {
+        for (var theta = 0.0; theta < 0.5 * PI; theta += delta) {
+            var tangent_sample = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            var l = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * n;
+            // a coarse mip keeps the sparse samples from aliasing
+            irradiance += textureSampleLevel(environment_cube, linear_sampler, l, 4.0).rgb * cos(theta) * sin(theta);
+            sample_count += 1.0;
+        }
+    }
+    irradiance = PI * irradiance / sample_count;
+    textureStore(cube_output, id.xy, id.z, vec4<f32>(irradiance, 1.0));
+}
+
+// split-sum scale and bias of f0 indexed by n.v and roughness
+@compute @workgroup_size(8, 8, 1)
+fn integrate_brdf(@builtin(global_invocation_id) id: vec3<u32>) {
+    var size = textureDimensions(lut_output);
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+    var n_dot_v = (f32(id.x) + 0.5) / f32(size.x);
+    var roughness = (f32(id.y) + 0.5) / f32(size.y);
+    var alpha = roughness * roughness;
+    var v = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    var n = vec3<f32>(0.0, 0.0, 1.0);
+    var scale = 0.0;
+    var bias = 0.0;
+    let sample_count = 512u;
+    for (var i = 0u; i < sample_count; i++) {
+        var h = importance_sample_ggx(hammersley(i, sample_count), n, alpha);
+        var l = normalize(2.0 * dot(v, h) * h - v);
+        var n_dot_l = max(l.z, 0.0);
+        var n_dot_h = max(h.z, 0.0);
+        var v_dot_h = max(dot(v, h), 0.0);
+        if (n_dot_l > 0.0) {
+            // Smith GGX for image-based lighting
+            var k = alpha * 0.5;
+            var g = (n_dot_v / (n_dot_v * (1.0 - k) + k)) * (n_dot_l / (n_dot_l * (1.0 - k) + k));
+            var g_vis = g * v_dot_h / max(n_dot_h * n_dot_v, 1e-4);
+            var fc = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fc) * g_vis;
+            bias += fc * g_vis;
+        }
+    }
+    var result = vec2<f32>(scale, bias) / f32(sample_count);
+    textureStore(lut_output, id.xy, vec4<f32>(result, 0.0, 1.0));
+}
+        ";
+        ShaderModule::with_code(device, code)
+    }
+}
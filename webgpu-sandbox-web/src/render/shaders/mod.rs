@@ -1,6 +1,11 @@
 
 mod skybox;
 mod common;
+mod environment;
+mod post_process;
+mod ambient_occlusion;
+mod temporal_antialiasing;
+mod depth_of_field;
 
 pub struct Shaders {}
 
@@ -0,0 +1,244 @@
+
+use std::sync::Arc;
+
+use super::Shaders;
+use crate::render::device::Device;
+use crate::render::shader::ShaderModule;
+
+impl Shaders {
+    pub fn post_process(device: &Arc<Device>) -> Arc<ShaderModule> {
+        let code = "
+struct Settings {
+    // exposure multiplier, tonemapping (0 ACES, 1 AgX, 2 Reinhard), bloom intensity, bloom radius
+    tone: vec4<f32>,
+    // vignette intensity, grain intensity, grain seed, 1 when the FXAA pass adds the grain
+    finish: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> settings: Settings;
+
+@group(0) @binding(1)
+var source_sampler: sampler;
+
+@group(0) @binding(2)
+var source_texture: texture_2d<f32>;
+
+@group(0) @binding(3)
+var bloom_texture: texture_2d<f32>;
+
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+// one triangle covering the target
+@vertex
+fn vert_main(@builtin(vertex_index) vertex_index: u32) -> VertexOut {
+    var tex_coord = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOut;
+    out.position = vec4<f32>(tex_coord.x * 2.0 - 1.0, 1.0 - tex_coord.y * 2.0, 0.0, 1.0);
+    out.tex_coord = tex_coord;
+    return out;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// 13 taps of the source around the texel, weighted like four overlapping 2x2 boxes around a
+// center box (Jimenez, Next Generation Post Processing in Call of Duty: Advanced Warfare).
+// the first level weights each box by its brightness so single bright texels do not flicker.
+fn downsample(tex_coord: vec2<f32>, karis_average: bool) -> vec3<f32> {
+    var texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+    var a = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(-2.0, -2.0)).rgb;
+    var b = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(0.0, -2.0)).rgb;
+    var c = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(2.0, -2.0)).rgb;
+    var d = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(-2.0, 0.0)).rgb;
+    var e = textureSample(source_texture, source_sampler, tex_coord).rgb;
+    var f = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(2.0, 0.0)).rgb;
+    var g = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(-2.0, 2.0)).rgb;
+    var h = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(0.0, 2.0)).rgb;
+    var i = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(2.0, 2.0)).rgb;
+    var j = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(-1.0, -1.0)).rgb;
+    var k = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(1.0, -1.0)).rgb;
+    var l = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(-1.0, 1.0)).rgb;
+    var m = textureSample(source_texture, source_sampler, tex_coord + texel * vec2<f32>(1.0, 1.0)).rgb;
+    var boxes = array<vec3<f32>, 5>(
+        (j + k + l + m) * 0.25,
+        (a + b + d + e) * 0.25,
+        (b + c + e + f) * 0.25,
+        (d + e + g + h) * 0.25,
+        (e + f + h + i) * 0.25);
+    var box_weights = array<f32, 5>(0.5, 0.125, 0.125, 0.125, 0.125);
+    var color = vec3<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var index = 0; index < 5; index++) {
+        var weight = box_weights[index];
+        if (karis_average) {
+            weight /= 1.0 + luminance(boxes[index]);
+        }
+        color += boxes[index] * weight;
+        weight_sum += weight;
+    }
+    return max(color / weight_sum, vec3<f32>(0.0));
+}
+
+@fragment
+fn frag_bloom_prefilter_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.tex_coord, true), 1.0);
+}
+
+@fragment
+fn frag_bloom_downsample_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.tex_coord, false), 1.0);
+}
+
+// 3x3 tent filter of the smaller level, added to the level above by the blend state
+@fragment
+fn frag_bloom_upsample_main(in: VertexOut) -> @location(0) vec4<f32> {
+    var radius = settings.tone.w;
+    var aspect = f32(textureDimensions(source_texture).x) / f32(textureDimensions(source_texture).y);
+    var offset = vec2<f32>(radius, radius * aspect);
+    var color = textureSample(source_texture, source_sampler, in.tex_coord).rgb * 4.0;
+    color += textureSample(source_texture, source_sampler, in.tex_coord + offset * vec2<f32>(0.0, -1.0)).rgb * 2.0;
+    color += textureSample(source_texture, source_sampler, in.tex_coord + offset * vec2<f32>(-1.0, 0.0)).rgb * 2.0;
+    color += textureSample(source_texture, source_sampler, in.tex_coord + offset * vec2<f32>(1.0, 0.0)).rgb * 2.0;
+    color += textureSample(source_texture, source_sampler, in.tex_coord + offset * vec2<f32>(0.0, 1.0)).rgb * 2.0;
+    color += textureSample(source_texture, source_sampler, in.tex_coord + offset * vec2<f32>(-1.0, -1.0)).rgb;
+    color += textureSample(source_texture, source_sampler, in.tex_coord + offset * vec2<f32>(1.0, -1.0)).rgb;
+    color += textureSample(source_texture, source_sampler, in.tex_coord + offset * vec2<f32>(-1.0, 1.0)).rgb;
+    color += textureSample(source_texture, source_sampler, in.tex_coord + offset * vec2<f32>(1.0, 1.0)).rgb;
+    return vec4<f32>(color / 16.0, 1.0);
+}
+
+fn rrt_and_odt_fit(v: vec3<f32>) -> vec3<f32> {
+    var a = v * (v + 0.0245786) - 0.000090537;
+    var b = v * (0.983729 * v + 0.4329510) + 0.238081;
+    return a / b;
+}
+
+fn tonemap_aces(color: vec3<f32>) -> vec3<f32> {
+    // sRGB to the rendering space and back, with the reference and output transforms baked in
+    var input_matrix = mat3x3<f32>(
+        0.59719, 0.07600, 0.02840,
+        0.35458, 0.90834, 0.13383,
+        0.04823, 0.01566, 0.83777);
+    var output_matrix = mat3x3<f32>(
+        1.60475, -0.10208, -0.00327,
+        -0.53108, 1.10813, -0.07276,
+        -0.07367, -0.00605, 1.07602);
+    return clamp(output_matrix * rrt_and_odt_fit(input_matrix * color), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// polynomial approximation of the AgX default contrast curve
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    var x2 = x * x;
+    var x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn tonemap_agx(color: vec3<f32>) -> vec3<f32> {
+    var inset_matrix = mat3x3<f32>(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104);
+    var outset_matrix = mat3x3<f32>(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116);
+    var min_ev = -12.47393;
+    var max_ev = 4.026069;
+    // log encoding over the dynamic range of the curve
+    var v = clamp(log2(max(inset_matrix * color, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    v = agx_contrast((v - min_ev) / (max_ev - min_ev));
+    v = outset_matrix * v;
+    // the curve produces display values, decoded back to linear
+    return pow(clamp(v, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(2.2));
+}
+
+fn tonemap_reinhard(color: vec3<f32>) -> vec3<f32> {
+    return clamp(color / (1.0 + luminance(color)), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// the canvas format stores values as they are, so the shader encodes sRGB
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    var c = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    var low = c * 12.92;
+    var high = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, c <= vec3<f32>(0.0031308));
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+    var p3 = fract(vec3<f32>(p.x, p.y, p.x) * 0.1031);
+    p3 += dot(p3, p3.yzx + 33.33);
+    return fract((p3.x + p3.y) * p3.z);
+}
+
+// noise changing every frame, strongest in the midtones
+fn film_grain(color: vec3<f32>, coord: vec2<f32>) -> vec3<f32> {
+    var noise = hash(coord + settings.finish.z * 17.0) - 0.5;
+    var midtones = 1.0 - abs(luminance(color) * 2.0 - 1.0);
+    return color + noise * settings.finish.y * (0.5 + midtones);
+}
+
+// exposure, bloom, tonemapping and vignette into display values
+@fragment
+fn frag_tonemap_main(in: VertexOut) -> @location(0) vec4<f32> {
+    var color = textureSample(source_texture, source_sampler, in.tex_coord).rgb;
+    var bloom = textureSample(bloom_texture, source_sampler, in.tex_coord).rgb;
+    color = mix(color, bloom, settings.tone.z) * settings.tone.x;
+    if (settings.tone.y == 0.0) {
+        color = tonemap_aces(color);
+    } else if (settings.tone.y == 1.0) {
+        color = tonemap_agx(color);
+    } else {
+        color = tonemap_reinhard(color);
+    }
+    var from_center = (in.tex_coord - 0.5) * 2.0;
+    color *= 1.0 - settings.finish.x * smoothstep(0.5, 1.5, dot(from_center, from_center));
+    var display = linear_to_srgb(color);
+    if (settings.finish.w == 0.0) {
+        display = film_grain(display, in.position.xy);
+    }
+    return vec4<f32>(display, 1.0);
+}
+
+// FXAA on the display values, blending along the edge direction found from the luma of the
+// diagonal neighbors
+@fragment
+fn frag_fxaa_main(in: VertexOut) -> @location(0) vec4<f32> {
+    var texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+    var uv = in.tex_coord;
+    var rgb_m = textureSample(source_texture, source_sampler, uv).rgb;
+    var luma_nw = luminance(textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(-1.0, -1.0)).rgb);
+    var luma_ne = luminance(textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(1.0, -1.0)).rgb);
+    var luma_sw = luminance(textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(-1.0, 1.0)).rgb);
+    var luma_se = luminance(textureSample(source_texture, source_sampler, uv + texel * vec2<f32>(1.0, 1.0)).rgb);
+    var luma_m = luminance(rgb_m);
+    var luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    var luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+    var direction = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se));
+    var direction_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * (1.0 / 8.0), 1.0 / 128.0);
+    var inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+    direction = clamp(direction * inverse_direction_min, vec2<f32>(-8.0), vec2<f32>(8.0)) * texel;
+    var rgb_a = 0.5 * (
+        textureSample(source_texture, source_sampler, uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        textureSample(source_texture, source_sampler, uv + direction * (2.0 / 3.0 - 0.5)).rgb);
+    var rgb_b = rgb_a * 0.5 + 0.25 * (
+        textureSample(source_texture, source_sampler, uv + direction * -0.5).rgb +
+        textureSample(source_texture, source_sampler, uv + direction * 0.5).rgb);
+    var luma_b = luminance(rgb_b);
+    // the wider blend overshoots when it crosses another edge
+    var display = select(rgb_b, rgb_a, luma_b < luma_min || luma_b > luma_max);
+    if (settings.finish.w == 1.0) {
+        display = film_grain(display, in.position.xy);
+    }
+    return vec4<f32>(display, 1.0);
+}
+        ";
+        ShaderModule::with_code(device, code)
+    }
+}
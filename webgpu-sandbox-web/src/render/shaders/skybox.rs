@@ -10,12 +10,32 @@ impl Shaders {
         let code = "
 struct Uniforms {
     projection_view: mat4x4<f32>,
+    // mode (0 gradient, 1 environment, 2 cubemap, 3 procedural), intensity, cos and sin of the rotation
+    parameters: vec4<f32>,
+    // direction towards the sun, turbidity
+    sun: vec4<f32>,
+    // projection view of the previous frame without the jitter
+    previous_projection_view: mat4x4<f32>,
+    // sub-pixel offset of the projection in normalized device coordinates
+    jitter: vec4<f32>,
 }
 @binding(0) @group(0) var<uniform> uniforms: Uniforms;
+@binding(1) @group(0) var environment_texture: texture_cube<f32>;
+@binding(2) @group(0) var environment_sampler: sampler;
+@binding(3) @group(0) var cubemap_texture: texture_cube<f32>;
+@binding(4) @group(0) var cubemap_sampler: sampler;
 
 struct VertexOut {
     @builtin(position) position: vec4<f32>,
     @location(0) normal: vec3<f32>,
+    @location(1) clip_position: vec4<f32>,
+    @location(2) previous_clip_position: vec4<f32>,
+}
+
+struct SkyOut {
+    @location(0) color: vec4<f32>,
+    // motion in texture coordinates since the previous frame, without the jitter
+    @location(1) velocity: vec2<f32>,
 }
 
 @vertex
@@ -27,21 +47,99 @@ fn vert_main(
     var out: VertexOut;
     out.position = uniforms.projection_view * p;
     out.normal = position;
+    out.clip_position = out.position;
+    out.previous_clip_position = uniforms.previous_projection_view * p;
     return out;
 }
 
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    var low = color / 12.92;
+    var high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+// Perez sky distribution for one of the Y, x, y channels
+fn perez(cos_theta: f32, gamma: f32, cos_gamma: f32, a: f32, b: f32, c: f32, d: f32, e: f32) -> f32 {
+    return (1.0 + a * exp(b / cos_theta)) * (1.0 + c * exp(d * gamma) + e * cos_gamma * cos_gamma);
+}
+
+// A Practical Analytic Model for Daylight (Preetham et al. 1999), returns linear sRGB
+fn preetham_sky(direction: vec3<f32>, sun: vec3<f32>, turbidity: f32) -> vec3<f32> {
+    var t = turbidity;
+    // zenith angles of the view and the sun clamped above the horizon
+    var cos_theta = max(direction.y, 0.001);
+    var sun_elevation = max(sun.y, 0.0);
+    var theta_s = acos(sun_elevation);
+    var cos_gamma = clamp(dot(direction, sun), -1.0, 1.0);
+    var gamma = acos(cos_gamma);
+    // zenith luminance in kcd/m^2 and chromaticity
+    var chi = (4.0 / 9.0 - t / 120.0) * (3.14159265 - 2.0 * theta_s);
+    var zenith_luminance = (4.0453 * t - 4.9710) * tan(chi) - 0.2155 * t + 2.4192;
+    var theta_s2 = theta_s * theta_s;
+    var theta_s3 = theta_s2 * theta_s;
+    var zenith_x = t * t * (0.00166 * theta_s3 - 0.00375 * theta_s2 + 0.00209 * theta_s)
+        + t * (-0.02903 * theta_s3 + 0.06377 * theta_s2 - 0.03202 * theta_s + 0.00394)
+        + (0.11693 * theta_s3 - 0.21196 * theta_s2 + 0.06052 * theta_s + 0.25886);
+    var zenith_y = t * t * (0.00275 * theta_s3 - 0.00610 * theta_s2 + 0.00317 * theta_s)
+        + t * (-0.04214 * theta_s3 + 0.08970 * theta_s2 - 0.04153 * theta_s + 0.00516)
+        + (0.15346 * theta_s3 - 0.26756 * theta_s2 + 0.06670 * theta_s + 0.26688);
+    // distribution coefficients
+    var luminance = zenith_luminance
+        * perez(cos_theta, gamma, cos_gamma, 0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703)
+        / perez(1.0, theta_s, sun_elevation, 0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703);
+    var x = zenith_x
+        * perez(cos_theta, gamma, cos_gamma, -0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452)
+        / perez(1.0, theta_s, sun_elevation, -0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452);
+    var y = zenith_y
+        * perez(cos_theta, gamma, cos_gamma, -0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529)
+        / perez(1.0, theta_s, sun_elevation, -0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529);
+    // xyY to XYZ to linear sRGB, with kcd/m^2 brought to display range
+    var luminance_scale = max(luminance, 0.0) * 0.05;
+    var xyz = vec3<f32>(x / y * luminance_scale, luminance_scale, (1.0 - x - y) / y * luminance_scale);
+    var rgb = mat3x3<f32>(
+        vec3<f32>(3.2406, -0.9689, 0.0557),
+        vec3<f32>(-1.5372, 1.8758, -0.2040),
+        vec3<f32>(-0.4986, 0.0415, 1.0570)) * xyz;
+    // sun disk and the darker ground below the horizon
+    var disk = smoothstep(0.99995, 0.99998, cos_gamma) * 20.0;
+    var ground = mix(vec3<f32>(0.05), rgb, smoothstep(-0.1, 0.0, direction.y));
+    // night falls once the sun sets
+    var dusk = smoothstep(-0.1, 0.05, sun.y);
+    return max(ground + disk * step(0.0, direction.y), vec3<f32>(0.0)) * dusk;
+}
+
 @fragment
-fn frag_main(
-    @builtin(position) coord_in: vec4<f32>,
-    @location(0) normal: vec3<f32>
-) -> @location(0) vec4<f32> {
+fn frag_main(in: VertexOut) -> SkyOut {
     // applies sky color (Ray Tracing in One Weekend, 4.2)
-    var direction = normalize(normal);
+    var direction = normalize(in.normal);
     var t = 0.5 * (direction.y + 1.0);
     var sky = vec3<f32>(0.5, 0.7, 1.0);
     var bottom = vec3<f32>(1.0);
-    var diffuse = mix(bottom, sky, t);
-    return vec4<f32>(diffuse, 1.0);
+    // the gradient is picked in display values
+    var diffuse = srgb_to_linear(mix(bottom, sky, t));
+    // turns the sky around +Y
+    var c = uniforms.parameters.z;
+    var s = uniforms.parameters.w;
+    var rotated = vec3<f32>(c * direction.x - s * direction.z, direction.y, s * direction.x + c * direction.z);
+    var intensity = uniforms.parameters.y;
+    // both cubes are sampled as linear like the meshes
+    if (uniforms.parameters.x == 1.0) {
+        var environment = textureSampleLevel(environment_texture, environment_sampler, rotated, 0.0).rgb;
+        diffuse = environment * intensity;
+    } else if (uniforms.parameters.x == 2.0) {
+        var cubemap = textureSampleLevel(cubemap_texture, cubemap_sampler, rotated, 0.0).rgb;
+        diffuse = cubemap * intensity;
+    } else if (uniforms.parameters.x == 3.0) {
+        // the sun stays fixed to the world to match the light it casts
+        var procedural = preetham_sky(direction, normalize(uniforms.sun.xyz), uniforms.sun.w);
+        diffuse = procedural * intensity;
+    }
+    var out: SkyOut;
+    out.color = vec4<f32>(diffuse, 1.0);
+    var current = in.clip_position.xy / in.clip_position.w - uniforms.jitter.xy;
+    var previous = in.previous_clip_position.xy / in.previous_clip_position.w;
+    out.velocity = (current - previous) * vec2<f32>(0.5, -0.5);
+    return out;
 }
         ";
         ShaderModule::with_code(device, code)
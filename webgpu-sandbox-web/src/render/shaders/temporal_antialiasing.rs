@@ -0,0 +1,114 @@
+
+use std::sync::Arc;
+
+use super::Shaders;
+use crate::render::device::Device;
+use crate::render::shader::ShaderModule;
+
+impl Shaders {
+    pub fn temporal_antialiasing(device: &Arc<Device>) -> Arc<ShaderModule> {
+        let code = "
+struct Settings {
+    // share of the current frame, history available
+    parameters: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> settings: Settings;
+
+@group(0) @binding(1)
+var history_sampler: sampler;
+
+@group(0) @binding(2)
+var current_texture: texture_2d<f32>;
+
+@group(0) @binding(3)
+var history_texture: texture_2d<f32>;
+
+@group(0) @binding(4)
+var velocity_texture: texture_2d<f32>;
+
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+// one triangle covering the target
+@vertex
+fn vert_main(@builtin(vertex_index) vertex_index: u32) -> VertexOut {
+    var tex_coord = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOut;
+    out.position = vec4<f32>(tex_coord.x * 2.0 - 1.0, 1.0 - tex_coord.y * 2.0, 0.0, 1.0);
+    out.tex_coord = tex_coord;
+    return out;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// the neighborhood bounds fit the colors tighter in luma and chroma
+fn rgb_to_ycocg(color: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        dot(color, vec3<f32>(0.25, 0.5, 0.25)),
+        dot(color, vec3<f32>(0.5, 0.0, -0.5)),
+        dot(color, vec3<f32>(-0.25, 0.5, -0.25)));
+}
+
+fn ycocg_to_rgb(color: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        color.x + color.y - color.z,
+        color.x + color.z,
+        color.x - color.y - color.z);
+}
+
+struct ResolveOut {
+    @location(0) color: vec4<f32>,
+    // the same image kept for the next frame
+    @location(1) history: vec4<f32>,
+}
+
+// blends the jittered frame into the history reprojected along the velocity, with the history
+// clamped to the colors around the pixel so disoccluded and changed surfaces do not ghost
+@fragment
+fn frag_main(in: VertexOut) -> ResolveOut {
+    var size = vec2<i32>(textureDimensions(current_texture));
+    var coord = vec2<i32>(in.position.xy);
+    var current = textureLoad(current_texture, coord, 0).rgb;
+    var minimum = vec3<f32>(1e9);
+    var maximum = vec3<f32>(-1e9);
+    // the longest motion around the pixel keeps the edges of moving surfaces with them
+    var velocity = vec2<f32>(0.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            var neighbor_coord = clamp(coord + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            var neighbor = rgb_to_ycocg(textureLoad(current_texture, neighbor_coord, 0).rgb);
+            minimum = min(minimum, neighbor);
+            maximum = max(maximum, neighbor);
+            var motion = textureLoad(velocity_texture, neighbor_coord, 0).xy;
+            if (dot(motion, motion) > dot(velocity, velocity)) {
+                velocity = motion;
+            }
+        }
+    }
+    var history_tex_coord = in.tex_coord - velocity;
+    var blend = settings.parameters.x;
+    var outside = any(history_tex_coord < vec2<f32>(0.0)) || any(history_tex_coord > vec2<f32>(1.0));
+    if (outside || settings.parameters.y == 0.0) {
+        blend = 1.0;
+    }
+    var history = textureSampleLevel(history_texture, history_sampler, history_tex_coord, 0.0).rgb;
+    history = ycocg_to_rgb(clamp(rgb_to_ycocg(history), minimum, maximum));
+    // weighted against the brightness so single bright samples do not flicker
+    var current_weight = blend / (1.0 + luminance(current));
+    var history_weight = (1.0 - blend) / (1.0 + luminance(history));
+    var color = (current * current_weight + history * history_weight) / max(current_weight + history_weight, 1e-6);
+    var out: ResolveOut;
+    out.color = vec4<f32>(color, 1.0);
+    out.history = out.color;
+    return out;
+}
+        ";
+        ShaderModule::with_code(device, code)
+    }
+}
@@ -0,0 +1,227 @@
+
+use std::sync::Arc;
+
+use crate::render::device::Device;
+use crate::shadow::ShadowSettings;
+
+use nalgebra_glm as glm;
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuTextureView,
+    GpuTextureFormat,
+    GpuTextureDescriptor,
+    GpuTextureViewDescriptor,
+    GpuTextureViewDimension,
+    gpu_texture_usage,
+    GpuSampler,
+    GpuSamplerDescriptor,
+    GpuFilterMode,
+    GpuCompareFunction,
+};
+
+pub const SHADOW_MAP_FORMAT: GpuTextureFormat = GpuTextureFormat::Depth32float;
+pub const MAX_CASCADE_COUNT: usize = 4;
+
+// depth rendered from a directional light into one layer per cascade, sampled with a
+// comparison sampler
+pub struct ShadowMap {
+    view: GpuTextureView,
+    layer_views: Vec<GpuTextureView>,
+    sampler: GpuSampler,
+    resolution: u32,
+}
+
+// light projection covering one slice of the view frustum
+pub struct Cascade {
+    pub projection_view: glm::Mat4,
+    // view depths where the slice starts and ends
+    pub near_depth: f32,
+    pub split_depth: f32,
+    // world-space width of a shadow map texel
+    pub texel_size: f32,
+}
+
+impl ShadowMap {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>, resolution: u32) -> Arc<Self> {
+        let resolution = resolution.max(1);
+        let extent: Vec<JsValue> = vec![resolution as f64, resolution as f64, MAX_CASCADE_COUNT as f64].into_iter().map(Into::into).collect();
+        let extent = extent.into_iter().collect::<js_sys::Array>();
+        let texture_descriptor = GpuTextureDescriptor::new(
+            SHADOW_MAP_FORMAT,
+            &extent,
+            gpu_texture_usage::RENDER_ATTACHMENT | gpu_texture_usage::TEXTURE_BINDING);
+        let texture = device.device().create_texture(&texture_descriptor);
+        let mut view_descriptor = GpuTextureViewDescriptor::new();
+        view_descriptor.dimension(GpuTextureViewDimension::N2dArray);
+        let view = texture.create_view_with_descriptor(&view_descriptor);
+        // render targets of the cascades
+        let layer_views = (0..MAX_CASCADE_COUNT)
+            .map(|layer| {
+                let mut view_descriptor = GpuTextureViewDescriptor::new();
+                view_descriptor.dimension(GpuTextureViewDimension::N2d);
+                view_descriptor.base_array_layer(layer as u32);
+                view_descriptor.array_layer_count(1);
+                texture.create_view_with_descriptor(&view_descriptor)
+            })
+            .collect();
+        // bilinear comparisons soften each tap of the filter
+        let mut sampler_descriptor = GpuSamplerDescriptor::new();
+        sampler_descriptor.mag_filter(GpuFilterMode::Linear);
+        sampler_descriptor.min_filter(GpuFilterMode::Linear);
+        sampler_descriptor.compare(GpuCompareFunction::Less);
+        let sampler = device.device().create_sampler_with_descriptor(&sampler_descriptor);
+        let this = Self {
+            view,
+            layer_views,
+            sampler,
+            resolution,
+        };
+        Arc::new(this)
+    }
+
+    // splits the part of the view frustum overlapping the scene bounds with the practical split
+    // scheme and fits an orthographic projection around the bounding sphere of each slice. the
+    // light travels along the direction. the slices only depend on the camera distance to the
+    // scene and the spheres on the slice depths, so their size holds while the view turns. the
+    // projections are snapped to whole texels so the shadow edges hold still while the camera moves.
+    pub fn cascades(&self,
+        direction: &glm::Vec3,
+        view_matrix: &glm::Mat4,
+        projection_matrix: &glm::Mat4,
+        scene_bounds: (glm::Vec3, glm::Vec3),
+        settings: &ShadowSettings) -> Vec<Cascade> {
+        Self::fit_cascades(self.resolution, direction, view_matrix, projection_matrix, scene_bounds, settings)
+    }
+
+    fn fit_cascades(
+        resolution: u32,
+        direction: &glm::Vec3,
+        view_matrix: &glm::Mat4,
+        projection_matrix: &glm::Mat4,
+        scene_bounds: (glm::Vec3, glm::Vec3),
+        settings: &ShadowSettings) -> Vec<Cascade> {
+        let Some(inverse_projection) = projection_matrix.try_inverse() else { return vec![] };
+        let Some(inverse_view) = view_matrix.try_inverse() else { return vec![] };
+        // in view space
+        let unproject = |x: f32, y: f32, z: f32| {
+            let p = inverse_projection * glm::vec4(x, y, z, 1.0);
+            p.xyz() / p.w
+        };
+        // frustum edges through the near plane and a point further in, which works for
+        // perspective, infinite and orthographic projections alike
+        let edges: Vec<(glm::Vec3, glm::Vec3)> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].into_iter()
+            .map(|(x, y)| (unproject(x, y, 0.0), unproject(x, y, 0.5)))
+            .collect();
+        let edge_near_depth = -edges[0].0.z;
+        let edge_far_depth = -edges[0].1.z;
+        if (edge_far_depth - edge_near_depth).abs() < 1e-6 {
+            return vec![]
+        }
+        let edge_point = |edge: &(glm::Vec3, glm::Vec3), depth: f32| {
+            edge.0 + (edge.1 - edge.0) * ((depth - edge_near_depth) / (edge_far_depth - edge_near_depth))
+        };
+        // depth range of the bounding sphere of the scene
+        let (scene_min, scene_max) = scene_bounds;
+        let scene_center = (scene_min + scene_max) * 0.5;
+        let scene_radius = glm::distance(&scene_max, &scene_min) * 0.5;
+        let camera_position = (inverse_view * glm::vec4(0.0, 0.0, 0.0, 1.0)).xyz();
+        let scene_distance = glm::distance(&camera_position, &scene_center);
+        let near = (scene_distance - scene_radius).max(edge_near_depth).max(1e-3);
+        let far = (scene_distance + scene_radius).min(settings.max_distance);
+        if far <= near {
+            return vec![]
+        }
+        let direction = glm::normalize(direction);
+        let up = if direction.y.abs() > 0.99 { glm::vec3(0.0, 0.0, 1.0) } else { glm::vec3(0.0, 1.0, 0.0) };
+        let cascade_count = settings.cascade_count.clamp(1, MAX_CASCADE_COUNT);
+        let resolution = resolution as f32;
+        let mut slice_near = near;
+        (1..=cascade_count)
+            .map(|cascade_index| {
+                let ratio = cascade_index as f32 / cascade_count as f32;
+                let logarithmic = near * (far / near).powf(ratio);
+                let uniform = near + (far - near) * ratio;
+                let slice_far = settings.split_lambda * logarithmic + (1.0 - settings.split_lambda) * uniform;
+                let corners: Vec<glm::Vec3> = edges.iter()
+                    .flat_map(|edge| [edge_point(edge, slice_near), edge_point(edge, slice_far)])
+                    .collect();
+                let near_depth = slice_near;
+                slice_near = slice_far;
+                // the sphere is fitted in view space, so the radius is the same for every view direction
+                let view_center = corners.iter().fold(glm::Vec3::zeros(), |sum, v| sum + v) / corners.len() as f32;
+                let radius = corners.iter().map(|v| glm::distance(v, &view_center)).fold(0.0, f32::max);
+                let center = (inverse_view * glm::vec4(view_center.x, view_center.y, view_center.z, 1.0)).xyz();
+                // casters behind a slice still land in its depth range
+                let back = glm::distance(&center, &scene_center) + scene_radius;
+                let eye = center - direction * (radius + back);
+                let light_view_matrix = glm::look_at(&eye, &center, &up);
+                let mut projection_matrix = glm::ortho_rh_zo(-radius, radius, -radius, radius, 0.0, radius * 2.0 + back);
+                // snaps the world origin to a texel
+                let origin = projection_matrix * light_view_matrix * glm::vec4(0.0, 0.0, 0.0, 1.0);
+                let origin = origin.xy() * (resolution * 0.5);
+                let offset = (origin.map(f32::round) - origin) * (2.0 / resolution);
+                projection_matrix[(0, 3)] += offset.x;
+                projection_matrix[(1, 3)] += offset.y;
+                Cascade {
+                    projection_view: projection_matrix * light_view_matrix,
+                    near_depth,
+                    split_depth: slice_far,
+                    texel_size: radius * 2.0 / resolution,
+                }
+            })
+            .collect()
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    // every cascade for sampling
+    pub fn view(&self) -> &GpuTextureView {
+        &self.view
+    }
+
+    // a single cascade for rendering
+    pub fn layer_view(&self, cascade_index: usize) -> &GpuTextureView {
+        &self.layer_views[cascade_index]
+    }
+
+    pub fn sampler(&self) -> &GpuSampler {
+        &self.sampler
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texel_sizes_hold_while_the_view_orbits() {
+        let settings = ShadowSettings::default();
+        let projection_matrix = glm::perspective(16.0 / 9.0, 1.2, 0.01, 100.0);
+        let scene_bounds = (glm::vec3(-1.0, -0.5, -1.0), glm::vec3(1.0, 0.5, 1.0));
+        let direction = glm::vec3(-0.3, -1.0, -0.2);
+        // the camera keeps its distance to the scene center while it orbits and looks around
+        let texel_sizes: Vec<Vec<f32>> = (0..16)
+            .map(|step| {
+                let angle = step as f32 * 0.4;
+                let eye = glm::normalize(&glm::vec3(angle.sin(), 0.3 + angle.cos() * 0.2, angle.cos())) * 3.0;
+                let target = glm::vec3(0.3 * angle.cos(), 0.2 * angle.sin(), 0.0);
+                let view_matrix = glm::look_at(&eye, &target, &glm::vec3(0.0, 1.0, 0.0));
+                ShadowMap::fit_cascades(2048, &direction, &view_matrix, &projection_matrix, scene_bounds, &settings)
+                    .iter()
+                    .map(|v| v.texel_size)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(texel_sizes[0].len(), settings.cascade_count);
+        for sizes in &texel_sizes {
+            for (size, first) in sizes.iter().zip(&texel_sizes[0]) {
+                assert!((size - first).abs() <= first * 1e-4, "{} != {}", size, first);
+            }
+        }
+    }
+}
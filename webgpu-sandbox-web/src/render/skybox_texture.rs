@@ -0,0 +1,88 @@
+
+use std::sync::Arc;
+
+use crate::skybox::Cubemap;
+use crate::render::device::Device;
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuTextureView,
+    GpuTextureFormat,
+    GpuTextureDescriptor,
+    GpuTextureViewDescriptor,
+    GpuTextureViewDimension,
+    gpu_texture_usage,
+    GpuSampler,
+    GpuSamplerDescriptor,
+    GpuFilterMode,
+    GpuImageCopyTexture,
+    GpuImageDataLayout,
+};
+
+// six sRGB faces of a cubemap skybox, a black 1x1 cube without one
+pub struct SkyboxTexture {
+    view: GpuTextureView,
+    sampler: GpuSampler,
+    available: bool,
+}
+
+impl SkyboxTexture {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>, cubemap: Option<&Cubemap>) -> Arc<Self> {
+        let (size, faces, available) = match cubemap {
+            Some(cubemap) => (cubemap.size(), cubemap.faces().clone(), true),
+            None => (1, vec![vec![0, 0, 0, 255]; 6], false),
+        };
+        let extent = Self::extent(size, size, 6);
+        let texture_descriptor = GpuTextureDescriptor::new(
+            GpuTextureFormat::Rgba8unormSrgb,
+            &extent,
+            gpu_texture_usage::TEXTURE_BINDING | gpu_texture_usage::COPY_DST);
+        let texture = device.device().create_texture(&texture_descriptor);
+        // transfer
+        let queue = device.device().queue();
+        for (face_index, pixels) in faces.iter().enumerate() {
+            let mut destination = GpuImageCopyTexture::new(&texture);
+            let origin: Vec<JsValue> = vec![0.0, 0.0, face_index as f64].into_iter().map(Into::into).collect();
+            let origin = origin.into_iter().collect::<js_sys::Array>();
+            destination.origin(&origin);
+            let mut data_layout = GpuImageDataLayout::new();
+            data_layout.bytes_per_row(size * 4);
+            data_layout.rows_per_image(size);
+            let face_extent = Self::extent(size, size, 1);
+            queue.write_texture_with_u8_array_and_u32_sequence(&destination, pixels, &data_layout, &face_extent);
+        }
+        let mut view_descriptor = GpuTextureViewDescriptor::new();
+        view_descriptor.dimension(GpuTextureViewDimension::Cube);
+        let view = texture.create_view_with_descriptor(&view_descriptor);
+        let mut sampler_descriptor = GpuSamplerDescriptor::new();
+        sampler_descriptor.mag_filter(GpuFilterMode::Linear);
+        sampler_descriptor.min_filter(GpuFilterMode::Linear);
+        let sampler = device.device().create_sampler_with_descriptor(&sampler_descriptor);
+        let this = Self {
+            view,
+            sampler,
+            available,
+        };
+        Arc::new(this)
+    }
+
+    fn extent(width: u32, height: u32, layers: u32) -> js_sys::Array {
+        let extent: Vec<JsValue> = vec![width as f64, height as f64, layers as f64].into_iter().map(Into::into).collect();
+        extent.into_iter().collect::<js_sys::Array>()
+    }
+
+    // false for the black placeholder
+    pub fn available(&self) -> bool {
+        self.available
+    }
+
+    pub fn view(&self) -> &GpuTextureView {
+        &self.view
+    }
+
+    pub fn sampler(&self) -> &GpuSampler {
+        &self.sampler
+    }
+}
@@ -60,8 +60,16 @@ pub struct Surface {
     canvas: web_sys::HtmlCanvasElement,
     canvas_context: GpuCanvasContext,
     configuration: Mutex<Option<SurfaceConfiguration>>,
+    // set by the observers until the next frame reconfigures the surface
+    resized: Mutex<bool>,
+    resize_observer: Mutex<Option<ResizeListener>>,
+    click_listener: Mutex<Option<ClickListener>>,
 }
 
+// the closures stay alive as long as the browser may call them
+type ResizeListener = (web_sys::ResizeObserver, Closure<dyn FnMut()>);
+type ClickListener = Closure<dyn FnMut(web_sys::MouseEvent)>;
+
 impl Surface {
     pub async fn acquire() -> Result<Arc<Self>, JsValue> {
         let window = global::window();
@@ -81,6 +89,9 @@ impl Surface {
             canvas,
             canvas_context,
             configuration: Mutex::new(None),
+            resized: Mutex::new(false),
+            resize_observer: Mutex::new(None),
+            click_listener: Mutex::new(None),
         };
         Arc::new(this)
     }
@@ -93,8 +104,8 @@ impl Surface {
         let window = &self.window;
         let canvas = &self.canvas;
         let device_pixel_ratio = window.device_pixel_ratio();
-        let width = (canvas.client_width() as f64 * device_pixel_ratio) as usize;
-        let height = (canvas.client_height() as f64 * device_pixel_ratio) as usize;
+        let width = ((canvas.client_width() as f64 * device_pixel_ratio).round() as usize).max(1);
+        let height = ((canvas.client_height() as f64 * device_pixel_ratio).round() as usize).max(1);
         // configure canvas size
         canvas.set_width(width as u32);
         canvas.set_height(height as u32);
@@ -113,6 +124,65 @@ impl Surface {
         });
     }
 
+    // watches the canvas size with a ResizeObserver and the device pixel ratio with a media
+    // query, which moving the window to another display or zooming changes
+    pub fn observe_resize(self: &Arc<Self>) {
+        let this_weak = Arc::downgrade(self);
+        let closure = Box::new(move || {
+            let Some(this) = this_weak.upgrade() else { return };
+            this.invalidate_size();
+        });
+        let closure = Closure::wrap(closure as Box<dyn FnMut()>);
+        let Ok(resize_observer) = web_sys::ResizeObserver::new(closure.as_ref().unchecked_ref()) else { return };
+        resize_observer.observe(&self.canvas);
+        if let Ok(mut mutex) = self.resize_observer.lock() {
+            *mutex = Some((resize_observer, closure));
+        }
+        self.observe_device_pixel_ratio();
+    }
+
+    // the query only matches the current ratio, so it is registered again after each change
+    fn observe_device_pixel_ratio(self: &Arc<Self>) {
+        let query = format!("(resolution: {}dppx)", self.window.device_pixel_ratio());
+        let Ok(Some(media_query_list)) = self.window.match_media(&query) else { return };
+        let this_weak = Arc::downgrade(self);
+        let callback = Closure::once_into_js(move || {
+            let Some(this) = this_weak.upgrade() else { return };
+            this.invalidate_size();
+            this.observe_device_pixel_ratio();
+        });
+        let mut options = web_sys::AddEventListenerOptions::new();
+        options.once(true);
+        _ = media_query_list.add_event_listener_with_callback_and_add_event_listener_options(
+            "change", callback.unchecked_ref(), &options);
+    }
+
+    // passes clicks on the canvas to the handler in texture coordinates
+    pub fn observe_click(&self, handler: impl Fn(glm::Vec2) + 'static) {
+        let canvas = self.canvas.clone();
+        let closure = Box::new(move |event: web_sys::MouseEvent| {
+            let width = canvas.client_width().max(1) as f32;
+            let height = canvas.client_height().max(1) as f32;
+            handler(glm::vec2(event.offset_x() as f32 / width, event.offset_y() as f32 / height));
+        });
+        let closure = Closure::wrap(closure as Box<dyn FnMut(web_sys::MouseEvent)>);
+        _ = self.canvas.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref());
+        if let Ok(mut mutex) = self.click_listener.lock() {
+            *mutex = Some(closure);
+        }
+    }
+
+    fn invalidate_size(&self) {
+        let Ok(mut resized) = self.resized.lock() else { return };
+        *resized = true;
+    }
+
+    // true once after the canvas size or the pixel ratio changed
+    pub fn take_resized(&self) -> bool {
+        let Ok(mut resized) = self.resized.lock() else { return false };
+        std::mem::replace(&mut *resized, false)
+    }
+
     pub fn configuration(&self) -> SurfaceConfiguration {
         let configuration = self.configuration.lock().unwrap();
         configuration.unwrap()
@@ -131,12 +201,6 @@ impl SurfaceConfiguration {
         self.presentation_format
     }
 
-    pub fn presentation_size(&self) -> js_sys::Array {
-        let presentation_size: Vec<JsValue> = vec![self.width as f64, self.height as f64].into_iter().map(Into::into).collect();
-        let presentation_size = presentation_size.into_iter().collect::<js_sys::Array>();
-        presentation_size
-    }
-
     pub fn width(&self) -> usize {
         self.width
     }
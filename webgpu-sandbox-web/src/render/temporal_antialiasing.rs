@@ -0,0 +1,198 @@
+use std::sync::{Arc, Mutex};
+
+use crate::render::{
+    Device,
+    Shaders,
+    PipelineLayouts,
+    RenderGraph,
+    PassDescription,
+    ColorAttachment,
+    TextureHandle,
+    HDR_FORMAT,
+};
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuRenderPipelineDescriptor,
+    GpuVertexState,
+    GpuFragmentState,
+    GpuColorTargetState,
+    GpuPrimitiveState,
+    GpuPrimitiveTopology,
+    GpuBufferDescriptor,
+    gpu_buffer_usage,
+    GpuBindGroupDescriptor,
+    GpuBindGroupEntry,
+    GpuBufferBinding,
+    GpuRenderPipeline,
+    GpuBuffer,
+    GpuTextureDescriptor,
+    gpu_texture_usage,
+    GpuTexture,
+    GpuTextureView,
+    GpuSampler,
+    GpuSamplerDescriptor,
+    GpuFilterMode,
+};
+
+// jittered frames blended into a history reprojected along the velocity
+pub struct TemporalAntialiasingPass {
+    device: Arc<Device>,
+    render_pipeline: GpuRenderPipeline,
+    uniform_buffer: GpuBuffer,
+    sampler: GpuSampler,
+    history: Mutex<Option<TemporalHistory>>,
+}
+
+impl TemporalAntialiasingPass {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>) -> Arc<Self> {
+        let shader_module = Shaders::temporal_antialiasing(device);
+        let layout = PipelineLayouts::temporal_antialiasing(device);
+        // fullscreen triangle writing the frame and the next history
+        let vertex_state = GpuVertexState::new("vert_main", shader_module.shader_module());
+        let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
+        let fragment_targets: Vec<JsValue> = vec![
+            GpuColorTargetState::new(HDR_FORMAT).into(),
+            GpuColorTargetState::new(HDR_FORMAT).into(),
+        ];
+        let fragment_targets = fragment_targets.into_iter().collect::<js_sys::Array>();
+        let fragment_state = GpuFragmentState::new("frag_main", shader_module.shader_module(), &fragment_targets);
+        render_descriptor.fragment(&fragment_state);
+        let mut primitive_state = GpuPrimitiveState::new();
+        primitive_state.topology(GpuPrimitiveTopology::TriangleList);
+        render_descriptor.primitive(&primitive_state);
+        let render_pipeline = device.device().create_render_pipeline(&render_descriptor);
+
+        // uniform: parameters
+        let uniform_buffer_descriptor = GpuBufferDescriptor::new(
+            (std::mem::size_of::<f32>() * 4) as f64,
+            gpu_buffer_usage::UNIFORM | gpu_buffer_usage::COPY_DST);
+        let uniform_buffer = device.device().create_buffer(&uniform_buffer_descriptor);
+        // the reprojected history falls between texels
+        let mut sampler_descriptor = GpuSamplerDescriptor::new();
+        sampler_descriptor.mag_filter(GpuFilterMode::Linear);
+        sampler_descriptor.min_filter(GpuFilterMode::Linear);
+        let sampler = device.device().create_sampler_with_descriptor(&sampler_descriptor);
+        let this = Self {
+            device: Arc::clone(device),
+            render_pipeline,
+            uniform_buffer,
+            sampler,
+            history: Mutex::new(None),
+        };
+        Arc::new(this)
+    }
+
+    // writes the antialiased source into the target, which the history follows in size. blend
+    // is the weight of the current frame.
+    pub fn add_pass(&self,
+        graph: &mut RenderGraph,
+        source: TextureHandle,
+        velocity: TextureHandle,
+        target: TextureHandle,
+        blend: f32) {
+        let Ok(mut history) = self.history.lock() else { return };
+        let Some(description) = graph.texture_description(target) else { return };
+        let resized = history.as_ref()
+            .is_none_or(|v| (v.width, v.height) != (description.width, description.height));
+        if resized {
+            if let Some(history) = history.take() {
+                history.destroy();
+            }
+            *history = Some(TemporalHistory::new(&self.device, description.width, description.height));
+        }
+        let Some(history) = history.as_mut() else { return };
+        let previous = graph.import_texture("history", &history.views[history.index]);
+        let next = graph.import_texture("history", &history.views[1 - history.index]);
+        let pass = PassDescription::new("temporal antialiasing")
+            .color(ColorAttachment::new(target))
+            .color(ColorAttachment::new(next))
+            .read(source)
+            .read(previous)
+            .read(velocity);
+        let device = Arc::clone(&self.device);
+        let render_pipeline = self.render_pipeline.clone();
+        let uniform_buffer = self.uniform_buffer.clone();
+        let sampler = self.sampler.clone();
+        graph.add_pass(pass, move |render_pass_encoder, textures| {
+            let (Some(source_view), Some(previous_view), Some(velocity_view)) =
+                (textures.view(source), textures.view(previous), textures.view(velocity)) else { return };
+            let buffer_binding: JsValue = GpuBufferBinding::new(&uniform_buffer).into();
+            let bind_entries: Vec<JsValue> = vec![
+                GpuBindGroupEntry::new(0, &buffer_binding).into(),
+                GpuBindGroupEntry::new(1, &sampler).into(),
+                GpuBindGroupEntry::new(2, source_view).into(),
+                GpuBindGroupEntry::new(3, previous_view).into(),
+                GpuBindGroupEntry::new(4, velocity_view).into(),
+            ];
+            let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+            let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &render_pipeline.get_bind_group_layout(0));
+            let bind_group = device.device().create_bind_group(&bind_group_descriptor);
+            render_pass_encoder.set_pipeline(&render_pipeline);
+            render_pass_encoder.set_bind_group(0, Some(&bind_group));
+            render_pass_encoder.draw(3);
+        });
+
+        // write
+        let parameters = [
+            blend.clamp(0.0, 1.0),
+            if history.available { 1.0 } else { 0.0 },
+            0.0,
+            0.0,
+        ];
+        let uniform_data = js_sys::Float32Array::new_with_length(4);
+        uniform_data.copy_from(&parameters);
+        self.device.device().queue().write_buffer_with_u32_and_buffer_source(&self.uniform_buffer, 0, &uniform_data);
+        history.index = 1 - history.index;
+        history.available = true;
+    }
+
+    // releases the history, which starts over once the pass is added again
+    pub fn reset(&self) {
+        let Ok(mut history) = self.history.lock() else { return };
+        if let Some(history) = history.take() {
+            history.destroy();
+        }
+    }
+}
+
+// accumulated frames read and written in turns, outliving the frame graph
+struct TemporalHistory {
+    width: u32,
+    height: u32,
+    textures: Vec<GpuTexture>,
+    views: Vec<GpuTextureView>,
+    // the history of the previous frame
+    index: usize,
+    available: bool,
+}
+
+impl TemporalHistory {
+    fn new(device: &Arc<Device>, width: u32, height: u32) -> Self {
+        let extent: Vec<JsValue> = vec![width as f64, height as f64].into_iter().map(Into::into).collect();
+        let extent = extent.into_iter().collect::<js_sys::Array>();
+        let textures: Vec<GpuTexture> = (0..2)
+            .map(|_| {
+                let mut texture_descriptor = GpuTextureDescriptor::new(HDR_FORMAT, &extent,
+                    gpu_texture_usage::RENDER_ATTACHMENT | gpu_texture_usage::TEXTURE_BINDING);
+                texture_descriptor.label("history");
+                device.device().create_texture(&texture_descriptor)
+            })
+            .collect();
+        let views = textures.iter().map(|v| v.create_view()).collect();
+        Self {
+            width,
+            height,
+            textures,
+            views,
+            index: 0,
+            available: false,
+        }
+    }
+
+    fn destroy(&self) {
+        self.textures.iter().for_each(GpuTexture::destroy);
+    }
+}
@@ -0,0 +1,228 @@
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::asset::{self, Image, Filter, WrapMode};
+use crate::render::device::Device;
+
+use wasm_bindgen::prelude::*;
+
+use web_sys::{
+    GpuTextureView,
+    GpuTextureFormat,
+    GpuTextureDescriptor,
+    gpu_texture_usage,
+    GpuSampler,
+    GpuSamplerDescriptor,
+    GpuAddressMode,
+    GpuFilterMode,
+    GpuMipmapFilterMode,
+    GpuImageCopyTexture,
+    GpuImageDataLayout,
+};
+
+pub struct Texture {
+    view: GpuTextureView,
+    sampler: GpuSampler,
+}
+
+impl Texture {
+    // color textures (base color, emissive) are stored in sRGB, the others are linear
+    pub fn with_image(device: &Arc<Device>, image: &Image, sampler: &asset::Sampler, srgb: bool) -> Arc<Self> {
+        let format = if srgb {
+            GpuTextureFormat::Rgba8unormSrgb
+        } else {
+            GpuTextureFormat::Rgba8unorm
+        };
+        let mip_levels = MipLevel::chain(image.width(), image.height(), image.pixels(), srgb);
+        Self::new(device, format, mip_levels, sampler)
+    }
+
+    // 1x1 placeholder for material slots without a texture
+    pub fn with_color(device: &Arc<Device>, color: [u8; 4]) -> Arc<Self> {
+        let mip_level = MipLevel {
+            width: 1,
+            height: 1,
+            pixels: color.to_vec(),
+        };
+        Self::new(device, GpuTextureFormat::Rgba8unorm, vec![mip_level], &asset::Sampler::default())
+    }
+
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn new(device: &Arc<Device>, format: GpuTextureFormat, mip_levels: Vec<MipLevel>, sampler: &asset::Sampler) -> Arc<Self> {
+        let base_level = &mip_levels[0];
+        let size = Self::extent(base_level.width, base_level.height);
+        let mut texture_descriptor = GpuTextureDescriptor::new(
+            format, &size, gpu_texture_usage::TEXTURE_BINDING | gpu_texture_usage::COPY_DST);
+        texture_descriptor.mip_level_count(mip_levels.len() as u32);
+        let texture = device.device().create_texture(&texture_descriptor);
+        // transfer
+        let queue = device.device().queue();
+        for (mip_level_index, mip_level) in mip_levels.iter().enumerate() {
+            let mut destination = GpuImageCopyTexture::new(&texture);
+            destination.mip_level(mip_level_index as u32);
+            let mut data_layout = GpuImageDataLayout::new();
+            data_layout.bytes_per_row(mip_level.width * 4);
+            data_layout.rows_per_image(mip_level.height);
+            let size = Self::extent(mip_level.width, mip_level.height);
+            queue.write_texture_with_u8_array_and_u32_sequence(&destination, &mip_level.pixels, &data_layout, &size);
+        }
+        let view = texture.create_view();
+        let sampler = Self::create_sampler(device, sampler);
+        let this = Self {
+            view,
+            sampler,
+        };
+        Arc::new(this)
+    }
+
+    fn create_sampler(device: &Arc<Device>, sampler: &asset::Sampler) -> GpuSampler {
+        let filter_mode = |v: Filter| match v {
+            Filter::Nearest => GpuFilterMode::Nearest,
+            Filter::Linear => GpuFilterMode::Linear,
+        };
+        let mipmap_filter_mode = |v: Filter| match v {
+            Filter::Nearest => GpuMipmapFilterMode::Nearest,
+            Filter::Linear => GpuMipmapFilterMode::Linear,
+        };
+        let address_mode = |v: WrapMode| match v {
+            WrapMode::ClampToEdge => GpuAddressMode::ClampToEdge,
+            WrapMode::MirroredRepeat => GpuAddressMode::MirrorRepeat,
+            WrapMode::Repeat => GpuAddressMode::Repeat,
+        };
+        let mut sampler_descriptor = GpuSamplerDescriptor::new();
+        sampler_descriptor.mag_filter(filter_mode(sampler.mag_filter()));
+        sampler_descriptor.min_filter(filter_mode(sampler.min_filter()));
+        sampler_descriptor.mipmap_filter(mipmap_filter_mode(sampler.mipmap_filter()));
+        sampler_descriptor.address_mode_u(address_mode(sampler.wrap_s()));
+        sampler_descriptor.address_mode_v(address_mode(sampler.wrap_t()));
+        device.device().create_sampler_with_descriptor(&sampler_descriptor)
+    }
+
+    fn extent(width: u32, height: u32) -> js_sys::Array {
+        let extent: Vec<JsValue> = vec![width as f64, height as f64].into_iter().map(Into::into).collect();
+        extent.into_iter().collect::<js_sys::Array>()
+    }
+
+    pub fn view(&self) -> &GpuTextureView {
+        &self.view
+    }
+
+    pub fn sampler(&self) -> &GpuSampler {
+        &self.sampler
+    }
+}
+
+struct MipLevel {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl MipLevel {
+    // box-filtered mip chain down to 1x1, averaging sRGB colors in linear space
+    fn chain(width: u32, height: u32, pixels: &[u8], srgb: bool) -> Vec<Self> {
+        let base_level = Self {
+            width,
+            height,
+            pixels: pixels.to_vec(),
+        };
+        let mut mip_levels = vec![base_level];
+        loop {
+            let previous = mip_levels.last().unwrap();
+            if previous.width == 1 && previous.height == 1 {
+                break;
+            }
+            let next = previous.downsample(srgb);
+            mip_levels.push(next);
+        }
+        mip_levels
+    }
+
+    fn downsample(&self, srgb: bool) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (x * 2).min(self.width - 1);
+                let x1 = (x * 2 + 1).min(self.width - 1);
+                let y0 = (y * 2).min(self.height - 1);
+                let y1 = (y * 2 + 1).min(self.height - 1);
+                for channel in 0..4 {
+                    let decode = srgb && channel < 3;
+                    let sum: f32 = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].iter()
+                        .map(|&(x, y)| self.pixels[((y * self.width + x) * 4 + channel) as usize])
+                        .map(|v| Self::decode(v, decode))
+                        .sum();
+                    pixels.push(Self::encode(sum * 0.25, decode));
+                }
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn decode(value: u8, srgb: bool) -> f32 {
+        let value = value as f32 / 255.0;
+        if !srgb {
+            return value
+        }
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    fn encode(value: f32, srgb: bool) -> u8 {
+        let value = if !srgb {
+            value
+        } else if value <= 0.0031308 {
+            value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        };
+        (value.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+}
+
+// shares GPU textures among materials referencing the same glTF texture
+pub struct TextureCache {
+    device: Arc<Device>,
+    textures: Mutex<HashMap<(usize, bool), Arc<Texture>>>,
+    white: Arc<Texture>,
+    flat_normal: Arc<Texture>,
+}
+
+impl TextureCache {
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(device: &Arc<Device>) -> Arc<Self> {
+        let this = Self {
+            device: Arc::clone(device),
+            textures: Mutex::new(HashMap::new()),
+            white: Texture::with_color(device, [255, 255, 255, 255]),
+            flat_normal: Texture::with_color(device, [128, 128, 255, 255]),
+        };
+        Arc::new(this)
+    }
+
+    pub fn texture(&self, texture: &asset::Texture, srgb: bool) -> Arc<Texture> {
+        let Ok(mut textures) = self.textures.lock() else { return Arc::clone(&self.white) };
+        let key = (texture.texture_index(), srgb);
+        let texture = textures.entry(key)
+            .or_insert_with(|| Texture::with_image(&self.device, texture.image(), texture.sampler(), srgb));
+        Arc::clone(texture)
+    }
+
+    pub fn white(&self) -> &Arc<Texture> {
+        &self.white
+    }
+
+    pub fn flat_normal(&self) -> &Arc<Texture> {
+        &self.flat_normal
+    }
+}
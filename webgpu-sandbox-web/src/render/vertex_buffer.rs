@@ -1,6 +1,7 @@
 
 use std::sync::Arc;
 
+use crate::asset::Mesh;
 use crate::render::device::Device;
 
 use web_sys::{
@@ -46,23 +47,24 @@ impl VertexBuffer {
 
 pub struct MeshBuffer {
     device: Arc<Device>,
-    index_count: usize,
+    mesh: Arc<Mesh>,
     position_buffer: GpuBuffer,
     index_buffer: GpuBuffer,
     normal_buffer: GpuBuffer,
+    tex_coord_0_buffer: GpuBuffer,
+    tex_coord_1_buffer: GpuBuffer,
+    tangent_buffer: GpuBuffer,
+    color_buffer: GpuBuffer,
+    joint_buffer: GpuBuffer,
+    weight_buffer: GpuBuffer,
+    morph_target_buffer: GpuBuffer,
 }
 
 impl MeshBuffer {
-    pub fn new(device: &Arc<Device>, positions: &Vec<f32>, indices: &Vec<u32>, normals: &Vec<f32>) -> Arc<Self> {
+    pub fn new(device: &Arc<Device>, mesh: &Arc<Mesh>) -> Arc<Self> {
+        let indices = mesh.indices();
         // positions
-        let mut position_buffer_descriptor = GpuBufferDescriptor::new(
-            (std::mem::size_of::<f32>() * positions.len()) as f64,
-            gpu_buffer_usage::VERTEX);
-        position_buffer_descriptor.mapped_at_creation(true);
-        let position_buffer = device.device().create_buffer(&position_buffer_descriptor);
-        let position_array = js_sys::Float32Array::new(&position_buffer.get_mapped_range());
-        position_array.copy_from(&positions);
-        position_buffer.unmap();
+        let position_buffer = Self::create_vertex_buffer(device, mesh.positions());
         // index
         let mut index_buffer_descriptor = GpuBufferDescriptor::new(
             (std::mem::size_of::<u32>() * indices.len()) as f64,
@@ -73,27 +75,82 @@ impl MeshBuffer {
         index_array.copy_from(&indices);
         index_buffer.unmap();
         // normal
-        let mut normal_buffer_descriptor = GpuBufferDescriptor::new(
-            (std::mem::size_of::<f32>() * normals.len()) as f64,
-            gpu_buffer_usage::VERTEX);
-        normal_buffer_descriptor.mapped_at_creation(true);
-        let normal_buffer = device.device().create_buffer(&normal_buffer_descriptor);
-        let normal_array = js_sys::Float32Array::new(&normal_buffer.get_mapped_range());
-        normal_array.copy_from(&normals);
-        normal_buffer.unmap();
+        let normal_buffer = Self::create_vertex_buffer(device, mesh.normals());
+        // texture coordinates
+        let tex_coord_0_buffer = Self::create_vertex_buffer(device, mesh.tex_coords_0());
+        let tex_coord_1_buffer = Self::create_vertex_buffer(device, mesh.tex_coords_1());
+        // tangent
+        let tangent_buffer = Self::create_vertex_buffer(device, mesh.tangents());
+        // color
+        let color_buffer = Self::create_vertex_buffer(device, mesh.colors());
+        // skinning
+        let joint_buffer = Self::create_joint_buffer(device, mesh.joints());
+        let weight_buffer = Self::create_vertex_buffer(device, mesh.weights());
+        // morph targets
+        let morph_target_buffer = Self::create_morph_target_buffer(device, mesh);
         // this
         let this = Self {
-            index_count: indices.len(),
+            mesh: Arc::clone(mesh),
             position_buffer,
             index_buffer,
             normal_buffer,
+            tex_coord_0_buffer,
+            tex_coord_1_buffer,
+            tangent_buffer,
+            color_buffer,
+            joint_buffer,
+            weight_buffer,
+            morph_target_buffer,
             device: Arc::clone(device),
         };
         Arc::new(this)
     }
 
-    pub fn index_count(&self) -> usize {
-        self.index_count
+    fn create_vertex_buffer(device: &Arc<Device>, values: &[f32]) -> GpuBuffer {
+        let mut buffer_descriptor = GpuBufferDescriptor::new(
+            std::mem::size_of_val(values) as f64,
+            gpu_buffer_usage::VERTEX);
+        buffer_descriptor.mapped_at_creation(true);
+        let buffer = device.device().create_buffer(&buffer_descriptor);
+        let array = js_sys::Float32Array::new(&buffer.get_mapped_range());
+        array.copy_from(values);
+        buffer.unmap();
+        buffer
+    }
+
+    fn create_joint_buffer(device: &Arc<Device>, values: &[u32]) -> GpuBuffer {
+        let mut buffer_descriptor = GpuBufferDescriptor::new(
+            std::mem::size_of_val(values) as f64,
+            gpu_buffer_usage::VERTEX);
+        buffer_descriptor.mapped_at_creation(true);
+        let buffer = device.device().create_buffer(&buffer_descriptor);
+        let array = js_sys::Uint32Array::new(&buffer.get_mapped_range());
+        array.copy_from(values);
+        buffer.unmap();
+        buffer
+    }
+
+    // position and normal deltas as vec4 pairs for the storage buffer
+    fn create_morph_target_buffer(device: &Arc<Device>, mesh: &Mesh) -> GpuBuffer {
+        let values: Vec<f32> = mesh.morph_positions().chunks(3)
+            .zip(mesh.morph_normals().chunks(3))
+            .flat_map(|(position, normal)| [position[0], position[1], position[2], 0.0, normal[0], normal[1], normal[2], 0.0])
+            .collect();
+        // bindings require a non-empty buffer
+        let size = (std::mem::size_of::<f32>() * values.len()).max(32);
+        let mut buffer_descriptor = GpuBufferDescriptor::new(size as f64, gpu_buffer_usage::STORAGE);
+        buffer_descriptor.mapped_at_creation(true);
+        let buffer = device.device().create_buffer(&buffer_descriptor);
+        if !values.is_empty() {
+            let array = js_sys::Float32Array::new(&buffer.get_mapped_range());
+            array.copy_from(&values);
+        }
+        buffer.unmap();
+        buffer
+    }
+
+    pub fn mesh(&self) -> &Arc<Mesh> {
+        &self.mesh
     }
 
     pub fn position_buffer(&self) -> &GpuBuffer {
@@ -107,4 +164,32 @@ impl MeshBuffer {
     pub fn normal_buffer(&self) -> &GpuBuffer {
         &self.normal_buffer
     }
+
+    pub fn tex_coord_0_buffer(&self) -> &GpuBuffer {
+        &self.tex_coord_0_buffer
+    }
+
+    pub fn tex_coord_1_buffer(&self) -> &GpuBuffer {
+        &self.tex_coord_1_buffer
+    }
+
+    pub fn tangent_buffer(&self) -> &GpuBuffer {
+        &self.tangent_buffer
+    }
+
+    pub fn color_buffer(&self) -> &GpuBuffer {
+        &self.color_buffer
+    }
+
+    pub fn joint_buffer(&self) -> &GpuBuffer {
+        &self.joint_buffer
+    }
+
+    pub fn weight_buffer(&self) -> &GpuBuffer {
+        &self.weight_buffer
+    }
+
+    pub fn morph_target_buffer(&self) -> &GpuBuffer {
+        &self.morph_target_buffer
+    }
 }
@@ -2,7 +2,7 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
-use crate::asset::Mesh;
+use crate::asset::{Model, Mesh, AlphaMode, Topology, LightKind};
 use crate::console_log;
 use crate::render::{
     Device,
@@ -11,9 +11,32 @@ use crate::render::{
     Vertices,
     SurfaceConfiguration,
     MeshBuffer,
+    MaterialBuffer,
+    LightBuffer,
+    EnvironmentMap,
+    SkyboxTexture,
+    ShadowMap,
+    SHADOW_MAP_FORMAT,
+    MAX_CASCADE_COUNT,
+    TextureCache,
+    RenderGraph,
+    PassDescription,
+    ColorAttachment,
+    DepthAttachment,
+    TextureHandle,
+    TextureDescription,
+    TransientTextures,
+    AmbientOcclusionPasses,
+    TemporalAntialiasingPass,
+    DepthOfFieldPass,
+    PostProcessPasses,
+    HDR_FORMAT,
 };
 use crate::render::PipelineLayouts;
 use crate::scene::SceneContext;
+use crate::skybox::SkyboxMode;
+use crate::depth_of_field::Focus;
+use crate::antialiasing::AntialiasingSettings;
 
 use nalgebra_glm as glm;
 
@@ -21,20 +44,19 @@ use wasm_bindgen::prelude::*;
 
 use web_sys::{
     GpuTextureFormat,
+    GpuTextureView,
     GpuRenderPipelineDescriptor,
     GpuVertexState,
     GpuFragmentState,
     GpuColorTargetState,
     GpuPrimitiveState,
     GpuPrimitiveTopology,
-    GpuRenderPassDescriptor,
-    GpuRenderPassColorAttachment,
-    GpuLoadOp,
-    GpuStoreOp,
-    GpuColorDict,
+    GpuCullMode,
+    GpuBlendState,
+    GpuBlendComponent,
+    GpuBlendFactor,
+    GpuBlendOperation,
     GpuMultisampleState,
-    GpuTextureDescriptor,
-    gpu_texture_usage, 
     GpuBufferDescriptor,
     gpu_buffer_usage,
     GpuVertexBufferLayout,
@@ -45,24 +67,74 @@ use web_sys::{
     GpuBufferBinding,
     GpuDepthStencilState,
     GpuCompareFunction,
-    GpuRenderPassDepthStencilAttachment,
-    GpuTexture,
     GpuIndexFormat,
     GpuBindGroup,
+    GpuRenderPipeline,
+    GpuBuffer,
+    GpuRenderPassEncoder,
+    GpuBindGroupLayoutDescriptor,
 };
 
+// motion of each pixel since the previous frame in texture coordinates
+const VELOCITY_FORMAT: GpuTextureFormat = GpuTextureFormat::Rg16float;
+// attachments sharing the sample count of the stage
+const MULTISAMPLED_FORMATS: [GpuTextureFormat; 3] = [HDR_FORMAT, VELOCITY_FORMAT, GpuTextureFormat::Depth24plus];
+
 pub struct Renderer {
     render_frame: Box<dyn Fn() + 'static>,
 }
 
 impl Renderer {
     pub fn new(device: &Arc<Device>, surface: &Arc<Surface>, scene_context: &Arc<SceneContext>) -> Arc<Self> {
-        let stage = RenderStage::new(device, surface);
-        let skybox_render_pipeline = SkyboxRenderPipeline::new(device, surface, scene_context, &stage);
-        let final_render_pipeline = FinalRenderPipeline::new(device, surface, scene_context, &stage);
+        let stage = RenderStage::new(device, &scene_context.antialiasing_settings());
+        let environment_map = EnvironmentMap::new(device, scene_context.environment().map(AsRef::as_ref));
+        let skybox_texture = SkyboxTexture::new(device, scene_context.cubemap().map(AsRef::as_ref));
+        let skybox_render_pipeline = SkyboxRenderPipeline::new(device, surface, scene_context, &stage, &environment_map, &skybox_texture);
+        let final_render_pipeline = FinalRenderPipeline::new(device, surface, scene_context, &stage, &environment_map);
+        let temporal_antialiasing = TemporalAntialiasingPass::new(device);
+        let depth_of_field = DepthOfFieldPass::new(device);
+        let post_process = PostProcessPasses::new(device, surface.configuration().presentation_format());
+        let transient_textures = TransientTextures::new();
+        let device = Arc::clone(device);
+        let surface = Arc::clone(surface);
+        let scene_context = Arc::clone(scene_context);
         let render_frame = move || {
-            skybox_render_pipeline.render_frame();
-            final_render_pipeline.render_frame();
+            // the canvas changed size or moved to a display with another pixel ratio
+            if surface.take_resized() {
+                surface.configure(&device);
+            }
+            // the pipelines follow the sample count of the stage
+            let surface_configuration = surface.configuration();
+            let antialiasing_settings = scene_context.antialiasing_settings();
+            stage.set_antialiasing(&antialiasing_settings);
+            stage.begin_frame(&surface_configuration, &scene_context, antialiasing_settings.temporal);
+            // the passes of the frame record into one command encoder
+            let mut graph = RenderGraph::new();
+            let swapchain_view = surface.canvas_context().get_current_texture().create_view();
+            let swapchain = graph.import_texture("swapchain", &swapchain_view);
+            let targets = stage.targets(&mut graph, &surface_configuration, swapchain);
+            skybox_render_pipeline.render_frame(&mut graph, &targets);
+            final_render_pipeline.render_frame(&mut graph, &targets);
+            // the jittered frames accumulate into the antialiased target
+            if stage.temporal() {
+                temporal_antialiasing.add_pass(&mut graph, targets.hdr, targets.velocity, targets.antialiased,
+                    antialiasing_settings.temporal_blend);
+            } else {
+                temporal_antialiasing.reset();
+            }
+            // the lens blurs the scene before the bloom spreads its highlights
+            stage.pick_focus_point(&surface_configuration, &scene_context);
+            let depth_of_field_settings = scene_context.depth_of_field_settings();
+            let scene = if depth_of_field_settings.enabled {
+                let focus_distance = stage.focus_distance(&surface_configuration, &scene_context, depth_of_field_settings.focus);
+                let projection_matrix = stage.projection_matrix(&surface_configuration, &scene_context);
+                depth_of_field.add_pass(&mut graph, targets.antialiased, targets.prepass_depth, &projection_matrix,
+                    focus_distance, &depth_of_field_settings)
+            } else {
+                targets.antialiased
+            };
+            post_process.add_passes(&mut graph, scene, targets.swapchain, &scene_context.post_process_settings());
+            graph.execute(&device, &transient_textures);
         };
         let this = Self {
             render_frame: Box::new(render_frame),
@@ -76,17 +148,20 @@ impl Renderer {
     }
 }
 
+// records the passes of a pipeline into the frame graph
+type RenderFrame = Box<dyn Fn(&mut RenderGraph, &StageTargets) + 'static>;
+
 struct SkyboxRenderPipeline {
-    render_frame: Box<dyn Fn() + 'static>,
+    render_frame: RenderFrame,
 }
 
 impl SkyboxRenderPipeline {
     pub fn new(device: &Arc<Device>,
         surface: &Arc<Surface>,
         scene_context: &Arc<SceneContext>,
-        stage: &Arc<RenderStage>) -> Arc<Self> {
-        let surface_configuration = surface.configuration();
-        let presentation_format = surface_configuration.presentation_format();
+        stage: &Arc<RenderStage>,
+        environment_map: &Arc<EnvironmentMap>,
+        skybox_texture: &Arc<SkyboxTexture>) -> Arc<Self> {
         // skybox cube
         let vertex_buffer = Vertices::cube(device);
         let shader_module = Shaders::skybox(device);
@@ -108,8 +183,10 @@ impl SkyboxRenderPipeline {
         let layout = PipelineLayouts::skybox(device);
         let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
         // fragment
-        let target = GpuColorTargetState::new(presentation_format);
-        let fragment_targets: Vec<JsValue> = vec![target.into()];
+        let fragment_targets: Vec<JsValue> = vec![
+            GpuColorTargetState::new(HDR_FORMAT).into(),
+            GpuColorTargetState::new(VELOCITY_FORMAT).into(),
+        ];
         let fragment_targets = fragment_targets.into_iter().collect::<js_sys::Array>();
         let fragment_state = GpuFragmentState::new("frag_main", shader_module.shader_module(), &fragment_targets);
         render_descriptor.fragment(&fragment_state);
@@ -118,22 +195,28 @@ impl SkyboxRenderPipeline {
         primitive_state.topology(GpuPrimitiveTopology::TriangleList);
         render_descriptor.primitive(&primitive_state);
 
-        // multisample
-        let sample_count = stage.sample_count();
-        let mut multisample_state = GpuMultisampleState::new();
-        multisample_state.count(sample_count);
-        render_descriptor.multisample(&multisample_state);
-
         // depth stencil
         let mut depth_stencil_state = GpuDepthStencilState::new(GpuCompareFunction::Less, true, GpuTextureFormat::Depth24plus);
         render_descriptor.depth_stencil(&depth_stencil_state);
 
-        // render
-        let render_pipeline = device.device().create_render_pipeline(&render_descriptor);
+        // render, again whenever the sample count changes
+        let create_render_pipeline = {
+            let device = Arc::clone(device);
+            move |sample_count: u32| {
+                // multisample
+                let mut render_descriptor = render_descriptor.clone();
+                let mut multisample_state = GpuMultisampleState::new();
+                multisample_state.count(sample_count);
+                render_descriptor.multisample(&multisample_state);
+                device.device().create_render_pipeline(&render_descriptor)
+            }
+        };
+        let sample_count = stage.sample_count();
+        let render_pipeline = create_render_pipeline(sample_count);
 
-        // uniform
+        // uniform: projection view, parameters, sun, previous projection view and jitter
         let uniform_buffer_descriptor = GpuBufferDescriptor::new(
-            (std::mem::size_of::<f32>() * 16) as f64,
+            (std::mem::size_of::<f32>() * (16 + 4 + 4 + 16 + 4)) as f64,
             gpu_buffer_usage::UNIFORM | gpu_buffer_usage::COPY_DST);
         let uniform_buffer = device.device().create_buffer(&uniform_buffer_descriptor);
         // entries
@@ -141,61 +224,80 @@ impl SkyboxRenderPipeline {
         let buffer_binding = GpuBufferBinding::new(&uniform_buffer);
         let buffer_binding: JsValue = buffer_binding.into();
         let buffer_bind_entry = GpuBindGroupEntry::new(0, &buffer_binding);
-        let bind_entries: Vec<JsValue> = vec![buffer_bind_entry.into()];
+        let bind_entries: Vec<JsValue> = vec![
+            buffer_bind_entry.into(),
+            GpuBindGroupEntry::new(1, environment_map.environment_view()).into(),
+            GpuBindGroupEntry::new(2, environment_map.sampler()).into(),
+            GpuBindGroupEntry::new(3, skybox_texture.view()).into(),
+            GpuBindGroupEntry::new(4, skybox_texture.sampler()).into(),
+        ];
         let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
         let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &render_pipeline.get_bind_group_layout(0));
         let bind_group = device.device().create_bind_group(&bind_group_descriptor);
+        let render_pipeline = Mutex::new((sample_count, render_pipeline));
+        let environment_available = environment_map.available();
+        let cubemap_available = skybox_texture.available();
 
         // render
         let device = Arc::clone(device);
+        let surface = Arc::clone(surface);
         let scene_context = Arc::clone(scene_context);
         let stage = Arc::clone(stage);
-        let render_frame = move || {
-            // frame
-            let command_encoder = device.device().create_command_encoder();
+        let render_frame = move |graph: &mut RenderGraph, targets: &StageTargets| {
+            let surface_configuration = surface.configuration();
+            let Ok(mut render_pipeline) = render_pipeline.lock() else { return };
+            if render_pipeline.0 != stage.sample_count() {
+                let sample_count = stage.sample_count();
+                *render_pipeline = (sample_count, create_render_pipeline(sample_count));
+            }
+            let (_, render_pipeline) = &*render_pipeline;
             // render pass
-            let mut color_attachment = GpuRenderPassColorAttachment::new(
-                GpuLoadOp::Clear, GpuStoreOp::Store, &stage.color_texture().create_view());
-            let clear_color = GpuColorDict::new(1.0, 0.0, 0.0, 0.0);
-            let clear_color: JsValue = clear_color.into();
-            color_attachment.clear_value(&clear_color);
-            let color_attachments: Vec<JsValue> = vec![
-                color_attachment.into(),
-            ];
-            let color_attachments = color_attachments.into_iter().collect::<js_sys::Array>();
-            let mut render_pass_descriptor = GpuRenderPassDescriptor::new(&color_attachments);
-            // depth stencil
-            let mut depth_stencil_attachment = GpuRenderPassDepthStencilAttachment::new(&stage.depth_texture().create_view());
-            depth_stencil_attachment.depth_load_op(GpuLoadOp::Clear);
-            depth_stencil_attachment.depth_store_op(GpuStoreOp::Store);
-            depth_stencil_attachment.depth_clear_value(1.0);
-            render_pass_descriptor.depth_stencil_attachment(&depth_stencil_attachment);
-
-            // render pass encoder
-            let render_pass_encoder = command_encoder.begin_render_pass(&render_pass_descriptor);
-            render_pass_encoder.set_pipeline(&render_pipeline);
-            render_pass_encoder.set_vertex_buffer(0, Some(vertex_buffer.buffer()));
-            render_pass_encoder.set_bind_group(0, Some(&bind_group));
-            render_pass_encoder.draw(vertex_buffer.vertex_count() as u32);
-            render_pass_encoder.end();
-            
+            let pass = PassDescription::new("skybox")
+                .color(ColorAttachment::new(targets.color).clear([0.0, 0.0, 0.0, 1.0]))
+                .color(ColorAttachment::new(targets.velocity).clear([0.0, 0.0, 0.0, 0.0]))
+                .depth(DepthAttachment::new(targets.depth));
+            let render_pipeline = render_pipeline.clone();
+            let vertex_buffer = Arc::clone(&vertex_buffer);
+            let bind_group = bind_group.clone();
+            graph.add_pass(pass, move |render_pass_encoder, _| {
+                render_pass_encoder.set_pipeline(&render_pipeline);
+                render_pass_encoder.set_vertex_buffer(0, Some(vertex_buffer.buffer()));
+                render_pass_encoder.set_bind_group(0, Some(&bind_group));
+                render_pass_encoder.draw(vertex_buffer.vertex_count() as u32);
+            });
+
             // write
             let queue = device.device().queue();
             let sky_height = 3.0;
             let projection_view_matrix = stage.projection_view_matrix(&surface_configuration, &scene_context);
             let model_matrix = glm::scaling(&glm::vec3(sky_height * 2.0, sky_height * 2.0, sky_height * 2.0));
             let projection_view_model = projection_view_matrix * model_matrix;
-            let uniform_data = js_sys::Float32Array::new_with_length(16);
-            uniform_data.copy_from(projection_view_model.as_slice());
-            queue.write_buffer_with_u32_and_buffer_source(&uniform_buffer, 0, &uniform_data);
-
-            // submit
-            let command_buffer = command_encoder.finish();
-            let command_buffers: Vec<JsValue> = vec![
-                command_buffer.into(),
+            // modes without their source fall back to the gradient
+            let skybox_settings = scene_context.skybox_settings();
+            let mode = match skybox_settings.mode {
+                SkyboxMode::Environment if environment_available => 1.0,
+                SkyboxMode::Cubemap if cubemap_available => 2.0,
+                SkyboxMode::Procedural => 3.0,
+                _ => 0.0,
+            };
+            let sun = &skybox_settings.sun;
+            let sun_direction = sun.direction();
+            let parameters = [
+                mode,
+                skybox_settings.intensity,
+                skybox_settings.rotation.cos(),
+                skybox_settings.rotation.sin(),
             ];
-            let command_buffers = command_buffers.into_iter().collect::<js_sys::Array>();
-            queue.submit(&command_buffers);
+            let sun_values = [sun_direction.x, sun_direction.y, sun_direction.z, sun.turbidity];
+            let previous_projection_view_model = stage.previous_projection_view_matrix()
+                .unwrap_or(projection_view_matrix) * model_matrix;
+            let jitter = stage.jitter();
+            let jitter_values = [jitter.x, jitter.y, 0.0, 0.0];
+            let uniform_values: Vec<f32> = [projection_view_model.as_slice(), &parameters, &sun_values, previous_projection_view_model.as_slice(), &jitter_values]
+                .concat();
+            let uniform_data = js_sys::Float32Array::new_with_length(16 + 4 + 4 + 16 + 4);
+            uniform_data.copy_from(uniform_values.as_slice());
+            queue.write_buffer_with_u32_and_buffer_source(&uniform_buffer, 0, &uniform_data);
         };
         let this = Self {
             render_frame: Box::new(render_frame),
@@ -203,102 +305,301 @@ impl SkyboxRenderPipeline {
         Arc::new(this)
     }
 
-    pub fn render_frame(&self) {
+    pub fn render_frame(&self, graph: &mut RenderGraph, targets: &StageTargets) {
         let func = self.render_frame.as_ref();
-        func();
+        func(graph, targets);
     }
 }
 
 
 struct FinalRenderPipeline {
-    render_frame: Box<dyn Fn() + 'static>,
+    render_frame: RenderFrame,
 }
 
 impl FinalRenderPipeline {
+    #[allow(clippy::arc_with_non_send_sync)]
     pub fn new(device: &Arc<Device>,
         surface: &Arc<Surface>,
         scene_context: &Arc<SceneContext>,
-        stage: &Arc<RenderStage>) -> Arc<Self> {
-        let surface_configuration = surface.configuration();
-        let presentation_format = surface_configuration.presentation_format();
+        stage: &Arc<RenderStage>,
+        environment_map: &Arc<EnvironmentMap>) -> Arc<Self> {
         // model
         let model = scene_context.model();
         let mesh_buffers: HashMap<usize, Arc<MeshBuffer>>;
         mesh_buffers = model.meshes().iter()
-            .map(|v| (v.mesh_index(), MeshBuffer::new(device, v.positions(), v.indices(), v.normals())))
+            .map(|v| (v.mesh_index(), MeshBuffer::new(device, v)))
             .collect();
         // shader
         let shader_module = Shaders::common(device);
-        // vertex shader
-        let mut vertex_state = GpuVertexState::new("vert_main", shader_module.shader_module());
-        // layouts
-        let vertex_buffer_layouts = {
-            // attributes
-            let positions_layout = {
-                let vertex_buffer_attribute = GpuVertexAttribute::new(GpuVertexFormat::Float32x3, 0.0, 0);
-                let vertex_buffer_attributes = vec![vertex_buffer_attribute];
-                let vertex_buffer_attributes = vertex_buffer_attributes.into_iter().collect::<js_sys::Array>();
-                let vertex_buffer_layout = GpuVertexBufferLayout::new(
-                    (std::mem::size_of::<f32>() * 3) as f64,
-                    &vertex_buffer_attributes);
-                vertex_buffer_layout
-            };
-            let normals_layout = {
-                let vertex_buffer_attribute = GpuVertexAttribute::new(GpuVertexFormat::Float32x3, 0.0, 1);
-                let vertex_buffer_attributes = vec![vertex_buffer_attribute];
-                let vertex_buffer_attributes = vertex_buffer_attributes.into_iter().collect::<js_sys::Array>();
-                let vertex_buffer_layout = GpuVertexBufferLayout::new(
-                    (std::mem::size_of::<f32>() * 3) as f64,
-                    &vertex_buffer_attributes);
-                vertex_buffer_layout
-            };
-            let vertex_buffer_layouts: Vec<JsValue> = vec![positions_layout.into(), normals_layout.into()];
-            let vertex_buffer_layouts = vertex_buffer_layouts.into_iter().collect::<js_sys::Array>();
-            vertex_buffer_layouts
+        // attributes
+        let vertex_buffer_layout = |format: GpuVertexFormat, component_count: usize, shader_location: u32| {
+            let vertex_buffer_attribute = GpuVertexAttribute::new(format, 0.0, shader_location);
+            let vertex_buffer_attributes = vec![vertex_buffer_attribute];
+            let vertex_buffer_attributes = vertex_buffer_attributes.into_iter().collect::<js_sys::Array>();
+            let vertex_buffer_layout = GpuVertexBufferLayout::new(
+                (std::mem::size_of::<f32>() * component_count) as f64,
+                &vertex_buffer_attributes);
+            vertex_buffer_layout
         };
-        vertex_state.buffers(&vertex_buffer_layouts);
+        // depth only pipelines of the shadow pass, triangles cast shadows
+        let mut shadow_pipelines: HashMap<PipelineKey, GpuRenderPipeline> = HashMap::new();
+        // depth pipelines of the prepass from the camera, in the shadow map format as well
+        let mut prepass_pipelines: HashMap<PipelineKey, GpuRenderPipeline> = HashMap::new();
+        // descriptors of the mesh pipelines, with and without skinning or morph targets
+        let mut render_descriptors: Vec<(bool, GpuRenderPipelineDescriptor)> = vec![];
+        for deformed in [false, true] {
+            // vertex shader
+            let entry_point = if deformed { "vert_deformed_main" } else { "vert_main" };
+            let mut vertex_state = GpuVertexState::new(entry_point, shader_module.shader_module());
+            // layouts
+            let mut vertex_buffer_layouts: Vec<JsValue> = vec![
+                // positions
+                vertex_buffer_layout(GpuVertexFormat::Float32x3, 3, 0).into(),
+                // normals
+                vertex_buffer_layout(GpuVertexFormat::Float32x3, 3, 1).into(),
+                // texture coordinates 0, 1
+                vertex_buffer_layout(GpuVertexFormat::Float32x2, 2, 2).into(),
+                vertex_buffer_layout(GpuVertexFormat::Float32x2, 2, 3).into(),
+                // tangents
+                vertex_buffer_layout(GpuVertexFormat::Float32x4, 4, 4).into(),
+                // colors
+                vertex_buffer_layout(GpuVertexFormat::Float32x4, 4, 5).into(),
+            ];
+            if deformed {
+                // joints, weights
+                vertex_buffer_layouts.push(vertex_buffer_layout(GpuVertexFormat::Uint32x4, 4, 6).into());
+                vertex_buffer_layouts.push(vertex_buffer_layout(GpuVertexFormat::Float32x4, 4, 7).into());
+            }
+            let vertex_buffer_layouts = vertex_buffer_layouts.into_iter().collect::<js_sys::Array>();
+            vertex_state.buffers(&vertex_buffer_layouts);
 
-        // init
-        let layout = PipelineLayouts::common(device);
-        let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
-        // fragment
-        let target = GpuColorTargetState::new(presentation_format);
-        let fragment_targets: Vec<JsValue> = vec![target.into()];
-        let fragment_targets = fragment_targets.into_iter().collect::<js_sys::Array>();
-        let fragment_state = GpuFragmentState::new("frag_main", shader_module.shader_module(), &fragment_targets);
-        render_descriptor.fragment(&fragment_state);
-        // primitive
-        let mut primitive_state = GpuPrimitiveState::new();
-        primitive_state.topology(GpuPrimitiveTopology::TriangleList);
-        render_descriptor.primitive(&primitive_state);
+            // shadow
+            {
+                let layout = PipelineLayouts::shadow(device, deformed);
+                let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
+                let mut primitive_state = GpuPrimitiveState::new();
+                primitive_state.topology(GpuPrimitiveTopology::TriangleList);
+                render_descriptor.primitive(&primitive_state);
+                let depth_stencil_state = GpuDepthStencilState::new(GpuCompareFunction::Less, true, SHADOW_MAP_FORMAT);
+                render_descriptor.depth_stencil(&depth_stencil_state);
+                shadow_pipelines.insert(PipelineKey::new(Topology::TriangleList, deformed), device.device().create_render_pipeline(&render_descriptor));
+            }
 
-        // multisample
-        let sample_count = stage.sample_count();
-        let mut multisample_state = GpuMultisampleState::new();
-        multisample_state.count(sample_count);
-        render_descriptor.multisample(&multisample_state);
+            // prepass, culled like the meshes. masked materials test their alpha in place of the
+            // alpha-to-coverage of the mesh pipelines.
+            {
+                let layout = PipelineLayouts::prepass(device, deformed);
+                let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
+                let depth_stencil_state = GpuDepthStencilState::new(GpuCompareFunction::Less, true, SHADOW_MAP_FORMAT);
+                render_descriptor.depth_stencil(&depth_stencil_state);
+                for alpha_to_coverage in [false, true] {
+                    if alpha_to_coverage {
+                        let fragment_targets = js_sys::Array::new();
+                        let fragment_state = GpuFragmentState::new("frag_alpha_test_main", shader_module.shader_module(), &fragment_targets);
+                        render_descriptor.fragment(&fragment_state);
+                    }
+                    for double_sided in [false, true] {
+                        let mut primitive_state = GpuPrimitiveState::new();
+                        primitive_state.topology(GpuPrimitiveTopology::TriangleList);
+                        primitive_state.cull_mode(if double_sided { GpuCullMode::None } else { GpuCullMode::Back });
+                        render_descriptor.primitive(&primitive_state);
+                        let key = PipelineKey {
+                            topology: Topology::TriangleList,
+                            deformed,
+                            alpha_to_coverage,
+                            double_sided,
+                            blend: false,
+                        };
+                        prepass_pipelines.insert(key, device.device().create_render_pipeline(&render_descriptor));
+                    }
+                }
+            }
 
-        // depth stencil
-        let mut depth_stencil_state = GpuDepthStencilState::new(GpuCompareFunction::Less, true, GpuTextureFormat::Depth24plus);
-        render_descriptor.depth_stencil(&depth_stencil_state);
+            // init
+            let layout = if deformed { PipelineLayouts::deformed(device) } else { PipelineLayouts::common(device) };
+            let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
 
-        // render
-        let render_pipeline = device.device().create_render_pipeline(&render_descriptor);
+            // depth stencil
+            let depth_stencil_state = GpuDepthStencilState::new(GpuCompareFunction::Less, true, GpuTextureFormat::Depth24plus);
+            render_descriptor.depth_stencil(&depth_stencil_state);
+            render_descriptors.push((deformed, render_descriptor));
+        }
+        // ground plane receiving the shadow
+        let ground_descriptor = {
+            let vertex_state = GpuVertexState::new("vert_ground_main", shader_module.shader_module());
+            let layout = PipelineLayouts::ground(device);
+            let mut render_descriptor = GpuRenderPipelineDescriptor::new(layout.layout(), &vertex_state);
+            let fragment_targets: Vec<JsValue> = vec![
+                GpuColorTargetState::new(HDR_FORMAT).into(),
+                GpuColorTargetState::new(VELOCITY_FORMAT).into(),
+            ];
+            let fragment_targets = fragment_targets.into_iter().collect::<js_sys::Array>();
+            let fragment_state = GpuFragmentState::new("frag_ground_main", shader_module.shader_module(), &fragment_targets);
+            render_descriptor.fragment(&fragment_state);
+            let mut primitive_state = GpuPrimitiveState::new();
+            primitive_state.topology(GpuPrimitiveTopology::TriangleList);
+            render_descriptor.primitive(&primitive_state);
+            let depth_stencil_state = GpuDepthStencilState::new(GpuCompareFunction::Less, true, GpuTextureFormat::Depth24plus);
+            render_descriptor.depth_stencil(&depth_stencil_state);
+            render_descriptor
+        };
+        // pipelines drawing into the stage, created again whenever the sample count changes.
+        // multisampled masked materials get their own pipelines with alpha-to-coverage, blended
+        // materials keep the depth and the velocity of the surfaces behind them.
+        let create_stage_pipelines = {
+            let device = Arc::clone(device);
+            let shader_module = Arc::clone(&shader_module);
+            move |sample_count: u32| {
+                let fragment_state = |entry_point: &str, blend: bool| {
+                    let mut color_target = GpuColorTargetState::new(HDR_FORMAT);
+                    let mut velocity_target = GpuColorTargetState::new(VELOCITY_FORMAT);
+                    if blend {
+                        let mut color = GpuBlendComponent::new();
+                        color.operation(GpuBlendOperation::Add);
+                        color.src_factor(GpuBlendFactor::SrcAlpha);
+                        color.dst_factor(GpuBlendFactor::OneMinusSrcAlpha);
+                        let mut alpha = GpuBlendComponent::new();
+                        alpha.operation(GpuBlendOperation::Add);
+                        alpha.src_factor(GpuBlendFactor::One);
+                        alpha.dst_factor(GpuBlendFactor::OneMinusSrcAlpha);
+                        color_target.blend(&GpuBlendState::new(&alpha, &color));
+                        velocity_target.write_mask(0);
+                    }
+                    let fragment_targets: Vec<JsValue> = vec![color_target.into(), velocity_target.into()];
+                    let fragment_targets = fragment_targets.into_iter().collect::<js_sys::Array>();
+                    GpuFragmentState::new(entry_point, shader_module.shader_module(), &fragment_targets)
+                };
+                let mut meshes: HashMap<PipelineKey, GpuRenderPipeline> = HashMap::new();
+                for (deformed, render_descriptor) in render_descriptors.iter() {
+                    let mut render_descriptor = render_descriptor.clone();
+                    for (alpha_to_coverage, blend) in [(false, false), (true, false), (false, true)] {
+                        if alpha_to_coverage && sample_count == 1 {
+                            continue
+                        }
+                        // fragment
+                        let entry_point = if alpha_to_coverage { "frag_alpha_to_coverage_main" } else { "frag_main" };
+                        render_descriptor.fragment(&fragment_state(entry_point, blend));
+                        // multisample
+                        let mut multisample_state = GpuMultisampleState::new();
+                        multisample_state.count(sample_count);
+                        multisample_state.alpha_to_coverage_enabled(alpha_to_coverage);
+                        render_descriptor.multisample(&multisample_state);
+                        // depth stencil
+                        let depth_stencil_state = GpuDepthStencilState::new(GpuCompareFunction::Less, !blend, GpuTextureFormat::Depth24plus);
+                        render_descriptor.depth_stencil(&depth_stencil_state);
+                        for topology in [Topology::PointList, Topology::LineList, Topology::TriangleList] {
+                            // only triangles have faces to cull
+                            let double_sided_variants: &[bool] = if topology == Topology::TriangleList { &[false, true] } else { &[true] };
+                            for &double_sided in double_sided_variants {
+                                // primitive
+                                let mut primitive_state = GpuPrimitiveState::new();
+                                primitive_state.topology(match topology {
+                                    Topology::PointList => GpuPrimitiveTopology::PointList,
+                                    Topology::LineList => GpuPrimitiveTopology::LineList,
+                                    Topology::TriangleList => GpuPrimitiveTopology::TriangleList,
+                                });
+                                primitive_state.cull_mode(if double_sided { GpuCullMode::None } else { GpuCullMode::Back });
+                                render_descriptor.primitive(&primitive_state);
+                                let key = PipelineKey {
+                                    topology,
+                                    deformed: *deformed,
+                                    alpha_to_coverage,
+                                    double_sided,
+                                    blend,
+                                };
+                                meshes.insert(key, device.device().create_render_pipeline(&render_descriptor));
+                            }
+                        }
+                    }
+                }
+                let mut ground_descriptor = ground_descriptor.clone();
+                let mut multisample_state = GpuMultisampleState::new();
+                multisample_state.count(sample_count);
+                ground_descriptor.multisample(&multisample_state);
+                let ground = device.device().create_render_pipeline(&ground_descriptor);
+                StagePipelines {
+                    sample_count,
+                    meshes,
+                    ground,
+                }
+            }
+        };
+        let stage_pipelines = create_stage_pipelines(stage.sample_count());
+        // bind groups are shared among the pipelines created with the same layout
+        let render_pipeline = &stage_pipelines.meshes[&PipelineKey::new(Topology::TriangleList, false)];
 
         // uniform
         let uniform_buffer_descriptor = GpuBufferDescriptor::new(
-            (std::mem::size_of::<f32>() * (16 + 4)) as f64,
+            std::mem::size_of::<UniformData>() as f64,
             gpu_buffer_usage::UNIFORM | gpu_buffer_usage::COPY_DST);
         let uniform_buffer = device.device().create_buffer(&uniform_buffer_descriptor);
-        // entries
+        // lights
+        let light_buffer = LightBuffer::new(device, model.lights().len());
+        // entries, recreated along with the shadow map
         // https://www.w3.org/TR/webgpu/#dictdef-gpubindgroupdescriptor
-        let buffer_binding = GpuBufferBinding::new(&uniform_buffer);
-        let buffer_binding: JsValue = buffer_binding.into();
-        let buffer_bind_entry = GpuBindGroupEntry::new(0, &buffer_binding);
-        let bind_entries: Vec<JsValue> = vec![buffer_bind_entry.into()];
-        let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
-        let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &render_pipeline.get_bind_group_layout(0));
-        let bind_group = device.device().create_bind_group(&bind_group_descriptor);
+        let primary_layout = render_pipeline.get_bind_group_layout(0);
+        let create_bind_group = {
+            let device = Arc::clone(device);
+            let uniform_buffer = uniform_buffer.clone();
+            let light_buffer = Arc::clone(&light_buffer);
+            let environment_map = Arc::clone(environment_map);
+            move |shadow_map: &ShadowMap, ambient_occlusion_view: &GpuTextureView| {
+                let buffer_binding: JsValue = GpuBufferBinding::new(&uniform_buffer).into();
+                let light_binding: JsValue = GpuBufferBinding::new(light_buffer.buffer()).into();
+                let bind_entries: Vec<JsValue> = vec![
+                    GpuBindGroupEntry::new(0, &buffer_binding).into(),
+                    GpuBindGroupEntry::new(1, &light_binding).into(),
+                    // environment
+                    GpuBindGroupEntry::new(2, environment_map.specular_view()).into(),
+                    GpuBindGroupEntry::new(3, environment_map.irradiance_view()).into(),
+                    GpuBindGroupEntry::new(4, environment_map.brdf_lut_view()).into(),
+                    GpuBindGroupEntry::new(5, environment_map.sampler()).into(),
+                    // shadow
+                    GpuBindGroupEntry::new(6, shadow_map.view()).into(),
+                    GpuBindGroupEntry::new(7, shadow_map.sampler()).into(),
+                    GpuBindGroupEntry::new(8, ambient_occlusion_view).into(),
+                ];
+                let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+                let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &primary_layout);
+                device.device().create_bind_group(&bind_group_descriptor)
+            }
+        };
+        // the primary bind group takes the ambient occlusion of each frame
+        let create_bind_group = Arc::new(create_bind_group);
+        let shadow_map = Mutex::new(ShadowMap::new(device, scene_context.shadow_settings().resolution));
+        // the shadow passes render the scene with the projection of each cascade in its own uniform
+        let shadow_layout = shadow_pipelines[&PipelineKey::new(Topology::TriangleList, false)].get_bind_group_layout(0);
+        let shadow_uniforms: Vec<(GpuBuffer, GpuBindGroup)> = (0..MAX_CASCADE_COUNT)
+            .map(|_| {
+                let shadow_uniform_buffer = device.device().create_buffer(&uniform_buffer_descriptor);
+                let buffer_binding: JsValue = GpuBufferBinding::new(&shadow_uniform_buffer).into();
+                let bind_entries: Vec<JsValue> = vec![GpuBindGroupEntry::new(0, &buffer_binding).into()];
+                let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+                let shadow_bind_group = device.device().create_bind_group(&GpuBindGroupDescriptor::new(&bind_entries, &shadow_layout));
+                (shadow_uniform_buffer, shadow_bind_group)
+            })
+            .collect();
+        // the depth prepass of the screen-space effects shares the primary group of the shadow pipelines
+        let prepass_bind_group = {
+            let buffer_binding: JsValue = GpuBufferBinding::new(&uniform_buffer).into();
+            let bind_entries: Vec<JsValue> = vec![GpuBindGroupEntry::new(0, &buffer_binding).into()];
+            let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+            device.device().create_bind_group(&GpuBindGroupDescriptor::new(&bind_entries, &shadow_layout))
+        };
+        let ambient_occlusion = AmbientOcclusionPasses::new(device);
+        let empty_bind_group = {
+            let empty_entries = js_sys::Array::new();
+            let layout = device.device().create_bind_group_layout(&GpuBindGroupLayoutDescriptor::new(&empty_entries));
+            device.device().create_bind_group(&GpuBindGroupDescriptor::new(&empty_entries, &layout))
+        };
+        // materials
+        let texture_cache = TextureCache::new(device);
+        let material_layout = render_pipeline.get_bind_group_layout(2);
+        let mut material_buffers: HashMap<Option<usize>, Arc<MaterialBuffer>> = HashMap::new();
+        for primitive in model.meshes().iter().flat_map(|v| v.primitives()) {
+            material_buffers.entry(primitive.material_index())
+                .or_insert_with(|| MaterialBuffer::new(device, primitive.material(), &material_layout, &texture_cache));
+        }
         // secondary bind group
         // entities
         let object_buffer_descriptor = GpuBufferDescriptor::new(
@@ -321,93 +622,351 @@ impl FinalRenderPipeline {
                 bind_group
             })
             .collect();
-        // transfer
-        {
-            let queue = device.device().queue();
-            let matrix_values: Vec<f32> = model.nodes().iter()
-                .flat_map(|v| 
-                    // align 256 bytes
-                    vec![v.transform().as_slice(),
-                         v.transform().as_slice(),
-                         v.transform().as_slice(),
-                         v.transform().as_slice()])
-                .flatten()
-                .copied()
-                .collect();
-            let object_data = js_sys::Float32Array::new_with_length(16 * 4 * model.nodes().len() as u32);
-            object_data.copy_from(matrix_values.as_slice());
-            queue.write_buffer_with_u32_and_buffer_source(&object_buffer, 0, &object_data);
-        }
+        // joint matrices and morph target weights of deformed nodes
+        let deformation_layout = stage_pipelines.meshes[&PipelineKey::new(Topology::TriangleList, true)].get_bind_group_layout(3);
+        let deformations: Vec<Deformation> = model.nodes().iter()
+            .filter_map(|v| {
+                let mesh_buffer = mesh_buffers.get(&v.mesh_index()?)?;
+                let mesh = mesh_buffer.mesh();
+                let skin = v.skin_index()
+                    .and_then(|v| model.skins().get(v))
+                    .filter(|_| mesh.skinned());
+                if skin.is_none() && mesh.morph_target_count() == 0 {
+                    return None
+                }
+                let deformation = Deformation {
+                    node_index: v.node_index(),
+                    skinned: skin.is_some(),
+                    joint_count: skin.map(|v| v.joint_indices().len()).unwrap_or(0).max(1),
+                    morph_target_count: mesh.morph_target_count(),
+                    joint_offset: 0,
+                    weight_offset: 0,
+                    bind_group: None,
+                };
+                Some(deformation)
+            })
+            .collect();
+        // align 256 bytes
+        let chunk_size = |size: usize| size.div_ceil(256) * 256;
+        let joint_size = |v: &Deformation| std::mem::size_of::<f32>() * 16 * v.joint_count;
+        // skinned flag and morph target count followed by the weights
+        let weight_size = |v: &Deformation| std::mem::size_of::<u32>() * (4 + v.morph_target_count.max(1));
+        let joint_buffer_descriptor = GpuBufferDescriptor::new(
+            deformations.iter().map(|v| chunk_size(joint_size(v))).sum::<usize>().max(256) as f64,
+            gpu_buffer_usage::STORAGE | gpu_buffer_usage::COPY_DST);
+        let joint_buffer = device.device().create_buffer(&joint_buffer_descriptor);
+        let weight_buffer_descriptor = GpuBufferDescriptor::new(
+            deformations.iter().map(|v| chunk_size(weight_size(v))).sum::<usize>().max(256) as f64,
+            gpu_buffer_usage::STORAGE | gpu_buffer_usage::COPY_DST);
+        let weight_buffer = device.device().create_buffer(&weight_buffer_descriptor);
+        let mut joint_offset = 0;
+        let mut weight_offset = 0;
+        let deformations: Vec<Deformation> = deformations.into_iter()
+            .map(|mut deformation| {
+                let node = model.nodes().iter().find(|v| v.node_index() == deformation.node_index);
+                let mesh_buffer = node.and_then(|v| mesh_buffers.get(&v.mesh_index()?));
+                let Some(mesh_buffer) = mesh_buffer else { return deformation };
+                let buffer_binding = |buffer: &GpuBuffer, offset: usize, size: usize| {
+                    let mut buffer_binding = GpuBufferBinding::new(buffer);
+                    buffer_binding.offset(offset as f64);
+                    buffer_binding.size(size as f64);
+                    let buffer_binding: JsValue = buffer_binding.into();
+                    buffer_binding
+                };
+                let morph_target_binding: JsValue = GpuBufferBinding::new(mesh_buffer.morph_target_buffer()).into();
+                let bind_entries: Vec<JsValue> = vec![
+                    GpuBindGroupEntry::new(0, &buffer_binding(&joint_buffer, joint_offset, joint_size(&deformation))).into(),
+                    GpuBindGroupEntry::new(1, &morph_target_binding).into(),
+                    GpuBindGroupEntry::new(2, &buffer_binding(&weight_buffer, weight_offset, weight_size(&deformation))).into(),
+                ];
+                let bind_entries = bind_entries.into_iter().collect::<js_sys::Array>();
+                let bind_group_descriptor = GpuBindGroupDescriptor::new(&bind_entries, &deformation_layout);
+                deformation.bind_group = Some(device.device().create_bind_group(&bind_group_descriptor));
+                deformation.joint_offset = joint_offset;
+                deformation.weight_offset = weight_offset;
+                joint_offset += chunk_size(joint_size(&deformation));
+                weight_offset += chunk_size(weight_size(&deformation));
+                deformation
+            })
+            .collect();
+        let deformations: HashMap<usize, Deformation> = deformations.into_iter()
+            .filter(|v| v.bind_group.is_some())
+            .map(|v| (v.node_index, v))
+            .collect();
+        let environment = glm::vec4(
+            environment_map.specular_mip_count() as f32,
+            1.0,
+            if environment_map.available() { 1.0 } else { 0.0 },
+            0.0);
+        // last transform of each node and the nodes that moved in the previous frame
+        let node_transforms: Mutex<(HashMap<usize, glm::Mat4>, Vec<usize>)> = Mutex::new((HashMap::new(), vec![]));
+        let stage_pipelines = Mutex::new(Arc::new(stage_pipelines));
+        let shadow_pipelines = Arc::new(shadow_pipelines);
+        let prepass_pipelines = Arc::new(prepass_pipelines);
+        let node_drawer = Arc::new(NodeDrawer {
+            model: Arc::clone(model),
+            mesh_buffers,
+            secondary_bind_groups,
+            deformations,
+            material_buffers,
+        });
         // render
         let device = Arc::clone(device);
         let surface = Arc::clone(surface);
         let scene_context = Arc::clone(scene_context);
         let stage = Arc::clone(stage);
         let model = Arc::clone(model);
-        let render_frame = move || {
-            // frame
-            let command_encoder = device.device().create_command_encoder();
-            let context_texture_view = surface.canvas_context().get_current_texture().create_view();
+        let render_frame = move |graph: &mut RenderGraph, targets: &StageTargets| {
+            let surface_configuration = surface.configuration();
+            // lights follow their nodes
+            let lights: Vec<_> = model.lights().iter()
+                .filter_map(|v| Some((Arc::clone(v), scene_context.world_transform(v.node_index())?)))
+                .collect();
+            // the procedural sky lights the meshes with its sun, which is also the key light of
+            // models without a directional light
+            let skybox_settings = scene_context.skybox_settings();
+            let directional = lights.iter().any(|(light, _)| matches!(light.kind(), LightKind::Directional));
+            let sun = Some(&skybox_settings.sun)
+                .filter(|_| skybox_settings.mode == SkyboxMode::Procedural || (skybox_settings.key_light && !directional));
+            // the sun casts the shadow, otherwise the first directional light
+            let shadow_settings = scene_context.shadow_settings();
+            let shadow_caster = match sun {
+                Some(sun) => Some((lights.len(), -sun.direction())),
+                None => lights.iter()
+                    .enumerate()
+                    .find(|(_, (light, _))| matches!(light.kind(), LightKind::Directional))
+                    .map(|(light_index, (_, transform))| {
+                        (light_index, glm::normalize(&(transform * glm::vec4(0.0, 0.0, -1.0, 0.0)).xyz()))
+                    }),
+            }.filter(|_| shadow_settings.enabled);
+            let shadow_map = {
+                let Ok(mut shadow_map) = shadow_map.lock() else { return };
+                if shadow_map.resolution() != shadow_settings.resolution.max(1) {
+                    *shadow_map = ShadowMap::new(&device, shadow_settings.resolution);
+                }
+                Arc::clone(&shadow_map)
+            };
+            let stage_pipelines = {
+                let Ok(mut stage_pipelines) = stage_pipelines.lock() else { return };
+                if stage_pipelines.sample_count != stage.sample_count() {
+                    *stage_pipelines = Arc::new(create_stage_pipelines(stage.sample_count()));
+                }
+                Arc::clone(&stage_pipelines)
+            };
+            // scene bounds include the ground catching the shadows
+            let bounding_box = model.bounding_box();
+            let ground_visible = shadow_settings.ground_plane && bounding_box.is_some();
+            let ground = match bounding_box.filter(|_| ground_visible) {
+                Some(bounding_box) => {
+                    let center = (bounding_box.max() + bounding_box.min()) * 0.5;
+                    let extent = glm::distance(bounding_box.max(), bounding_box.min()) * 2.0;
+                    glm::vec4(center.x, center.z, bounding_box.min().y, extent)
+                },
+                None => glm::vec4(0.0, 0.0, 0.0, 0.0),
+            };
+            let scene_bounds = bounding_box.map(|v| {
+                let ground_min = glm::vec3(ground.x - ground.w, ground.z, ground.y - ground.w);
+                let ground_max = glm::vec3(ground.x + ground.w, ground.z, ground.y + ground.w);
+                if ground_visible {
+                    (glm::min2(v.min(), &ground_min), glm::max2(v.max(), &ground_max))
+                } else {
+                    (*v.min(), *v.max())
+                }
+            });
+            // cascades along the view frustum
+            let view_matrix = stage.view_matrix(&surface_configuration, &scene_context);
+            let projection_view_matrix = stage.projection_view_matrix(&surface_configuration, &scene_context);
+            let projection_matrix = stage.unjittered_projection_matrix(&surface_configuration, &scene_context);
+            let cascades = match (shadow_caster, scene_bounds) {
+                (Some((_, direction)), Some(scene_bounds)) => {
+                    shadow_map.cascades(&direction, &view_matrix, &projection_matrix, scene_bounds, &shadow_settings)
+                },
+                _ => vec![],
+            };
 
-            // render pass
-            let mut color_attachment = GpuRenderPassColorAttachment::new(
-                GpuLoadOp::Load, GpuStoreOp::Discard, &stage.color_texture().create_view());
-            color_attachment.resolve_target(&context_texture_view);
-            let color_attachments: Vec<JsValue> = vec![
-                color_attachment.into(),
-            ];
-            let color_attachments = color_attachments.into_iter().collect::<js_sys::Array>();
-            let mut render_pass_descriptor = GpuRenderPassDescriptor::new(&color_attachments);
-            // depth stencil
-            let mut depth_stencil_attachment = GpuRenderPassDepthStencilAttachment::new(&stage.depth_texture().create_view());
-            depth_stencil_attachment.depth_load_op(GpuLoadOp::Clear);
-            depth_stencil_attachment.depth_store_op(GpuStoreOp::Store);
-            depth_stencil_attachment.depth_clear_value(1.0);
-            render_pass_descriptor.depth_stencil_attachment(&depth_stencil_attachment);
-
-            // render pass encoder
-            let render_pass_encoder = command_encoder.begin_render_pass(&render_pass_descriptor);
-            render_pass_encoder.set_pipeline(&render_pipeline);
-            render_pass_encoder.set_bind_group(0, Some(&bind_group));
-            // nodes
-            let nodes = model.nodes();
-            for (local_index, node) in nodes.iter().enumerate() {
-                let Some(mesh_index) = node.mesh_index() else { continue };
-                let Some(mesh_buffer) = mesh_buffers.get(&mesh_index) else { continue };
-                let Some(secondary_bind_group) = secondary_bind_groups.get(local_index) else { continue };
-                render_pass_encoder.set_bind_group(1, Some(&secondary_bind_group));
-                render_pass_encoder.set_vertex_buffer(0, Some(mesh_buffer.position_buffer()));
-                render_pass_encoder.set_vertex_buffer(1, Some(mesh_buffer.normal_buffer()));
-                render_pass_encoder.set_index_buffer(mesh_buffer.index_buffer(), GpuIndexFormat::Uint32);
-                render_pass_encoder.draw_indexed(mesh_buffer.index_count() as u32);
+            // shadow pass of each cascade
+            let mut shadow_cascades = vec![];
+            for (cascade_index, (_, shadow_bind_group)) in shadow_uniforms.iter().enumerate().take(cascades.len()) {
+                let shadow_cascade = graph.import_texture("shadow cascade", shadow_map.layer_view(cascade_index));
+                shadow_cascades.push(shadow_cascade);
+                let pass = PassDescription::new("shadow")
+                    .depth(DepthAttachment::new(shadow_cascade));
+                let node_drawer = Arc::clone(&node_drawer);
+                let shadow_pipelines = Arc::clone(&shadow_pipelines);
+                let shadow_bind_group = shadow_bind_group.clone();
+                let empty_bind_group = empty_bind_group.clone();
+                graph.add_pass(pass, move |render_pass_encoder, _| {
+                    render_pass_encoder.set_bind_group(0, Some(&shadow_bind_group));
+                    render_pass_encoder.set_bind_group(2, Some(&empty_bind_group));
+                    node_drawer.draw_nodes(render_pass_encoder, &shadow_pipelines, false);
+                });
+            }
+
+            // depth of the nodes for the ambient occlusion and the depth of field
+            let ambient_occlusion_settings = scene_context.ambient_occlusion_settings();
+            if ambient_occlusion_settings.enabled || scene_context.depth_of_field_settings().enabled {
+                let pass = PassDescription::new("depth prepass")
+                    .depth(DepthAttachment::new(targets.prepass_depth));
+                let node_drawer = Arc::clone(&node_drawer);
+                let prepass_pipelines = Arc::clone(&prepass_pipelines);
+                let prepass_bind_group = prepass_bind_group.clone();
+                graph.add_pass(pass, move |render_pass_encoder, _| {
+                    render_pass_encoder.set_bind_group(0, Some(&prepass_bind_group));
+                    // blended surfaces leave the depth behind them like in the mesh pass
+                    node_drawer.draw_primitives(render_pass_encoder, &prepass_pipelines, Some(false));
+                });
             }
-            render_pass_encoder.end();
-            
+
+            // ambient occlusion from the depth of the nodes, or none at all
+            let Some(hdr_description) = graph.texture_description(targets.hdr) else { return };
+            let (width, height) = (hdr_description.width, hdr_description.height);
+            let ambient_occlusion_target = graph.create_texture("ambient occlusion",
+                TextureDescription::new(GpuTextureFormat::R8unorm, width, height));
+            if ambient_occlusion_settings.enabled {
+                // the radius follows the size of the model
+                let model_size = model.bounding_box()
+                    .map(|v| glm::distance(v.max(), v.min()))
+                    .unwrap_or(1.0);
+                let projection_matrix = stage.projection_matrix(&surface_configuration, &scene_context);
+                ambient_occlusion.add_passes(graph, targets.prepass_depth, ambient_occlusion_target, &projection_matrix,
+                    ambient_occlusion_settings.radius * model_size, &ambient_occlusion_settings);
+            } else {
+                let pass = PassDescription::new("ambient occlusion")
+                    .color(ColorAttachment::new(ambient_occlusion_target).clear([1.0, 1.0, 1.0, 1.0]));
+                graph.add_pass(pass, |_, _| {});
+            }
+
+            // render pass over the skybox, resolving the multisampled color into the HDR target
+            let pass = PassDescription::new("meshes")
+                .color(ColorAttachment::new(targets.color).resolve(targets.resolve_target))
+                .color(ColorAttachment::new(targets.velocity))
+                .depth(DepthAttachment::new(targets.depth).clear())
+                .read(ambient_occlusion_target);
+            let pass = shadow_cascades.into_iter().fold(pass, PassDescription::read);
+            {
+                let node_drawer = Arc::clone(&node_drawer);
+                let create_bind_group = Arc::clone(&create_bind_group);
+                let shadow_map = Arc::clone(&shadow_map);
+                graph.add_pass(pass, move |render_pass_encoder, textures| {
+                    let Some(ambient_occlusion_view) = textures.view(ambient_occlusion_target) else { return };
+                    let bind_group = create_bind_group(&shadow_map, ambient_occlusion_view);
+                    render_pass_encoder.set_bind_group(0, Some(&bind_group));
+                    node_drawer.draw_nodes(render_pass_encoder, &stage_pipelines.meshes, true);
+                    if ground_visible {
+                        render_pass_encoder.set_pipeline(&stage_pipelines.ground);
+                        render_pass_encoder.draw(6);
+                    }
+                });
+            }
+
             // write
             let queue = device.device().queue();
+            // node transforms changed by the scene graph, followed by the transform of the previous
+            // frame for the velocity
+            let changed_transforms = scene_context.take_changed_transforms();
+            let chunk_size = std::mem::size_of::<f32>() * 16 * 4;
+            let Ok(mut node_transforms) = node_transforms.lock() else { return };
+            let (transforms, moved) = &mut *node_transforms;
+            // nodes that stopped moving were still in motion during the previous frame
+            for local_index in moved.iter().filter(|v| !changed_transforms.iter().any(|(w, _)| w == *v)) {
+                let Some(transform) = transforms.get(local_index) else { continue };
+                let object_data = js_sys::Float32Array::new_with_length(16);
+                object_data.copy_from(transform.as_slice());
+                queue.write_buffer_with_u32_and_buffer_source(
+                    &object_buffer, (chunk_size * local_index + std::mem::size_of::<f32>() * 16 * 2) as u32, &object_data);
+            }
+            *moved = changed_transforms.iter().map(|(v, _)| *v).collect();
+            for (local_index, transform) in changed_transforms {
+                // align 256 bytes
+                let normal_matrix = glm::transpose(&glm::inverse(&transform));
+                let previous_transform = transforms.insert(local_index, transform).unwrap_or(transform);
+                let matrix_values: Vec<f32> = [transform.as_slice(), normal_matrix.as_slice(), previous_transform.as_slice()]
+                    .concat();
+                let object_data = js_sys::Float32Array::new_with_length(16 * 3);
+                object_data.copy_from(matrix_values.as_slice());
+                queue.write_buffer_with_u32_and_buffer_source(
+                    &object_buffer, (chunk_size * local_index) as u32, &object_data);
+            }
+            light_buffer.write(&lights, sun);
+            // joint matrices and weights follow the scene every frame
+            for (node_index, deformation) in node_drawer.deformations.iter() {
+                let matrix_values: Vec<f32> = scene_context.joint_matrices(*node_index).iter()
+                    .flat_map(|v| v.as_slice().to_vec())
+                    .collect();
+                if deformation.skinned && !matrix_values.is_empty() {
+                    let joint_data = js_sys::Float32Array::new_with_length(matrix_values.len() as u32);
+                    joint_data.copy_from(matrix_values.as_slice());
+                    queue.write_buffer_with_u32_and_buffer_source(&joint_buffer, deformation.joint_offset as u32, &joint_data);
+                }
+                let weights = scene_context.node_weights(*node_index);
+                let weight_values: Vec<u32> = [deformation.skinned as u32, weights.len() as u32, 0, 0].into_iter()
+                    .chain(weights.iter().map(|v| v.to_bits()))
+                    .collect();
+                let weight_data = js_sys::Uint32Array::new_with_length(weight_values.len() as u32);
+                weight_data.copy_from(weight_values.as_slice());
+                queue.write_buffer_with_u32_and_buffer_source(&weight_buffer, deformation.weight_offset as u32, &weight_data);
+            }
             {
-                let size = std::mem::size_of::<f32>() * (16 + 4);
-                let projection_view_matrix = stage.projection_view_matrix(&surface_configuration, &scene_context);
+                let size = std::mem::size_of::<UniformData>();
                 let camera_position = stage.camera_position(&surface_configuration, &scene_context);
-                let uniform_array = js_sys::Uint8Array::new_with_length(size as u32);
+                let shadow = match shadow_caster.filter(|_| !cascades.is_empty()) {
+                    Some((light_index, _)) => glm::vec4(
+                        light_index as f32,
+                        shadow_settings.depth_bias,
+                        shadow_settings.normal_bias,
+                        shadow_settings.filter_radius as f32),
+                    None => glm::vec4(-1.0, 0.0, 0.0, 0.0),
+                };
+                let mut shadow_projection_view_matrices = [glm::Mat4::identity(); MAX_CASCADE_COUNT];
+                let mut shadow_splits = glm::Vec4::zeros();
+                let mut shadow_texel_sizes = glm::Vec4::zeros();
+                for (cascade_index, cascade) in cascades.iter().enumerate() {
+                    shadow_projection_view_matrices[cascade_index] = cascade.projection_view;
+                    shadow_splits[cascade_index] = cascade.split_depth;
+                    shadow_texel_sizes[cascade_index] = cascade.texel_size;
+                }
+                let shadow_cascades = glm::vec4(
+                    cascades.len() as f32,
+                    shadow_settings.cascade_blend,
+                    if shadow_settings.debug_cascades { 1.0 } else { 0.0 },
+                    cascades.first().map(|v| v.near_depth).unwrap_or(0.0));
+                // the third row of the view matrix gives the view depth
+                let jitter = stage.jitter();
+                let camera_depth = -glm::vec4(view_matrix[(2, 0)], view_matrix[(2, 1)], view_matrix[(2, 2)], view_matrix[(2, 3)]);
                 let uniform_data = UniformData {
                     projection_view_matrix,
                     camera_position,
+                    // the lighting turns with the skybox
+                    environment: glm::vec4(environment.x, environment.y, environment.z, skybox_settings.rotation),
+                    shadow_projection_view_matrices,
+                    shadow,
+                    shadow_splits,
+                    shadow_texel_sizes,
+                    shadow_cascades,
+                    camera_depth,
+                    ground,
+                    previous_projection_view_matrix: stage.previous_projection_view_matrix().unwrap_or(projection_view_matrix),
+                    jitter: glm::vec4(jitter.x, jitter.y, 0.0, 0.0),
                 };
-                let ptr = (&uniform_data as *const _) as *const u8;
-                let slice = unsafe {
-                    std::slice::from_raw_parts(ptr, size)
+                let write_uniform = |buffer: &GpuBuffer, uniform_data: &UniformData| {
+                    let uniform_array = js_sys::Uint8Array::new_with_length(size as u32);
+                    let ptr = (uniform_data as *const _) as *const u8;
+                    let slice = unsafe {
+                        std::slice::from_raw_parts(ptr, size)
+                    };
+                    uniform_array.copy_from(slice);
+                    queue.write_buffer_with_u32_and_buffer_source(buffer, 0, &uniform_array);
                 };
-                uniform_array.copy_from(slice);
-                queue.write_buffer_with_u32_and_buffer_source(&uniform_buffer, 0, &uniform_array);
+                write_uniform(&uniform_buffer, &uniform_data);
+                // the shadow passes project with the cascades
+                for (cascade, (shadow_uniform_buffer, _)) in cascades.iter().zip(shadow_uniforms.iter()) {
+                    let shadow_uniform_data = UniformData {
+                        projection_view_matrix: cascade.projection_view,
+                        ..uniform_data
+                    };
+                    write_uniform(shadow_uniform_buffer, &shadow_uniform_data);
+                }
             }
-            // submit
-            let command_buffer = command_encoder.finish();
-            let command_buffers: Vec<JsValue> = vec![
-                command_buffer.into(),
-            ];
-            let command_buffers = command_buffers.into_iter().collect::<js_sys::Array>();
-            queue.submit(&command_buffers);
         };
         let this = Self {
             render_frame: Box::new(render_frame),
@@ -415,56 +974,173 @@ impl FinalRenderPipeline {
         Arc::new(this)
     }
 
-    pub fn render_frame(&self) {
+    pub fn render_frame(&self, graph: &mut RenderGraph, targets: &StageTargets) {
         let func = self.render_frame.as_ref();
-        func();
+        func(graph, targets);
     }
 }
 
+// attachments shared by the passes drawing the scene
+struct StageTargets {
+    // multisampled color resolved into the HDR target, or the HDR target itself
+    color: TextureHandle,
+    resolve_target: Option<TextureHandle>,
+    depth: TextureHandle,
+    // screen-space motion since the previous frame, at the sample count of the color
+    velocity: TextureHandle,
+    hdr: TextureHandle,
+    // the temporally antialiased HDR image, or the HDR target itself
+    antialiased: TextureHandle,
+    // single-sampled depth of the nodes for the screen-space effects, only drawn when one reads it
+    prepass_depth: TextureHandle,
+    swapchain: TextureHandle,
+}
+
+// camera motion between frames for the temporal antialiasing
+#[derive(Clone, Copy)]
+struct StageFrame {
+    temporal: bool,
+    frame_index: u32,
+    // sub-pixel offset of the projection in normalized device coordinates, none without the
+    // temporal antialiasing
+    jitter: Option<glm::Vec2>,
+    // projection views without the jitter
+    projection_view: glm::Mat4,
+    previous_projection_view: glm::Mat4,
+}
+
 struct RenderStage {
-    color_texture: GpuTexture,
-    depth_texture: GpuTexture,
-    sample_count: u32,
+    device: Arc<Device>,
+    sample_count: Mutex<u32>,
+    frame: Mutex<Option<StageFrame>>,
 }
 
 impl RenderStage {
-    pub fn new(device: &Arc<Device>, surface: &Arc<Surface>) -> Arc<Self> {
-        let sample_count = 4;
-        let surface_configuration = surface.configuration();
-        let presentation_format = surface_configuration.presentation_format();
-        let presentation_size = surface_configuration.presentation_size();
-        // color texture
-        let mut color_texture_descriptor = GpuTextureDescriptor::new(
-            presentation_format, &presentation_size, gpu_texture_usage::RENDER_ATTACHMENT);
-        color_texture_descriptor.sample_count(sample_count);
-        let color_texture = device.device().create_texture(&color_texture_descriptor);
-        // depth texture
-        let mut depth_texture_descriptor = GpuTextureDescriptor::new(
-            GpuTextureFormat::Depth24plus, &presentation_size, gpu_texture_usage::RENDER_ATTACHMENT);
-        depth_texture_descriptor.sample_count(sample_count);
-        let depth_texture = device.device().create_texture(&depth_texture_descriptor);
+    pub fn new(device: &Arc<Device>, settings: &AntialiasingSettings) -> Arc<Self> {
         // this
         let this = Self {
-            color_texture,
-            depth_texture,
-            sample_count,
+            device: Arc::clone(device),
+            sample_count: Mutex::new(1),
+            frame: Mutex::new(None),
         };
+        this.set_antialiasing(settings);
         Arc::new(this)
     }
 
-    pub fn color_texture(&self) -> &GpuTexture {
-        &self.color_texture
+    // WebGPU only guarantees 1 and 4 samples, and only for the formats it allows to multisample.
+    // the largest of them up to the requested count, or 1 when any of the formats cannot take it.
+    pub fn supported_sample_count(formats: &[GpuTextureFormat], sample_count: u32, has_feature: impl Fn(&str) -> bool) -> u32 {
+        let multisampled = formats.iter().all(|format| match format {
+            GpuTextureFormat::Rg11b10ufloat => has_feature("rg11b10ufloat-renderable"),
+            GpuTextureFormat::R8snorm | GpuTextureFormat::Rg8snorm | GpuTextureFormat::Rgba8snorm |
+            GpuTextureFormat::Rgb9e5ufloat |
+            GpuTextureFormat::R32uint | GpuTextureFormat::R32sint |
+            GpuTextureFormat::Rg32uint | GpuTextureFormat::Rg32sint | GpuTextureFormat::Rg32float |
+            GpuTextureFormat::Rgba32uint | GpuTextureFormat::Rgba32sint | GpuTextureFormat::Rgba32float => false,
+            _ => true,
+        });
+        if multisampled && sample_count >= 4 { 4 } else { 1 }
+    }
+
+    // the temporal antialiasing replaces the multisampling
+    pub fn set_antialiasing(&self, settings: &AntialiasingSettings) {
+        let sample_count = if settings.temporal {
+            1
+        } else {
+            Self::supported_sample_count(&MULTISAMPLED_FORMATS, settings.sample_count, |v| self.device.has_feature(v))
+        };
+        let Ok(mut mutex) = self.sample_count.lock() else { return };
+        *mutex = sample_count;
     }
 
-    pub fn depth_texture(&self) -> &GpuTexture {
-        &self.depth_texture
+    // moves the jitter along the Halton sequence and keeps the projection of the previous frame
+    pub fn begin_frame(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>, temporal: bool) {
+        let projection_view = self.unjittered_projection_matrix(surface_configuration, scene_context) *
+            self.view_matrix(surface_configuration, scene_context);
+        let Ok(mut frame) = self.frame.lock() else { return };
+        let frame_index = frame.map(|v| v.frame_index.wrapping_add(1)).unwrap_or(0);
+        // the projection only moves for the temporal antialiasing
+        let jitter = temporal.then(|| halton_jitter(frame_index, surface_configuration.width() as u32, surface_configuration.height() as u32));
+        *frame = Some(StageFrame {
+            temporal,
+            frame_index,
+            jitter,
+            projection_view,
+            previous_projection_view: frame.map(|v| v.projection_view).unwrap_or(projection_view),
+        });
+    }
+
+    fn frame(&self) -> Option<StageFrame> {
+        let Ok(frame) = self.frame.lock() else { return None };
+        *frame
+    }
+
+    pub fn temporal(&self) -> bool {
+        self.frame().is_some_and(|v| v.temporal)
+    }
+
+    // zero without the temporal antialiasing
+    pub fn jitter(&self) -> glm::Vec2 {
+        self.frame().and_then(|v| v.jitter).unwrap_or_else(glm::Vec2::zeros)
+    }
+
+    pub fn previous_projection_view_matrix(&self) -> Option<glm::Mat4> {
+        self.frame().map(|v| v.previous_projection_view)
     }
 
     pub fn sample_count(&self) -> u32 {
-        self.sample_count
+        let Ok(sample_count) = self.sample_count.lock() else { return 1 };
+        *sample_count
     }
-    
+
+    // declares the attachments of the frame sized like the surface. without multisampling the
+    // color goes straight to the HDR target.
+    pub fn targets(&self, graph: &mut RenderGraph, surface_configuration: &SurfaceConfiguration, swapchain: TextureHandle) -> StageTargets {
+        let sample_count = self.sample_count();
+        let width = surface_configuration.width() as u32;
+        let height = surface_configuration.height() as u32;
+        let depth = graph.create_texture("depth",
+            TextureDescription::new(GpuTextureFormat::Depth24plus, width, height).with_sample_count(sample_count));
+        let velocity = graph.create_texture("velocity",
+            TextureDescription::new(VELOCITY_FORMAT, width, height).with_sample_count(sample_count));
+        let hdr = graph.create_texture("hdr", TextureDescription::new(HDR_FORMAT, width, height));
+        let antialiased = if self.temporal() {
+            graph.create_texture("antialiased", TextureDescription::new(HDR_FORMAT, width, height))
+        } else {
+            hdr
+        };
+        // the prepass pipelines draw in the shadow map format like the shadow pipelines
+        let prepass_depth = graph.create_texture("prepass depth", TextureDescription::new(SHADOW_MAP_FORMAT, width, height));
+        if sample_count == 1 {
+            return StageTargets {
+                color: hdr,
+                resolve_target: None,
+                depth,
+                velocity,
+                hdr,
+                antialiased,
+                prepass_depth,
+                swapchain,
+            }
+        }
+        let color = graph.create_texture("color",
+            TextureDescription::new(HDR_FORMAT, width, height).with_sample_count(sample_count));
+        StageTargets {
+            color,
+            resolve_target: Some(hdr),
+            depth,
+            velocity,
+            hdr,
+            antialiased,
+            prepass_depth,
+            swapchain,
+        }
+    }
+
     fn view_matrix(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>) -> glm::Mat4 {
+        if let Some((view_matrix, _)) = scene_context.camera_matrices(Self::aspect_ratio(surface_configuration)) {
+            return view_matrix
+        }
         let model = scene_context.model();
         let center: glm::Vec3;
         let eye: glm::Vec3;
@@ -484,15 +1160,67 @@ impl RenderStage {
         view_matrix
     }
 
-    pub fn projection_view_matrix(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>) -> glm::Mat4 {
-        let aspect = (surface_configuration.width() as f64 / surface_configuration.height() as f64) as f32;
+    fn aspect_ratio(surface_configuration: &SurfaceConfiguration) -> f32 {
+        (surface_configuration.width() as f64 / surface_configuration.height() as f64) as f32
+    }
+
+    // shifted by the jitter of the frame
+    pub fn projection_matrix(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>) -> glm::Mat4 {
+        let projection_matrix = self.unjittered_projection_matrix(surface_configuration, scene_context);
+        match self.frame().and_then(|v| v.jitter) {
+            Some(jitter) => glm::translation(&glm::vec3(jitter.x, jitter.y, 0.0)) * projection_matrix,
+            None => projection_matrix,
+        }
+    }
+
+    pub fn unjittered_projection_matrix(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>) -> glm::Mat4 {
+        let aspect = Self::aspect_ratio(surface_configuration);
+        // authored camera
+        if let Some((_, projection_matrix)) = scene_context.camera_matrices(aspect) {
+            return projection_matrix
+        }
         let fovy: f32 = 90.0;
         let fovy = fovy.to_radians();
-        let projection_matrix = glm::perspective(aspect, fovy, 0.001, 100.0);
+        glm::perspective(aspect, fovy, 0.001, 100.0)
+    }
+
+    pub fn projection_view_matrix(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>) -> glm::Mat4 {
+        let projection_matrix = self.projection_matrix(surface_configuration, scene_context);
         let view_matrix = self.view_matrix(surface_configuration, scene_context);
         projection_matrix * view_matrix
     }
 
+    // picks the surface under a pending click as the focus point, a miss falls back to the
+    // bounding box center
+    pub fn pick_focus_point(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>) {
+        let Some(position) = scene_context.take_focus_pick() else { return };
+        let projection_view = self.unjittered_projection_matrix(surface_configuration, scene_context) *
+            self.view_matrix(surface_configuration, scene_context);
+        let Some(inverse_projection_view) = projection_view.try_inverse() else { return };
+        // two points along the ray through the pixel, in front of the camera for any depth range
+        let unproject = |z: f32| {
+            let p = inverse_projection_view * glm::vec4(position.x * 2.0 - 1.0, 1.0 - position.y * 2.0, z, 1.0);
+            p.xyz() / p.w
+        };
+        let origin = unproject(0.0);
+        let direction = glm::normalize(&(unproject(0.5) - origin));
+        scene_context.set_focus_point(scene_context.pick_surface(&origin, &direction));
+    }
+
+    // distance from the camera plane to the point the lens focuses on
+    pub fn focus_distance(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>, focus: Focus) -> f32 {
+        let center = scene_context.model().bounding_box()
+            .map(|v| (v.max() + v.min()) * 0.5)
+            .unwrap_or_else(glm::Vec3::zeros);
+        let point = match focus {
+            Focus::Distance(distance) => return distance,
+            Focus::BoundingBoxCenter => center,
+            Focus::PickedPoint => scene_context.focus_point().unwrap_or(center),
+        };
+        let view_position = self.view_matrix(surface_configuration, scene_context) * glm::vec4(point.x, point.y, point.z, 1.0);
+        -view_position.z
+    }
+
     fn camera_position(&self, surface_configuration: &SurfaceConfiguration, scene_context: &Arc<SceneContext>) -> glm::Vec4 {
         let view_matrix = self.view_matrix(surface_configuration, scene_context);
         let inverse_view_matrix = view_matrix.try_inverse().unwrap_or_else(|| glm::identity());
@@ -500,8 +1228,210 @@ impl RenderStage {
     }
 }
 
+// sub-pixel offset of the frame in normalized device coordinates. eight offsets cover the
+// pixel evenly.
+fn halton_jitter(frame_index: u32, width: u32, height: u32) -> glm::Vec2 {
+    let sequence_index = frame_index % 8 + 1;
+    glm::vec2(
+        (halton(sequence_index, 2) - 0.5) * 2.0 / width.max(1) as f32,
+        (halton(sequence_index, 3) - 0.5) * 2.0 / height.max(1) as f32)
+}
+
+// radical inverse of the index in the base
+fn halton(index: u32, base: u32) -> f32 {
+    let mut index = index;
+    let mut fraction = 1.0;
+    let mut result = 0.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
 #[repr(C)]
+#[derive(Clone, Copy)]
 struct UniformData {
     pub projection_view_matrix: glm::Mat4,
     pub camera_position: glm::Vec4,
+    // specular mip count, intensity, available, rotation around +Y like the skybox
+    pub environment: glm::Vec4,
+    pub shadow_projection_view_matrices: [glm::Mat4; MAX_CASCADE_COUNT],
+    // casting light index or -1, depth bias, normal bias and filter radius in texels
+    pub shadow: glm::Vec4,
+    // view depths where the cascades end
+    pub shadow_splits: glm::Vec4,
+    pub shadow_texel_sizes: glm::Vec4,
+    // cascade count, blend fraction, debug view, view depth where the first cascade starts
+    pub shadow_cascades: glm::Vec4,
+    pub camera_depth: glm::Vec4,
+    // ground center x and z, height, half extent
+    pub ground: glm::Vec4,
+    // unjittered, for the velocity
+    pub previous_projection_view_matrix: glm::Mat4,
+    // sub-pixel offset of the projection in normalized device coordinates
+    pub jitter: glm::Vec4,
+}
+
+// variant of the pipelines a primitive draws with
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    topology: Topology,
+    deformed: bool,
+    // masked materials, with alpha-to-coverage in the stage and an alpha test in the prepass
+    alpha_to_coverage: bool,
+    // back faces are culled unless the material is double-sided, points and lines never are
+    double_sided: bool,
+    blend: bool,
+}
+
+impl PipelineKey {
+    // culled, opaque and without alpha-to-coverage like the depth only pipelines
+    fn new(topology: Topology, deformed: bool) -> Self {
+        Self {
+            topology,
+            deformed,
+            alpha_to_coverage: false,
+            double_sided: false,
+            blend: false,
+        }
+    }
+}
+
+// pipelines drawing into the attachments of the stage
+struct StagePipelines {
+    sample_count: u32,
+    meshes: HashMap<PipelineKey, GpuRenderPipeline>,
+    ground: GpuRenderPipeline,
+}
+
+// buffers and bind groups drawing the nodes of the model
+struct NodeDrawer {
+    model: Arc<Model>,
+    mesh_buffers: HashMap<usize, Arc<MeshBuffer>>,
+    secondary_bind_groups: Vec<GpuBindGroup>,
+    deformations: HashMap<usize, Deformation>,
+    material_buffers: HashMap<Option<usize>, Arc<MaterialBuffer>>,
+}
+
+impl NodeDrawer {
+    // nodes with the pipelines of their primitives. with materials the blended primitives are
+    // drawn after every opaque one.
+    fn draw_nodes(&self, render_pass_encoder: &GpuRenderPassEncoder, pipelines: &HashMap<PipelineKey, GpuRenderPipeline>, materials: bool) {
+        if materials {
+            self.draw_primitives(render_pass_encoder, pipelines, Some(false));
+            self.draw_primitives(render_pass_encoder, pipelines, Some(true));
+        } else {
+            self.draw_primitives(render_pass_encoder, pipelines, None);
+        }
+    }
+
+    // the primitives of the blend mode with their materials, or every primitive without them
+    fn draw_primitives(&self, render_pass_encoder: &GpuRenderPassEncoder, pipelines: &HashMap<PipelineKey, GpuRenderPipeline>, blend: Option<bool>) {
+        let materials = blend.is_some();
+        let nodes = self.model.nodes();
+        for (local_index, node) in nodes.iter().enumerate() {
+            let Some(mesh_index) = node.mesh_index() else { continue };
+            let Some(mesh_buffer) = self.mesh_buffers.get(&mesh_index) else { continue };
+            let Some(secondary_bind_group) = self.secondary_bind_groups.get(local_index) else { continue };
+            render_pass_encoder.set_bind_group(1, Some(secondary_bind_group));
+            render_pass_encoder.set_vertex_buffer(0, Some(mesh_buffer.position_buffer()));
+            render_pass_encoder.set_vertex_buffer(1, Some(mesh_buffer.normal_buffer()));
+            render_pass_encoder.set_vertex_buffer(2, Some(mesh_buffer.tex_coord_0_buffer()));
+            render_pass_encoder.set_vertex_buffer(3, Some(mesh_buffer.tex_coord_1_buffer()));
+            render_pass_encoder.set_vertex_buffer(4, Some(mesh_buffer.tangent_buffer()));
+            render_pass_encoder.set_vertex_buffer(5, Some(mesh_buffer.color_buffer()));
+            render_pass_encoder.set_index_buffer(mesh_buffer.index_buffer(), GpuIndexFormat::Uint32);
+            // skinned and morphed meshes deform with the joint matrices and weights of the node
+            let deformation = self.deformations.get(&node.node_index());
+            if let Some(bind_group) = deformation.and_then(|v| v.bind_group.as_ref()) {
+                render_pass_encoder.set_bind_group(3, Some(bind_group));
+                render_pass_encoder.set_vertex_buffer(6, Some(mesh_buffer.joint_buffer()));
+                render_pass_encoder.set_vertex_buffer(7, Some(mesh_buffer.weight_buffer()));
+            }
+            let deformed = deformation.is_some();
+            // primitives
+            for primitive in mesh_buffer.mesh().primitives() {
+                let material = primitive.material();
+                let primitive_blend = material.alpha_mode() == AlphaMode::Blend;
+                if blend.is_some_and(|v| v != primitive_blend) {
+                    continue
+                }
+                let key = if materials {
+                    PipelineKey {
+                        topology: primitive.topology(),
+                        deformed,
+                        alpha_to_coverage: material.alpha_mode() == AlphaMode::Mask,
+                        double_sided: material.double_sided() || primitive.topology() != Topology::TriangleList,
+                        blend: primitive_blend,
+                    }
+                } else {
+                    PipelineKey::new(primitive.topology(), deformed)
+                };
+                // masks fall back to discarding without alpha-to-coverage pipelines
+                let render_pipeline = pipelines.get(&key)
+                    .or_else(|| pipelines.get(&PipelineKey { alpha_to_coverage: false, ..key }));
+                let Some(render_pipeline) = render_pipeline else { continue };
+                render_pass_encoder.set_pipeline(render_pipeline);
+                if materials {
+                    let Some(material_buffer) = self.material_buffers.get(&primitive.material_index()) else { continue };
+                    render_pass_encoder.set_bind_group(2, Some(material_buffer.bind_group()));
+                }
+                render_pass_encoder.draw_indexed_with_instance_count_and_first_index(
+                    primitive.index_count(), 1, primitive.first_index());
+            }
+        }
+    }
+}
+
+// per node bindings of the deformed pipelines
+struct Deformation {
+    node_index: usize,
+    skinned: bool,
+    joint_count: usize,
+    morph_target_count: usize,
+    joint_offset: usize,
+    weight_offset: usize,
+    bind_group: Option<GpuBindGroup>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_counts_round_down_to_the_guaranteed_counts() {
+        let sample_counts: Vec<u32> = [0, 1, 2, 4, 8, 16].into_iter()
+            .map(|v| RenderStage::supported_sample_count(&MULTISAMPLED_FORMATS, v, |_| false))
+            .collect();
+        assert_eq!(sample_counts, vec![1, 1, 1, 4, 4, 4]);
+    }
+
+    #[test]
+    fn formats_without_multisampling_take_one_sample() {
+        let formats = [HDR_FORMAT, GpuTextureFormat::Rgba32float];
+        assert_eq!(RenderStage::supported_sample_count(&formats, 4, |_| true), 1);
+    }
+
+    #[test]
+    fn feature_gated_formats_follow_the_device() {
+        let formats = [GpuTextureFormat::Rg11b10ufloat];
+        assert_eq!(RenderStage::supported_sample_count(&formats, 4, |_| false), 1);
+        assert_eq!(RenderStage::supported_sample_count(&formats, 4, |v| v == "rg11b10ufloat-renderable"), 4);
+    }
+
+    #[test]
+    fn jitter_stays_within_a_pixel_and_repeats_every_eight_frames() {
+        let (width, height) = (200, 100);
+        let jitters: Vec<glm::Vec2> = (0..16).map(|v| halton_jitter(v, width, height)).collect();
+        for jitter in &jitters {
+            assert!(jitter.x.abs() <= 1.0 / width as f32 && jitter.y.abs() <= 1.0 / height as f32);
+        }
+        assert_eq!(jitters[..8], jitters[8..]);
+        // no two frames of a cycle share an offset
+        for (index, jitter) in jitters[..8].iter().enumerate() {
+            assert!(jitters[index + 1..8].iter().all(|v| v != jitter));
+        }
+    }
 }
@@ -6,17 +6,34 @@ use std::sync::{Arc, Mutex};
 use crate::{
     Model,
 };
+use crate::asset::{BoundingBox, Topology};
+use crate::environment::Environment;
+use crate::skybox::{Cubemap, SkyboxMode, SkyboxSettings, Sun};
+use crate::shadow::ShadowSettings;
+use crate::antialiasing::AntialiasingSettings;
+use crate::post_process::PostProcessSettings;
+use crate::ambient_occlusion::AmbientOcclusionSettings;
+use crate::depth_of_field::DepthOfFieldSettings;
+use crate::graph::SceneGraph;
+use crate::animator::Animator;
 
 pub struct SceneContext {
     state: Mutex<SceneState>,
     model: Arc<Model>,
+    environment: Option<Arc<Environment>>,
+    cubemap: Option<Arc<Cubemap>>,
 }
 
 impl SceneContext {
-    pub fn new(model: &Arc<Model>) -> Arc<SceneContext> {
+    pub fn new(model: &Arc<Model>,
+        environment: Option<&Arc<Environment>>,
+        cubemap: Option<&Arc<Cubemap>>,
+        skybox_settings: SkyboxSettings) -> Arc<SceneContext> {
         let this = Self {
-            state: Mutex::new(SceneState::new()),
+            state: Mutex::new(SceneState::new(model, skybox_settings)),
             model: Arc::clone(model),
+            environment: environment.map(Arc::clone),
+            cubemap: cubemap.map(Arc::clone),
         };
         Arc::new(this)
     }
@@ -31,19 +48,359 @@ impl SceneContext {
         state.view_quat()
     }
 
+    // glTF indices of the scene graph nodes, parents before their children
+    pub fn node_indices(&self) -> Vec<usize> {
+        let Ok(state) = self.state.lock() else { return vec![] };
+        state.scene_graph.node_indices()
+    }
+
+    pub fn node_name(&self, node_index: usize) -> Option<String> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.scene_graph.name(node_index).map(Into::into)
+    }
+
+    pub fn node_parent_index(&self, node_index: usize) -> Option<usize> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.scene_graph.parent_index(node_index)
+    }
+
+    pub fn node_children_indices(&self, node_index: usize) -> Vec<usize> {
+        let Ok(state) = self.state.lock() else { return vec![] };
+        state.scene_graph.children_indices(node_index)
+    }
+
+    pub fn node_translation(&self, node_index: usize) -> Option<glm::Vec3> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.scene_graph.translation(node_index)
+    }
+
+    pub fn set_node_translation(&self, node_index: usize, translation: glm::Vec3) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.scene_graph.set_translation(node_index, translation);
+    }
+
+    pub fn node_rotation(&self, node_index: usize) -> Option<glm::Quat> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.scene_graph.rotation(node_index)
+    }
+
+    pub fn set_node_rotation(&self, node_index: usize, rotation: glm::Quat) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.scene_graph.set_rotation(node_index, rotation);
+    }
+
+    pub fn node_scale(&self, node_index: usize) -> Option<glm::Vec3> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.scene_graph.scale(node_index)
+    }
+
+    pub fn set_node_scale(&self, node_index: usize, scale: glm::Vec3) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.scene_graph.set_scale(node_index, scale);
+    }
+
+    pub fn set_node_weights(&self, node_index: usize, weights: Vec<f32>) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.scene_graph.set_weights(node_index, weights);
+    }
+
+    // morph target weights of the node, empty when its mesh has no targets
+    pub fn node_weights(&self, node_index: usize) -> Vec<f32> {
+        let Ok(state) = self.state.lock() else { return vec![] };
+        state.scene_graph.weights(node_index).cloned().unwrap_or_default()
+    }
+
+    pub fn world_transform(&self, node_index: usize) -> Option<glm::Mat4> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.scene_graph.world_transform(node_index)
+    }
+
+    // recomputes dirty world transforms and returns the ones waiting for the upload
+    pub fn take_changed_transforms(&self) -> Vec<(usize, glm::Mat4)> {
+        let Ok(mut state) = self.state.lock() else { return vec![] };
+        state.scene_graph.update();
+        state.scene_graph.take_changed_transforms()
+    }
+
+    // joint matrices of the skin attached to the node, empty when the node has none
+    pub fn joint_matrices(&self, node_index: usize) -> Vec<glm::Mat4> {
+        let skin = self.model.nodes().iter()
+            .find(|v| v.node_index() == node_index)
+            .and_then(|v| v.skin_index())
+            .and_then(|v| self.model.skins().get(v));
+        let Some(skin) = skin else { return vec![] };
+        let Ok(state) = self.state.lock() else { return vec![] };
+        state.scene_graph.joint_matrices(node_index, skin)
+    }
+
+    pub fn camera_names(&self) -> Vec<String> {
+        self.model.cameras().iter()
+            .map(|v| v.name()
+                .map(Into::into)
+                .unwrap_or_else(|| format!("camera {}", v.camera_index())))
+            .collect()
+    }
+
+    pub fn camera_index(&self) -> Option<usize> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.camera_index
+    }
+
+    // none falls back to the orbiting view around the model
+    pub fn select_camera(&self, camera_index: Option<usize>) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.camera_index = camera_index.filter(|&v| v < self.model.cameras().len());
+    }
+
+    // view and projection matrices of the selected camera
+    pub fn camera_matrices(&self, viewport_aspect_ratio: f32) -> Option<(glm::Mat4, glm::Mat4)> {
+        let Ok(state) = self.state.lock() else { return None };
+        let camera = self.model.cameras().get(state.camera_index?)?;
+        let world_transform = state.scene_graph.world_transform(camera.node_index())?;
+        let view_matrix = camera.view_matrix(&world_transform);
+        let projection_matrix = camera.projection_matrix(viewport_aspect_ratio);
+        Some((view_matrix, projection_matrix))
+    }
+
+    pub fn animation_clip_names(&self) -> Vec<String> {
+        let Ok(state) = self.state.lock() else { return vec![] };
+        state.animator.clip_names()
+    }
+
+    pub fn animation_clip_index(&self) -> Option<usize> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.animator.clip_index()
+    }
+
+    pub fn play_animation_clip(&self, clip_index: Option<usize>) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.animator.select_clip(clip_index);
+        state.animator.set_playing(true);
+    }
+
+    pub fn is_animation_playing(&self) -> bool {
+        let Ok(state) = self.state.lock() else { return false };
+        state.animator.is_playing()
+    }
+
+    pub fn set_animation_playing(&self, playing: bool) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.animator.set_playing(playing);
+    }
+
+    pub fn is_animation_looping(&self) -> bool {
+        let Ok(state) = self.state.lock() else { return false };
+        state.animator.is_looping()
+    }
+
+    pub fn set_animation_looping(&self, looping: bool) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.animator.set_looping(looping);
+    }
+
+    pub fn skybox_settings(&self) -> SkyboxSettings {
+        let Ok(state) = self.state.lock() else { return SkyboxSettings::default() };
+        state.skybox_settings
+    }
+
+    pub fn set_skybox_mode(&self, mode: SkyboxMode) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.skybox_settings.mode = mode;
+    }
+
+    // radians around +Y
+    pub fn set_skybox_rotation(&self, rotation: f32) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.skybox_settings.rotation = rotation;
+    }
+
+    pub fn set_skybox_intensity(&self, intensity: f32) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.skybox_settings.intensity = intensity.max(0.0);
+    }
+
+    pub fn set_sun(&self, sun: Sun) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.skybox_settings.sun = sun;
+    }
+
+    // moves the sun from the east at 6:00 over the south to the west at 18:00
+    pub fn set_time_of_day(&self, hours: f32) {
+        let Ok(mut state) = self.state.lock() else { return };
+        let day_angle = (hours - 6.0) / 12.0 * glm::pi::<f32>();
+        let sun = &mut state.skybox_settings.sun;
+        sun.elevation = day_angle.sin() * 1.2;
+        sun.azimuth = glm::half_pi::<f32>() - day_angle;
+    }
+
+    pub fn shadow_settings(&self) -> ShadowSettings {
+        let Ok(state) = self.state.lock() else { return ShadowSettings::default() };
+        state.shadow_settings
+    }
+
+    pub fn set_shadow_settings(&self, shadow_settings: ShadowSettings) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.shadow_settings = shadow_settings;
+    }
+
+    pub fn antialiasing_settings(&self) -> AntialiasingSettings {
+        let Ok(state) = self.state.lock() else { return AntialiasingSettings::default() };
+        state.antialiasing_settings
+    }
+
+    pub fn set_antialiasing_settings(&self, antialiasing_settings: AntialiasingSettings) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.antialiasing_settings = antialiasing_settings;
+    }
+
+    pub fn post_process_settings(&self) -> PostProcessSettings {
+        let Ok(state) = self.state.lock() else { return PostProcessSettings::default() };
+        state.post_process_settings
+    }
+
+    pub fn set_post_process_settings(&self, post_process_settings: PostProcessSettings) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.post_process_settings = post_process_settings;
+    }
+
+    pub fn ambient_occlusion_settings(&self) -> AmbientOcclusionSettings {
+        let Ok(state) = self.state.lock() else { return AmbientOcclusionSettings::default() };
+        state.ambient_occlusion_settings
+    }
+
+    pub fn set_ambient_occlusion_settings(&self, ambient_occlusion_settings: AmbientOcclusionSettings) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.ambient_occlusion_settings = ambient_occlusion_settings;
+    }
+
+    pub fn depth_of_field_settings(&self) -> DepthOfFieldSettings {
+        let Ok(state) = self.state.lock() else { return DepthOfFieldSettings::default() };
+        state.depth_of_field_settings
+    }
+
+    pub fn set_depth_of_field_settings(&self, depth_of_field_settings: DepthOfFieldSettings) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.depth_of_field_settings = depth_of_field_settings;
+    }
+
+    // canvas position in texture coordinates, picked by the renderer on the next frame
+    pub fn request_focus_pick(&self, position: glm::Vec2) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.focus_pick = Some(position);
+    }
+
+    pub fn take_focus_pick(&self) -> Option<glm::Vec2> {
+        let Ok(mut state) = self.state.lock() else { return None };
+        state.focus_pick.take()
+    }
+
+    // world position the depth of field focuses on, none for the bounding box center
+    pub fn focus_point(&self) -> Option<glm::Vec3> {
+        let Ok(state) = self.state.lock() else { return None };
+        state.focus_point
+    }
+
+    pub fn set_focus_point(&self, focus_point: Option<glm::Vec3>) {
+        let Ok(mut state) = self.state.lock() else { return };
+        state.focus_point = focus_point;
+    }
+
+    // nearest world position where the ray hits a triangle of the meshes
+    pub fn pick_surface(&self, origin: &glm::Vec3, direction: &glm::Vec3) -> Option<glm::Vec3> {
+        let mut nearest: Option<f32> = None;
+        for node in self.model.nodes().iter() {
+            let Some(mesh) = node.mesh_index().and_then(|v| self.model.meshes().get(v)) else { continue };
+            let Some(world_transform) = self.world_transform(node.node_index()) else { continue };
+            // the direction keeps its scale, so distances along it match in both spaces
+            let inverse = glm::inverse(&world_transform);
+            let local_origin = (inverse * glm::vec4(origin.x, origin.y, origin.z, 1.0)).xyz();
+            let local_direction = (inverse * glm::vec4(direction.x, direction.y, direction.z, 0.0)).xyz();
+            // skinned meshes in their current pose, morph targets stay undeformed
+            let joint_matrices = self.joint_matrices(node.node_index());
+            let skinned_positions = if mesh.skinned() && !joint_matrices.is_empty() {
+                Some(mesh.skinned_positions(&joint_matrices))
+            } else {
+                None
+            };
+            let positions = skinned_positions.as_ref().unwrap_or(mesh.positions());
+            let indices = mesh.indices();
+            let vertex = |index: u32| {
+                let i = index as usize * 3;
+                glm::vec3(positions[i], positions[i + 1], positions[i + 2])
+            };
+            for primitive in mesh.primitives().iter() {
+                if primitive.topology() != Topology::TriangleList {
+                    continue;
+                }
+                // the bounding box only holds the bind pose
+                if skinned_positions.is_none() {
+                    let Some(t) = intersect_bounding_box(&local_origin, &local_direction, primitive.bounding_box()) else { continue };
+                    if nearest.is_some_and(|v| v < t) {
+                        continue;
+                    }
+                }
+                let first = primitive.first_index() as usize;
+                let last = first + primitive.index_count() as usize;
+                for triangle in indices[first..last].chunks_exact(3) {
+                    let p0 = vertex(triangle[0]);
+                    let p1 = vertex(triangle[1]);
+                    let p2 = vertex(triangle[2]);
+                    let Some(t) = intersect_triangle(&local_origin, &local_direction, &p0, &p1, &p2) else { continue };
+                    if nearest.is_none_or(|v| t < v) {
+                        nearest = Some(t);
+                    }
+                }
+            }
+        }
+        nearest.map(|t| origin + direction * t)
+    }
+
     pub fn model(&self) -> &Arc<Model> {
         &self.model
     }
+
+    // image-based lighting source, absent when the preset has none
+    pub fn environment(&self) -> Option<&Arc<Environment>> {
+        self.environment.as_ref()
+    }
+
+    // six skybox faces, absent when the preset has none
+    pub fn cubemap(&self) -> Option<&Arc<Cubemap>> {
+        self.cubemap.as_ref()
+    }
 }
 
 struct SceneState {
     view_quat: glm::Quat,
+    scene_graph: SceneGraph,
+    animator: Animator,
+    camera_index: Option<usize>,
+    skybox_settings: SkyboxSettings,
+    shadow_settings: ShadowSettings,
+    antialiasing_settings: AntialiasingSettings,
+    post_process_settings: PostProcessSettings,
+    ambient_occlusion_settings: AmbientOcclusionSettings,
+    depth_of_field_settings: DepthOfFieldSettings,
+    focus_pick: Option<glm::Vec2>,
+    focus_point: Option<glm::Vec3>,
 }
 
 impl SceneState {
-    pub fn new() -> Self {
+    pub fn new(model: &Model, skybox_settings: SkyboxSettings) -> Self {
         let this = Self {
             view_quat: glm::quat_identity(),
+            scene_graph: SceneGraph::new(model),
+            animator: Animator::new(model),
+            // the first authored camera when the model has one
+            camera_index: if model.cameras().is_empty() { None } else { Some(0) },
+            skybox_settings,
+            shadow_settings: ShadowSettings::default(),
+            antialiasing_settings: AntialiasingSettings::default(),
+            post_process_settings: PostProcessSettings::default(),
+            ambient_occlusion_settings: AmbientOcclusionSettings::default(),
+            depth_of_field_settings: DepthOfFieldSettings::default(),
+            focus_pick: None,
+            focus_point: None,
         };
         this
     }
@@ -55,5 +412,44 @@ impl SceneState {
     pub fn forward_frame(&mut self, delta_time: f32) {
         let rotation_y = glm::quat_angle_axis(delta_time * glm::pi::<f32>() * 0.32, &glm::vec3(0.0, 1.0, 0.0));
         self.view_quat *= rotation_y;
+        self.animator.forward_frame(delta_time, &mut self.scene_graph);
+    }
+}
+
+// distance where the ray enters the box, zero when it starts inside
+fn intersect_bounding_box(origin: &glm::Vec3, direction: &glm::Vec3, bounding_box: &BoundingBox) -> Option<f32> {
+    let mut near = 0.0f32;
+    let mut far = f32::INFINITY;
+    for i in 0..3 {
+        let inverse = 1.0 / direction[i];
+        let t0 = (bounding_box.min()[i] - origin[i]) * inverse;
+        let t1 = (bounding_box.max()[i] - origin[i]) * inverse;
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+    if near <= far { Some(near) } else { None }
+}
+
+// Fast, Minimum Storage Ray/Triangle Intersection (Möller and Trumbore 1997), both sides
+fn intersect_triangle(origin: &glm::Vec3, direction: &glm::Vec3, p0: &glm::Vec3, p1: &glm::Vec3, p2: &glm::Vec3) -> Option<f32> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let p = glm::cross(direction, &edge2);
+    let determinant = glm::dot(&edge1, &p);
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+    let inverse = 1.0 / determinant;
+    let s = origin - p0;
+    let u = glm::dot(&s, &p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = glm::cross(&s, &edge1);
+    let v = glm::dot(direction, &q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
     }
+    let t = glm::dot(&edge2, &q) * inverse;
+    if t > 0.0 { Some(t) } else { None }
 }
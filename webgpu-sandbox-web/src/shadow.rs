@@ -0,0 +1,44 @@
+
+// shadow of the first directional light, or of the sun under the procedural sky
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    // width and height of the square shadow map of each cascade
+    pub resolution: u32,
+    // subtracted from the light space depth before the comparison
+    pub depth_bias: f32,
+    // offset along the surface normal in shadow map texels
+    pub normal_bias: f32,
+    // percentage-closer filtering kernel radius in texels, zero for a single tap
+    pub filter_radius: u32,
+    // plane under the model receiving the shadow
+    pub ground_plane: bool,
+    // slices of the view frustum, from one to four
+    pub cascade_count: usize,
+    // blends the logarithmic (1.0) and uniform (0.0) split distances
+    pub split_lambda: f32,
+    // part of each slice fading into the next cascade
+    pub cascade_blend: f32,
+    // view depth beyond which nothing is shadowed
+    pub max_distance: f32,
+    // tints the surfaces with the color of their cascade
+    pub debug_cascades: bool,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            resolution: 2048,
+            depth_bias: 0.002,
+            normal_bias: 1.0,
+            filter_radius: 1,
+            ground_plane: false,
+            cascade_count: 4,
+            split_lambda: 0.75,
+            cascade_blend: 0.1,
+            max_distance: 100.0,
+            debug_cascades: false,
+        }
+    }
+}
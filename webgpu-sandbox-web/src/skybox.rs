@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{fetch::fetch, console_log};
+
+use nalgebra_glm as glm;
+
+// what the skybox draws behind the meshes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkyboxMode {
+    Gradient,
+    Environment,
+    Cubemap,
+    // Preetham daylight lit by the sun
+    Procedural,
+}
+
+// rotation in radians around +Y, intensity scales the linear sky color. the sun lights the
+// meshes under the procedural sky, and in every mode as the key light when key_light is set
+// and the model has no directional light of its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SkyboxSettings {
+    pub mode: SkyboxMode,
+    pub rotation: f32,
+    pub intensity: f32,
+    pub sun: Sun,
+    pub key_light: bool,
+}
+
+impl Default for SkyboxSettings {
+    fn default() -> Self {
+        Self {
+            mode: SkyboxMode::Gradient,
+            rotation: 0.0,
+            intensity: 1.0,
+            sun: Sun::default(),
+            key_light: false,
+        }
+    }
+}
+
+// sun of the procedural sky. azimuth is measured from +Z towards +X and elevation from the
+// horizon, both in radians. turbidity ranges from 2 (clear) to 10 (hazy).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sun {
+    pub azimuth: f32,
+    pub elevation: f32,
+    pub turbidity: f32,
+    pub intensity: f32,
+}
+
+impl Default for Sun {
+    fn default() -> Self {
+        Self {
+            azimuth: std::f32::consts::FRAC_PI_4,
+            elevation: 0.6,
+            turbidity: 2.5,
+            intensity: 3.0,
+        }
+    }
+}
+
+impl Sun {
+    // unit vector towards the sun
+    pub fn direction(&self) -> glm::Vec3 {
+        let (sin_elevation, cos_elevation) = self.elevation.sin_cos();
+        let (sin_azimuth, cos_azimuth) = self.azimuth.sin_cos();
+        glm::vec3(cos_elevation * sin_azimuth, sin_elevation, cos_elevation * cos_azimuth)
+    }
+
+    // sunlight reaching the ground after Rayleigh and aerosol extinction along the
+    // Kasten-Young air mass, fading out as the sun sets
+    pub fn radiance(&self) -> glm::Vec3 {
+        let zenith_degrees = 90.0 - self.elevation.to_degrees().clamp(0.0, 90.0);
+        let air_mass = 1.0 / (zenith_degrees.to_radians().cos() + 0.50572 * (96.07995 - zenith_degrees).powf(-1.6364));
+        // red, green and blue at 680, 550 and 440 nm
+        let rayleigh = glm::vec3(0.041, 0.097, 0.236);
+        let angstrom_beta = (0.04608 * self.turbidity - 0.04586).max(0.0);
+        let aerosol = glm::vec3(0.68f32.powf(-1.3), 0.55f32.powf(-1.3), 0.44f32.powf(-1.3)) * angstrom_beta;
+        let optical_depth = (rayleigh + aerosol) * air_mass;
+        let transmittance = optical_depth.map(|v| (-v).exp());
+        let horizon = glm::smoothstep(-0.02, 0.05, self.elevation);
+        transmittance * self.intensity * horizon
+    }
+}
+
+// faces are ordered +X, -X, +Y, -Y, +Z, -Z like the cube texture layers
+#[derive(Debug, Clone)]
+pub enum CubemapSource {
+    Faces([String; 6]),
+    // horizontal (4x3) or vertical (3x4) cross
+    Cross(String),
+}
+
+// six square RGBA8 sRGB faces of the same size
+pub struct Cubemap {
+    size: u32,
+    faces: Vec<Vec<u8>>,
+}
+
+impl Cubemap {
+    // none when an image is missing, undecodable or the faces do not match
+    pub async fn fetch(source: &CubemapSource) -> Result<Option<Arc<Self>>, JsValue> {
+        let this = match source {
+            CubemapSource::Faces(urls) => {
+                let mut images = vec![];
+                for url in urls {
+                    images.push(Self::decode(&fetch(url).await?));
+                }
+                images.into_iter().collect::<Option<Vec<_>>>()
+                    .and_then(Self::with_faces)
+            }
+            CubemapSource::Cross(url) => Self::decode(&fetch(url).await?)
+                .and_then(Self::with_cross),
+        };
+        if this.is_none() {
+            console_log!("cubemap unavailable: {:?}", source);
+        }
+        Ok(this)
+    }
+
+    fn decode(data: &[u8]) -> Option<image::RgbaImage> {
+        image::load_from_memory(data).ok().map(|v| v.to_rgba8())
+    }
+
+    fn with_faces(images: Vec<image::RgbaImage>) -> Option<Arc<Self>> {
+        let size = images.first()?.width();
+        if size == 0 || images.iter().any(|v| v.width() != size || v.height() != size) {
+            return None
+        }
+        let this = Self {
+            size,
+            faces: images.into_iter().map(|v| v.into_raw()).collect(),
+        };
+        Some(Arc::new(this))
+    }
+
+    // horizontal cross:      vertical cross:
+    //     +Y                     +Y
+    // -X  +Z  +X  -Z         -X  +Z  +X
+    //     -Y                     -Y
+    //                            -Z (upside down)
+    fn with_cross(image: image::RgbaImage) -> Option<Arc<Self>> {
+        use image::imageops;
+        let (width, height) = image.dimensions();
+        let horizontal = width * 3 == height * 4;
+        let vertical = width * 4 == height * 3;
+        let size = if horizontal { width / 4 } else { width / 3 };
+        if !(horizontal || vertical) || size == 0 {
+            return None
+        }
+        let face = |column: u32, row: u32| {
+            imageops::crop_imm(&image, column * size, row * size, size, size).to_image()
+        };
+        let negative_z = if horizontal {
+            face(3, 1)
+        } else {
+            imageops::rotate180(&face(1, 3))
+        };
+        let faces = vec![
+            face(2, 1),
+            face(0, 1),
+            face(1, 0),
+            face(1, 2),
+            face(1, 1),
+            negative_z,
+        ];
+        Self::with_faces(faces)
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    // RGBA8 rows from top to bottom per face
+    pub fn faces(&self) -> &Vec<Vec<u8>> {
+        &self.faces
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every cell filled with its column and row
+    fn cross(columns: u32, rows: u32, size: u32) -> image::RgbaImage {
+        image::RgbaImage::from_fn(columns * size, rows * size, |x, y| {
+            image::Rgba([(x / size) as u8, (y / size) as u8, (x % size) as u8, (y % size) as u8])
+        })
+    }
+
+    fn cell(cubemap: &Cubemap, face_index: usize) -> [u8; 2] {
+        let face = &cubemap.faces()[face_index];
+        [face[0], face[1]]
+    }
+
+    #[test]
+    fn horizontal_cross_splits_into_faces() {
+        let cubemap = Cubemap::with_cross(cross(4, 3, 2)).unwrap();
+        assert_eq!(cubemap.size(), 2);
+        let cells: Vec<[u8; 2]> = (0..6).map(|v| cell(&cubemap, v)).collect();
+        assert_eq!(cells, vec![[2, 1], [0, 1], [1, 0], [1, 2], [1, 1], [3, 1]]);
+    }
+
+    #[test]
+    fn vertical_cross_turns_the_back_face() {
+        let cubemap = Cubemap::with_cross(cross(3, 4, 2)).unwrap();
+        let cells: Vec<[u8; 2]> = (0..6).map(|v| cell(&cubemap, v)).collect();
+        assert_eq!(cells, vec![[2, 1], [0, 1], [1, 0], [1, 2], [1, 1], [1, 3]]);
+        // the first texel of -Z is the last texel of its cell
+        let negative_z = &cubemap.faces()[5];
+        assert_eq!(&negative_z[2..4], &[1, 1]);
+    }
+
+    #[test]
+    fn other_aspect_ratios_are_rejected() {
+        assert!(Cubemap::with_cross(cross(2, 2, 2)).is_none());
+    }
+}
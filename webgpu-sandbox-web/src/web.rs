@@ -13,4 +13,22 @@ impl CurrentQueryParameters {
         let search = search.trim_start_matches('?');
         Some(search.into())
     }
+
+    // first entry of the query without a value, such as the preset in ?2024&normals=flat
+    pub fn name() -> Option<String> {
+        let value = Self::value()?;
+        value.split('&')
+            .find(|v| !v.is_empty() && !v.contains('='))
+            .map(Into::into)
+    }
+
+    // percent-decoded value of a key=value entry of the query
+    pub fn get(key: &str) -> Option<String> {
+        let value = Self::value()?;
+        value.split('&')
+            .filter_map(|v| v.split_once('='))
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, v)| js_sys::decode_uri_component(v).ok())
+            .map(Into::into)
+    }
 }
@@ -50,6 +50,8 @@ module.exports = {
                 { from: 'stanford_bunny.glb', context: 'models' },
                 { from: 'box.glb', context: 'models' },
                 { from: 'dragon.glb', context: 'models' },
+                { from: 'environment.hdr', context: 'models', noErrorOnMissing: true },
+                { from: 'skybox', to: 'skybox', context: 'models', noErrorOnMissing: true },
             ]
         })
     ]